@@ -0,0 +1,118 @@
+//! Aggregation: Combining the estimates of several data types into a single estimate.
+
+use serde::{Deserialize, Serialize};
+
+use crate::model::{DataType, MicroLamportPriorityFeeEstimates};
+
+/// Strategy used to combine the estimates of the global fees and of each account.
+#[derive(
+    Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+#[serde(rename_all = "camelCase")]
+pub enum Aggregation {
+    /// Highest value of each level across all data types, including the global fees.
+    #[default]
+    Max,
+    /// Highest value of each level across the accounts, ignoring the global fees.
+    MaxExcludingGlobal,
+    /// Mean of each level across the accounts, weighted by the number of fees of each account.
+    WeightedMean,
+    /// Estimates of the account with the most fees.
+    MostContended,
+}
+
+impl Aggregation {
+    /// Combines `(data type, estimates, number of fees)` entries into a single estimate.
+    ///
+    /// Strategies other than [`Aggregation::Max`] ignore the global fees, unless they are the only
+    /// entry. Returns zeros when there are no fees.
+    pub fn aggregate(
+        self,
+        entries: &[(DataType<'_>, MicroLamportPriorityFeeEstimates, usize)],
+    ) -> MicroLamportPriorityFeeEstimates {
+        let accounts: Vec<_> = entries
+            .iter()
+            .filter(|(data_type, _, count)| *data_type != DataType::Global && *count > 0)
+            .collect();
+        let selected: Vec<_> = if self == Aggregation::Max || accounts.is_empty() {
+            entries.iter().filter(|(_, _, count)| *count > 0).collect()
+        } else {
+            accounts
+        };
+
+        match self {
+            Aggregation::Max | Aggregation::MaxExcludingGlobal => selected.iter().fold(
+                MicroLamportPriorityFeeEstimates::default(),
+                |aggregate, (_, estimates, _)| aggregate.zip_with(estimates, f64::max),
+            ),
+            Aggregation::WeightedMean => {
+                let total: usize = selected.iter().map(|(_, _, count)| count).sum();
+                if total == 0 {
+                    return MicroLamportPriorityFeeEstimates::default();
+                }
+                selected
+                    .iter()
+                    .fold(
+                        MicroLamportPriorityFeeEstimates::default(),
+                        |aggregate, (_, estimates, count)| {
+                            aggregate.zip_with(estimates, |sum, value| sum + value * *count as f64)
+                        },
+                    )
+                    .map(|sum| sum / total as f64)
+            }
+            Aggregation::MostContended => selected
+                .iter()
+                .max_by(|a, b| {
+                    a.2.cmp(&b.2)
+                        .then_with(|| a.1.medium.total_cmp(&b.1.medium))
+                })
+                .map(|(_, estimates, _)| estimates.clone())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::pubkey::Pubkey;
+
+    fn estimates(medium: f64, unsafe_max: f64) -> MicroLamportPriorityFeeEstimates {
+        MicroLamportPriorityFeeEstimates {
+            medium,
+            unsafe_max,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_aggregations() {
+        let hot = Pubkey::new_unique();
+        let quiet = Pubkey::new_unique();
+        let idle = Pubkey::new_unique();
+        let entries = vec![
+            (DataType::Global, estimates(500.0, 90_000.0), 1_000),
+            (DataType::Account(&hot), estimates(300.0, 10_000.0), 30),
+            (DataType::Account(&quiet), estimates(100.0, 20_000.0), 10),
+            // accounts without fees are ignored
+            (DataType::Account(&idle), estimates(f64::NAN, f64::NAN), 0),
+        ];
+
+        let max = Aggregation::Max.aggregate(&entries);
+        assert_eq!((max.medium, max.unsafe_max), (500.0, 90_000.0));
+
+        let max = Aggregation::MaxExcludingGlobal.aggregate(&entries);
+        assert_eq!((max.medium, max.unsafe_max), (300.0, 20_000.0));
+
+        let mean = Aggregation::WeightedMean.aggregate(&entries);
+        assert_eq!((mean.medium, mean.unsafe_max), (250.0, 12_500.0));
+
+        let hottest = Aggregation::MostContended.aggregate(&entries);
+        assert_eq!((hottest.medium, hottest.unsafe_max), (300.0, 10_000.0));
+
+        // only global fees when no accounts were requested
+        let hottest = Aggregation::MostContended.aggregate(&entries[..1]);
+        assert_eq!(hottest.medium, 500.0);
+        assert_eq!(Aggregation::WeightedMean.aggregate(&[]).medium, 0.0);
+    }
+}
@@ -1,13 +1,74 @@
-//! Calculation Algorithms: v1/v2 percentile algorithms.
+//! Calculation Algorithms: v1/v2 percentile algorithms and v3 time-decayed percentiles.
 
-use crate::model::{DataType, PriorityFeesBySlot};
+use crate::aggregation::Aggregation;
+use crate::confidence::apply_min_samples;
+use crate::model::{
+    DataType, OutlierFilter, PayerDedup, PriorityFeesBySlot, SlotCommitment,
+    TransactionStatusFilter,
+};
+use crate::samples::FeeSamples;
+use solana_sdk::clock::Slot;
 use solana_sdk::pubkey::Pubkey;
-use statrs::statistics::Data;
 use std::collections::HashMap;
-use Calculations::{Calculation1, Calculation2};
+use std::time::{SystemTime, UNIX_EPOCH};
+use Calculations::{Calculation1, Calculation2, Calculation3};
 
 /// The result type for priority fee statistics.
-pub type DataStats<'a> = HashMap<DataType<'a>, Data<Vec<f64>>>;
+pub type DataStats<'a> = HashMap<DataType<'a>, FeeSamples>;
+
+/// Options shared by all calculation algorithms.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Ord, PartialOrd)]
+pub struct CalculationOptions {
+    /// Only use slots whose block time is at most this many seconds old.
+    pub lookback_seconds: Option<u32>,
+    /// Minimum commitment of the slots used; processed also includes provisional slots.
+    pub commitment: SlotCommitment,
+    /// Which transactions to sample by execution status; failed ones are excluded by default.
+    pub transaction_status: TransactionStatusFilter,
+    /// Outlier handling applied to each data type before percentiles are taken.
+    pub outlier_filter: Option<OutlierFilter>,
+    /// Blends the fee history of the given leaders into the aggregated estimates, only for
+    /// calculations where [`Calculations::supports_leader_boost`] holds.
+    pub leader_boost: Option<LeaderBoost>,
+    /// Limits the fees each payer contributes to every slot.
+    pub payer_dedup: Option<PayerDedup>,
+    /// How the estimates of the global fees and of each account are combined.
+    pub aggregation: Aggregation,
+    /// Accounts with fewer fees than this fall back to the global fees.
+    pub min_samples: Option<u32>,
+}
+
+impl CalculationOptions {
+    /// Returns the default options: confirmed slots, successful transactions and no filtering.
+    pub const fn new() -> Self {
+        Self {
+            lookback_seconds: None,
+            commitment: SlotCommitment::Confirmed,
+            transaction_status: TransactionStatusFilter::Succeeded,
+            outlier_filter: None,
+            leader_boost: None,
+            payer_dedup: None,
+            aggregation: Aggregation::Max,
+            min_samples: None,
+        }
+    }
+}
+
+impl Default for CalculationOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Favours the recorded fees of `leaders` over the slot window, see
+/// [`LeaderFeeHistory::boost`](crate::leader::LeaderFeeHistory::boost).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Ord, PartialOrd)]
+pub struct LeaderBoost {
+    /// Leaders whose slots are favoured, usually the upcoming leaders.
+    pub leaders: Vec<Pubkey>,
+    /// Weight of every recorded slot of the leaders relative to a slot of the window.
+    pub weight: u32,
+}
 
 /// Enum representing different priority fee calculation algorithms.
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Ord, PartialOrd)]
@@ -22,6 +83,8 @@ pub enum Calculations<'a> {
         include_empty_slots: bool,
         /// Lookback period in slots.
         lookback_period: &'a Option<u32>,
+        /// Additional options shared by all algorithms.
+        options: CalculationOptions,
     },
     /// Algorithm 2: Collects all transaction fees and fees for each specified account separately.
     Calculation2 {
@@ -33,6 +96,24 @@ pub enum Calculations<'a> {
         include_empty_slots: bool,
         /// Lookback period in slots.
         lookback_period: &'a Option<u32>,
+        /// Additional options shared by all algorithms.
+        options: CalculationOptions,
+    },
+    /// Algorithm 3: Like algorithm 2, but weights each slot's fees by an exponential decay of the
+    /// slot's age so that recent slots dominate the percentiles.
+    Calculation3 {
+        /// Accounts to filter fees for.
+        accounts: &'a [Pubkey],
+        /// Whether to include vote transactions.
+        include_vote: bool,
+        /// Whether to include empty slots in the calculation.
+        include_empty_slots: bool,
+        /// Lookback period in slots.
+        lookback_period: &'a Option<u32>,
+        /// Number of slots after which a slot's weight is halved.
+        half_life_slots: u32,
+        /// Additional options shared by all algorithms.
+        options: CalculationOptions,
     },
 }
 
@@ -49,6 +130,7 @@ impl<'a> Calculations<'a> {
             include_vote,
             include_empty_slots,
             lookback_period,
+            options: CalculationOptions::new(),
         }
     }
 
@@ -64,6 +146,153 @@ impl<'a> Calculations<'a> {
             include_vote,
             include_empty_slots,
             lookback_period,
+            options: CalculationOptions::new(),
+        }
+    }
+
+    /// Creates a new Calculation3 instance.
+    pub const fn new_calculation3(
+        accounts: &'a [Pubkey],
+        include_vote: bool,
+        include_empty_slots: bool,
+        lookback_period: &'a Option<u32>,
+        half_life_slots: u32,
+    ) -> Calculations<'a> {
+        Calculation3 {
+            accounts,
+            include_vote,
+            include_empty_slots,
+            lookback_period,
+            half_life_slots,
+            options: CalculationOptions::new(),
+        }
+    }
+
+    /// Replaces the shared calculation options.
+    pub fn with_options(self, options: CalculationOptions) -> Self {
+        match self {
+            Calculation1 {
+                accounts,
+                include_vote,
+                include_empty_slots,
+                lookback_period,
+                ..
+            } => Calculation1 {
+                accounts,
+                include_vote,
+                include_empty_slots,
+                lookback_period,
+                options,
+            },
+            Calculation2 {
+                accounts,
+                include_vote,
+                include_empty_slots,
+                lookback_period,
+                ..
+            } => Calculation2 {
+                accounts,
+                include_vote,
+                include_empty_slots,
+                lookback_period,
+                options,
+            },
+            Calculation3 {
+                accounts,
+                include_vote,
+                include_empty_slots,
+                lookback_period,
+                half_life_slots,
+                ..
+            } => Calculation3 {
+                accounts,
+                include_vote,
+                include_empty_slots,
+                lookback_period,
+                half_life_slots,
+                options,
+            },
+        }
+    }
+
+    /// Returns the shared calculation options.
+    pub const fn options(&self) -> &CalculationOptions {
+        match self {
+            Calculation1 { options, .. }
+            | Calculation2 { options, .. }
+            | Calculation3 { options, .. } => options,
+        }
+    }
+
+    /// Returns the accounts the calculation filters fees for.
+    pub const fn accounts(&self) -> &'a [Pubkey] {
+        match self {
+            Calculation1 { accounts, .. }
+            | Calculation2 { accounts, .. }
+            | Calculation3 { accounts, .. } => accounts,
+        }
+    }
+
+    /// Returns the lookback period in slots.
+    pub const fn lookback_period(&self) -> &'a Option<u32> {
+        match self {
+            Calculation1 {
+                lookback_period, ..
+            }
+            | Calculation2 {
+                lookback_period, ..
+            }
+            | Calculation3 {
+                lookback_period, ..
+            } => lookback_period,
+        }
+    }
+
+    /// Returns true if the calculation gathers the fees the leader fee history is made of, the
+    /// successful non-vote fees of all transactions, so that a [`LeaderBoost`] can be blended in.
+    pub const fn supports_leader_boost(&self) -> bool {
+        let (Calculation1 {
+            accounts,
+            include_vote,
+            options,
+            ..
+        }
+        | Calculation2 {
+            accounts,
+            include_vote,
+            options,
+            ..
+        }
+        | Calculation3 {
+            accounts,
+            include_vote,
+            options,
+            ..
+        }) = self;
+        accounts.is_empty()
+            && !*include_vote
+            && matches!(
+                options.transaction_status,
+                TransactionStatusFilter::Succeeded
+            )
+            && options.payer_dedup.is_none()
+    }
+
+    /// Returns whether slots without fees for an account count as a zero fee.
+    pub const fn include_empty_slots(&self) -> bool {
+        match self {
+            Calculation1 {
+                include_empty_slots,
+                ..
+            }
+            | Calculation2 {
+                include_empty_slots,
+                ..
+            }
+            | Calculation3 {
+                include_empty_slots,
+                ..
+            } => *include_empty_slots,
         }
     }
 
@@ -72,17 +301,38 @@ impl<'a> Calculations<'a> {
         &self,
         priority_fees: &PriorityFeesBySlot,
     ) -> anyhow::Result<DataStats<'a>> {
-        match self {
+        Ok(self.collect_fees(priority_fees, false)?.data)
+    }
+
+    /// Calculates priority fee estimates together with the fees of succeeded and failed
+    /// transactions, gathering all of them in a single pass over the lookback slots.
+    pub fn get_priority_fee_details(
+        &self,
+        priority_fees: &PriorityFeesBySlot,
+    ) -> anyhow::Result<StatusStats<'a>> {
+        self.collect_fees(priority_fees, true)
+    }
+
+    fn collect_fees(
+        &self,
+        priority_fees: &PriorityFeesBySlot,
+        details: bool,
+    ) -> anyhow::Result<StatusStats<'a>> {
+        let runs = FeeRun::runs(self.options(), self.include_empty_slots(), details);
+        let data = match self {
             Calculation1 {
                 accounts,
                 include_vote,
                 include_empty_slots,
                 lookback_period,
+                options,
             } => v1::get_priority_fee_estimates(
                 accounts,
                 include_vote,
                 include_empty_slots,
                 lookback_period,
+                options,
+                &runs,
                 priority_fees,
             ),
             Calculation2 {
@@ -90,23 +340,180 @@ impl<'a> Calculations<'a> {
                 include_vote,
                 include_empty_slots,
                 lookback_period,
+                options,
             } => v2::get_priority_fee_estimates(
                 accounts,
                 include_vote,
                 include_empty_slots,
                 lookback_period,
+                options,
+                &runs,
+                priority_fees,
+            ),
+            Calculation3 {
+                accounts,
+                include_vote,
+                include_empty_slots,
+                lookback_period,
+                half_life_slots,
+                options,
+            } => v3::get_priority_fee_estimates(
+                accounts,
+                include_vote,
+                include_empty_slots,
+                lookback_period,
+                half_life_slots,
+                options,
+                &runs,
                 priority_fees,
             ),
+        }?;
+        Ok(StatusStats::from_runs(data, self.options(), details))
+    }
+}
+
+/// The fees of a calculation together with the fees of each transaction status, as reported in
+/// the details of an estimate.
+#[derive(Debug, Clone, Default)]
+pub struct StatusStats<'a> {
+    /// Fees of the calculation's transaction status.
+    pub data: DataStats<'a>,
+    /// Fees of succeeded transactions, empty slots are never padded with zero fees.
+    pub succeeded: DataStats<'a>,
+    /// Fees of failed transactions, empty slots are never padded with zero fees.
+    pub failed: DataStats<'a>,
+}
+
+impl<'a> StatusStats<'a> {
+    /// Assembles the fees gathered for each run, applying the minimum samples and the outlier
+    /// filter of `options` to the calculation's fees only, so that the status fees keep counting
+    /// every transaction of their own data type.
+    ///
+    /// A status without a run of its own reuses the calculation's fees, see [`FeeRun::runs`].
+    /// When empty slots are padded, the run of the calculation's status holds the fees counted
+    /// towards the minimum samples.
+    pub fn from_runs(
+        mut runs: HashMap<FeeRun, DataStats<'a>>,
+        options: &CalculationOptions,
+        details: bool,
+    ) -> Self {
+        let data = runs.remove(&FeeRun::Estimate).unwrap_or_default();
+        let status_run = FeeRun::Status(options.transaction_status);
+        let mut status_data = (details && !runs.contains_key(&status_run)).then(|| data.clone());
+        let data = apply_outlier_filter(
+            apply_min_samples(data, runs.get(&status_run), options),
+            options,
+        );
+        let mut status_fees = |status| {
+            runs.remove(&FeeRun::Status(status))
+                .filter(|_| details)
+                .or_else(|| {
+                    (status == options.transaction_status)
+                        .then(|| status_data.take())
+                        .flatten()
+                })
+                .unwrap_or_default()
+        };
+        let succeeded = status_fees(TransactionStatusFilter::Succeeded);
+        let failed = status_fees(TransactionStatusFilter::Failed);
+        Self {
+            data,
+            succeeded,
+            failed,
         }
     }
 }
 
+/// A set of fees gathered by a calculation in its pass over the lookback slots.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FeeRun {
+    /// Fees of the calculation's transaction status, padded for empty slots when requested.
+    Estimate,
+    /// Fees of a single transaction status, never padded.
+    Status(TransactionStatusFilter),
+}
+
+impl FeeRun {
+    /// Returns the runs a calculation gathers, with the succeeded and failed fees only when
+    /// `details` is set, and the fees of the calculation's status when `options.min_samples` is
+    /// set so that padded zeros are not counted as samples. A status run is left out when it
+    /// would equal the estimate run, that is when it is the calculation's status and empty slots
+    /// are not padded.
+    pub fn runs(
+        options: &CalculationOptions,
+        include_empty_slots: bool,
+        details: bool,
+    ) -> Vec<Self> {
+        let mut statuses = Vec::new();
+        if details {
+            statuses.extend([
+                TransactionStatusFilter::Succeeded,
+                TransactionStatusFilter::Failed,
+            ]);
+        }
+        if options.min_samples.is_some() && !statuses.contains(&options.transaction_status) {
+            statuses.push(options.transaction_status);
+        }
+        let mut runs = vec![FeeRun::Estimate];
+        runs.extend(
+            statuses
+                .into_iter()
+                .filter(|status| include_empty_slots || *status != options.transaction_status)
+                .map(FeeRun::Status),
+        );
+        runs
+    }
+
+    /// Returns the transaction status whose fees the run gathers.
+    pub const fn status(self, options: &CalculationOptions) -> TransactionStatusFilter {
+        match self {
+            FeeRun::Estimate => options.transaction_status,
+            FeeRun::Status(status) => status,
+        }
+    }
+
+    /// Returns true if a slot without fees of the run's status adds a zero fee.
+    pub const fn pads_empty_slots(self, include_empty_slots: bool) -> bool {
+        matches!(self, FeeRun::Estimate) && include_empty_slots
+    }
+}
+
+/// Groups the fees gathered per run and data type into the samples of each run.
+pub fn group_runs<'a, T>(
+    data: HashMap<(FeeRun, DataType<'a>), Vec<T>>,
+    samples: impl Fn(Vec<T>) -> FeeSamples,
+) -> HashMap<FeeRun, DataStats<'a>> {
+    let mut runs: HashMap<FeeRun, DataStats<'a>> = HashMap::new();
+    for ((run, data_type), fees) in data {
+        runs.entry(run)
+            .or_default()
+            .insert(data_type, samples(fees));
+    }
+    runs
+}
+
+/// Applies the outlier filter of `options`, if any, to the fees of every data type.
+pub fn apply_outlier_filter<'a>(
+    data: DataStats<'a>,
+    options: &CalculationOptions,
+) -> DataStats<'a> {
+    match options.outlier_filter {
+        Some(filter) => data
+            .into_iter()
+            .map(|(data_type, fees)| (data_type, fees.filter_outliers(filter)))
+            .collect(),
+        None => data,
+    }
+}
+
 mod v1 {
-    use super::{calculate_lookback_size, DataStats, DataType};
+    use super::{
+        group_runs, select_lookback_slots, CalculationOptions, DataStats, DataType, FeeRun,
+    };
     use crate::model::PriorityFeesBySlot;
-    use solana_sdk::clock::Slot;
+    use crate::samples::FeeSamples;
     use solana_sdk::pubkey::Pubkey;
-    use statrs::statistics::Data;
+    use std::collections::HashMap;
 
     /// Algorithm 1: Collects all transaction fees and fees for all specified accounts.
     pub(super) fn get_priority_fee_estimates<'a>(
@@ -114,56 +521,65 @@ mod v1 {
         include_vote: &bool,
         include_empty_slots: &bool,
         lookback_period: &Option<u32>,
+        options: &CalculationOptions,
+        runs: &[FeeRun],
         priority_fees: &PriorityFeesBySlot,
-    ) -> anyhow::Result<DataStats<'a>> {
-        let mut slots_vec: Vec<Slot> = priority_fees.iter().map(|entry| entry.slot).collect();
-        slots_vec.sort();
-        slots_vec.reverse();
+    ) -> anyhow::Result<HashMap<FeeRun, DataStats<'a>>> {
+        let slots_vec = select_lookback_slots(priority_fees, lookback_period, options);
 
-        let lookback = calculate_lookback_size(lookback_period, slots_vec.len());
-
-        let mut global_fees: Vec<f64> = Vec::new();
-        let mut account_fees: Vec<f64> = Vec::new();
-        for slot in &slots_vec[..lookback] {
+        let mut data: HashMap<(FeeRun, DataType<'a>), Vec<f64>> = HashMap::new();
+        for run in runs {
+            data.entry((*run, DataType::Global)).or_default();
+            data.entry((*run, DataType::AllAccounts)).or_default();
+        }
+        for slot in &slots_vec {
             if let Some(slot_priority_fees) = priority_fees.get(slot) {
-                if *include_vote {
-                    global_fees.extend_from_slice(&slot_priority_fees.fees.vote_fees);
-                }
-                global_fees.extend_from_slice(&slot_priority_fees.fees.non_vote_fees);
+                for run in runs {
+                    let status = run.status(options);
+                    slot_priority_fees.fees.collect_into(
+                        data.entry((*run, DataType::Global)).or_default(),
+                        *include_vote,
+                        status,
+                        options.payer_dedup,
+                    );
 
-                if !accounts.is_empty() {
-                    let mut has_data = false;
-                    accounts.iter().for_each(|account| {
-                        if let Some(account_priority_fees) =
-                            slot_priority_fees.account_fees.get(account)
-                        {
-                            if *include_vote {
-                                account_fees.extend_from_slice(&account_priority_fees.vote_fees);
+                    if !accounts.is_empty() {
+                        let account_fees = data.entry((*run, DataType::AllAccounts)).or_default();
+                        let mut has_data = false;
+                        accounts.iter().for_each(|account| {
+                            if let Some(account_priority_fees) = slot_priority_fees
+                                .account_fees
+                                .get(account)
+                                .filter(|entry| entry.has_transactions(status))
+                            {
+                                account_priority_fees.collect_into(
+                                    account_fees,
+                                    *include_vote,
+                                    status,
+                                    options.payer_dedup,
+                                );
+                                has_data = true;
                             }
-                            account_fees.extend_from_slice(&account_priority_fees.non_vote_fees);
-                            has_data = true;
+                        });
+                        if !has_data && run.pads_empty_slots(*include_empty_slots) {
+                            account_fees.push(0f64);
                         }
-                    });
-                    if !has_data && *include_empty_slots {
-                        account_fees.push(0f64);
                     }
                 }
             }
         }
 
-        let mut data = DataStats::new();
-        data.insert(DataType::Global, Data::new(global_fees));
-        data.insert(DataType::AllAccounts, Data::new(account_fees));
-        Ok(data)
+        Ok(group_runs(data, FeeSamples::new))
     }
 }
 
 mod v2 {
-    use super::{calculate_lookback_size, DataStats, DataType};
+    use super::{
+        group_runs, select_lookback_slots, CalculationOptions, DataStats, DataType, FeeRun,
+    };
     use crate::model::PriorityFeesBySlot;
-    use solana_sdk::clock::Slot;
+    use crate::samples::FeeSamples;
     use solana_sdk::pubkey::Pubkey;
-    use statrs::statistics::Data;
     use std::collections::HashMap;
 
     /// Algorithm 2: Collects fees for each specified account separately.
@@ -172,48 +588,131 @@ mod v2 {
         include_vote: &bool,
         include_empty_slots: &bool,
         lookback_period: &Option<u32>,
+        options: &CalculationOptions,
+        runs: &[FeeRun],
         priority_fees: &PriorityFeesBySlot,
-    ) -> anyhow::Result<DataStats<'a>> {
-        let mut slots_vec: Vec<Slot> = priority_fees.iter().map(|entry| entry.slot).collect();
-        slots_vec.sort();
-        slots_vec.reverse();
+    ) -> anyhow::Result<HashMap<FeeRun, DataStats<'a>>> {
+        let slots_vec = select_lookback_slots(priority_fees, lookback_period, options);
+
+        let mut data: HashMap<(FeeRun, DataType<'a>), Vec<f64>> = HashMap::new();
+        for slot in &slots_vec {
+            if let Some(slot_priority_fees) = priority_fees.get(slot) {
+                for run in runs {
+                    slot_priority_fees.fees.collect_into(
+                        data.entry((*run, DataType::Global)).or_default(),
+                        *include_vote,
+                        run.status(options),
+                        options.payer_dedup,
+                    );
+                }
+
+                accounts.iter().for_each(|account| {
+                    let account_priority_fees = slot_priority_fees.account_fees.get(account);
+                    for run in runs {
+                        let status = run.status(options);
+                        let fees = data.entry((*run, DataType::Account(account))).or_default();
+                        if let Some(account_priority_fees) = account_priority_fees
+                            .as_ref()
+                            .filter(|entry| entry.has_transactions(status))
+                        {
+                            account_priority_fees.collect_into(
+                                fees,
+                                *include_vote,
+                                status,
+                                options.payer_dedup,
+                            );
+                        } else if run.pads_empty_slots(*include_empty_slots) {
+                            fees.push(0f64);
+                        }
+                    }
+                });
+            }
+        }
 
-        let lookback = calculate_lookback_size(lookback_period, slots_vec.len());
+        Ok(group_runs(data, FeeSamples::new))
+    }
+}
 
-        let mut data: HashMap<DataType<'a>, Vec<f64>> = HashMap::new();
-        for slot in &slots_vec[..lookback] {
+mod v3 {
+    use super::{
+        decay_weight, group_runs, select_lookback_slots, CalculationOptions, DataStats, DataType,
+        FeeRun,
+    };
+    use crate::model::PriorityFeesBySlot;
+    use crate::samples::FeeSamples;
+    use solana_sdk::pubkey::Pubkey;
+    use std::collections::HashMap;
+
+    /// Algorithm 3: Collects fees for each specified account separately, weighting every fee by
+    /// the age of its slot relative to the newest slot in the lookback window.
+    #[allow(clippy::too_many_arguments)]
+    pub(super) fn get_priority_fee_estimates<'a>(
+        accounts: &'a [Pubkey],
+        include_vote: &bool,
+        include_empty_slots: &bool,
+        lookback_period: &Option<u32>,
+        half_life_slots: &u32,
+        options: &CalculationOptions,
+        runs: &[FeeRun],
+        priority_fees: &PriorityFeesBySlot,
+    ) -> anyhow::Result<HashMap<FeeRun, DataStats<'a>>> {
+        let slots_vec = select_lookback_slots(priority_fees, lookback_period, options);
+        let newest_slot = slots_vec.first().copied().unwrap_or_default();
+
+        let mut data: HashMap<(FeeRun, DataType<'a>), Vec<(f64, f64)>> = HashMap::new();
+        let mut slot_fees: Vec<f64> = Vec::new();
+        for slot in &slots_vec {
             if let Some(slot_priority_fees) = priority_fees.get(slot) {
-                let fees: &mut Vec<f64> = data.entry(DataType::Global).or_default();
+                let weight = decay_weight(newest_slot.saturating_sub(*slot), *half_life_slots);
 
-                if *include_vote {
-                    fees.extend_from_slice(&slot_priority_fees.fees.vote_fees);
+                for run in runs {
+                    slot_fees.clear();
+                    slot_priority_fees.fees.collect_into(
+                        &mut slot_fees,
+                        *include_vote,
+                        run.status(options),
+                        options.payer_dedup,
+                    );
+                    data.entry((*run, DataType::Global))
+                        .or_default()
+                        .extend(slot_fees.iter().map(|fee| (*fee, weight)));
                 }
-                fees.extend_from_slice(&slot_priority_fees.fees.non_vote_fees);
 
                 accounts.iter().for_each(|account| {
-                    let fees: &mut Vec<f64> = data.entry(DataType::Account(account)).or_default();
-                    if let Some(account_priority_fees) =
-                        slot_priority_fees.account_fees.get(account)
-                    {
-                        if *include_vote {
-                            fees.extend_from_slice(&account_priority_fees.vote_fees);
+                    let account_priority_fees = slot_priority_fees.account_fees.get(account);
+                    for run in runs {
+                        let status = run.status(options);
+                        let fees = data.entry((*run, DataType::Account(account))).or_default();
+                        if let Some(account_priority_fees) = account_priority_fees
+                            .as_ref()
+                            .filter(|entry| entry.has_transactions(status))
+                        {
+                            slot_fees.clear();
+                            account_priority_fees.collect_into(
+                                &mut slot_fees,
+                                *include_vote,
+                                status,
+                                options.payer_dedup,
+                            );
+                            fees.extend(slot_fees.iter().map(|fee| (*fee, weight)));
+                        } else if run.pads_empty_slots(*include_empty_slots) {
+                            fees.push((0f64, weight));
                         }
-                        fees.extend_from_slice(&account_priority_fees.non_vote_fees);
-                    } else if *include_empty_slots {
-                        fees.push(0f64);
                     }
                 });
             }
         }
 
-        let data = data
-            .into_iter()
-            .map(|(data_type, fees)| (data_type, Data::new(fees)))
-            .collect::<DataStats>();
-        Ok(data)
+        Ok(group_runs(data, FeeSamples::weighted))
     }
 }
 
+/// Returns the weight of a slot `age_slots` slots older than the newest slot, halving every
+/// `half_life_slots` slots.
+pub fn decay_weight(age_slots: Slot, half_life_slots: u32) -> f64 {
+    0.5f64.powf(age_slots as f64 / half_life_slots.max(1) as f64)
+}
+
 fn calculate_lookback_size(pref_num_slots: &Option<u32>, max_available_slots: usize) -> usize {
     max_available_slots.min(
         pref_num_slots
@@ -222,13 +721,52 @@ fn calculate_lookback_size(pref_num_slots: &Option<u32>, max_available_slots: us
     )
 }
 
+/// Returns the slots used for a calculation, newest first.
+///
+/// Slots are limited to the newest `lookback_period` slots and, when `lookback_seconds` is set,
+/// to slots whose block time falls within that many seconds of the current wall-clock time.
+/// Slots without a known block time are skipped by the time-based window. Processed slots are
+/// only used when `commitment` is [`SlotCommitment::Processed`].
+pub fn select_lookback_slots(
+    priority_fees: &PriorityFeesBySlot,
+    lookback_period: &Option<u32>,
+    options: &CalculationOptions,
+) -> Vec<Slot> {
+    let oldest_block_time = options.lookback_seconds.map(|lookback_seconds| {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs() as i64)
+            .unwrap_or_default();
+        now - lookback_seconds as i64
+    });
+
+    let mut slots_vec: Vec<Slot> = priority_fees
+        .iter()
+        .filter(|entry| entry.commitment >= options.commitment)
+        .filter(|entry| match oldest_block_time {
+            Some(oldest_block_time) => entry
+                .block
+                .as_ref()
+                .and_then(|block| block.block_time)
+                .is_some_and(|block_time| block_time >= oldest_block_time),
+            None => true,
+        })
+        .map(|entry| entry.slot)
+        .collect();
+    slots_vec.sort();
+    slots_vec.reverse();
+
+    let lookback = calculate_lookback_size(lookback_period, slots_vec.len());
+    slots_vec.truncate(lookback);
+    slots_vec
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::calculation::DataType::{Account, AllAccounts, Global};
     use crate::model::{Fees, SlotPriorityFees};
     use solana_sdk::clock::Slot;
-    use statrs::statistics::OrderStatistics;
 
     #[test]
     fn test_specific_fee_estimates_for_global_accounts_only() {
@@ -257,6 +795,171 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_time_decayed_fee_estimates() {
+        let tracker = PriorityFeesBySlot::default();
+        let account = Pubkey::new_unique();
+
+        // ten old slots paying 1000 followed by a single recent slot paying 10
+        for slot in 1..=10 {
+            push_priority_fee_for_txn(slot, vec![account], 1_000, false, &tracker);
+        }
+        push_priority_fee_for_txn(30, vec![account], 10, false, &tracker);
+
+        let accounts = vec![account];
+        let unweighted = Calculations::new_calculation2(&accounts, false, false, &None);
+        let mut estimates = unweighted
+            .get_priority_fee_estimates(&tracker)
+            .expect("estimates to be valid");
+        assert_eq!(
+            estimates
+                .get_mut(&Account(&account))
+                .unwrap()
+                .percentile(50),
+            1_000.0
+        );
+
+        // with a half-life of 2 slots the old slots weigh less than 1/1000 of the newest one
+        let decayed = Calculations::new_calculation3(&accounts, false, false, &None, 2);
+        let mut estimates = decayed
+            .get_priority_fee_estimates(&tracker)
+            .expect("estimates to be valid");
+        let stats = estimates.get_mut(&Account(&account)).unwrap();
+        assert_eq!(stats.len(), 11);
+        assert_eq!(stats.percentile(50), 10.0);
+        assert_eq!(stats.percentile(100), 1_000.0);
+        let stats = estimates.get_mut(&Global).unwrap();
+        assert_eq!(stats.percentile(95), 10.0);
+
+        assert_eq!(decay_weight(0, 4), 1.0);
+        assert_eq!(decay_weight(4, 4), 0.5);
+        assert_eq!(decay_weight(8, 4), 0.25);
+    }
+
+    #[test]
+    fn test_supports_leader_boost() {
+        let no_accounts: Vec<Pubkey> = vec![];
+        let accounts = vec![Pubkey::new_unique()];
+        assert!(
+            Calculations::new_calculation2(&no_accounts, false, true, &None)
+                .supports_leader_boost()
+        );
+        assert!(
+            Calculations::new_calculation3(&no_accounts, false, false, &None, 4)
+                .supports_leader_boost()
+        );
+        assert!(
+            !Calculations::new_calculation1(&accounts, false, false, &None).supports_leader_boost()
+        );
+        assert!(
+            !Calculations::new_calculation2(&no_accounts, true, false, &None)
+                .supports_leader_boost()
+        );
+
+        let options = CalculationOptions {
+            transaction_status: TransactionStatusFilter::All,
+            ..Default::default()
+        };
+        assert!(
+            !Calculations::new_calculation2(&no_accounts, false, false, &None)
+                .with_options(options)
+                .supports_leader_boost()
+        );
+        let options = CalculationOptions {
+            payer_dedup: Some(PayerDedup::Cap(1)),
+            ..Default::default()
+        };
+        assert!(
+            !Calculations::new_calculation2(&no_accounts, false, false, &None)
+                .with_options(options)
+                .supports_leader_boost()
+        );
+    }
+
+    #[test]
+    fn test_min_samples_with_all_accounts() {
+        let tracker = PriorityFeesBySlot::default();
+        let quiet = Pubkey::new_unique();
+        let other = Pubkey::new_unique();
+
+        for _ in 0..20 {
+            push_priority_fee_for_txn(1, vec![other], 10_000, false, &tracker);
+        }
+        push_priority_fee_for_txn(1, vec![quiet], 100, false, &tracker);
+
+        let accounts = vec![quiet];
+        let calc = Calculations::new_calculation1(&accounts, false, false, &None);
+        let estimates = calc
+            .get_priority_fee_estimates(&tracker)
+            .expect("estimates to be valid");
+        assert_eq!(estimates[&AllAccounts].len(), 1);
+
+        let calc = calc.with_options(CalculationOptions {
+            min_samples: Some(10),
+            ..Default::default()
+        });
+        let mut estimates = calc
+            .get_priority_fee_estimates(&tracker)
+            .expect("estimates to be valid");
+        let stats = estimates.get_mut(&AllAccounts).unwrap();
+        assert!(stats.is_fallback());
+        assert_eq!(stats.len(), 21);
+        assert_eq!(stats.percentile(50), 10_000.0);
+        assert!(!estimates[&Global].is_fallback());
+    }
+
+    #[test]
+    fn test_min_samples_with_empty_slots() {
+        let tracker = PriorityFeesBySlot::default();
+        let quiet = Pubkey::new_unique();
+        let other = Pubkey::new_unique();
+
+        push_priority_fee_for_txn(1, vec![quiet], 100, false, &tracker);
+        for slot in 2..=20 {
+            push_priority_fee_for_txn(slot, vec![other], 10_000, false, &tracker);
+        }
+
+        let accounts = vec![quiet];
+        let options = CalculationOptions {
+            min_samples: Some(10),
+            ..Default::default()
+        };
+        // the zeros padded for the 19 empty slots are not samples of the account
+        for calc in [
+            Calculations::new_calculation1(&accounts, false, true, &None),
+            Calculations::new_calculation2(&accounts, false, true, &None),
+            Calculations::new_calculation3(&accounts, false, true, &None, 5),
+        ] {
+            let calc = calc.with_options(options.clone());
+            let estimates = calc
+                .get_priority_fee_estimates(&tracker)
+                .expect("estimates to be valid");
+            let data_type = match calc {
+                Calculation1 { .. } => AllAccounts,
+                _ => Account(&quiet),
+            };
+            assert!(estimates[&data_type].is_fallback(), "{calc:?}");
+
+            let stats = calc
+                .get_priority_fee_details(&tracker)
+                .expect("details to be valid");
+            assert!(stats.data[&data_type].is_fallback());
+            assert_eq!(stats.succeeded[&data_type].len(), 1);
+        }
+
+        // enough real transactions keep the padded fees of the account
+        for _ in 0..10 {
+            push_priority_fee_for_txn(1, vec![quiet], 100, false, &tracker);
+        }
+        let calc =
+            Calculations::new_calculation2(&accounts, false, true, &None).with_options(options);
+        let estimates = calc
+            .get_priority_fee_estimates(&tracker)
+            .expect("estimates to be valid");
+        assert!(!estimates[&Account(&quiet)].is_fallback());
+        assert_eq!(estimates[&Account(&quiet)].len(), 30);
+    }
+
     fn push_priority_fee_for_txn(
         slot: Slot,
         accounts: Vec<Pubkey>,
@@ -0,0 +1,258 @@
+//! Confidence Intervals: Bootstrap intervals of percentile estimates and minimum sample fallback.
+
+use std::collections::HashMap;
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use crate::calculation::{CalculationOptions, DataStats};
+use crate::model::{ConfidenceInterval, DataType, MicroLamportPriorityFeeEstimates};
+use crate::samples::FeeSamples;
+
+/// Number of bootstrap resamples taken for an interval when the budget allows.
+pub const BOOTSTRAP_RESAMPLES: usize = 200;
+
+/// Fewest resamples an interval is taken from, data types the budget cannot cover get none.
+pub const MIN_BOOTSTRAP_RESAMPLES: usize = 20;
+
+/// Confidence level of the bootstrap intervals.
+pub const CONFIDENCE_LEVEL: f64 = 0.95;
+
+/// Largest resample drawn for an interval. Bigger samples are resampled to this size, which keeps
+/// the cost bounded and makes their intervals slightly wider than a full bootstrap would; the
+/// size used is reported as [`ConfidenceInterval::resample_size`].
+pub const MAX_BOOTSTRAP_SAMPLES: usize = 5_000;
+
+/// Fees drawn across all the resamples of all the intervals of a request, so that requests with
+/// many accounts cost no more than a single full size interval.
+pub const BOOTSTRAP_SAMPLE_BUDGET: usize = BOOTSTRAP_RESAMPLES * MAX_BOOTSTRAP_SAMPLES;
+
+/// Percentiles of the estimate levels, in the order of [`MicroLamportPriorityFeeEstimates`].
+const LEVELS: [usize; 6] = [0, 25, 50, 75, 95, 100];
+
+/// Returns the bootstrap interval of every data type of `data` that has fees.
+///
+/// Every interval resamples its whole sample. The [`BOOTSTRAP_SAMPLE_BUDGET`] is shared out as
+/// resamples from the smallest sample up, so that small samples get all of theirs and the largest
+/// ones split what is left. Data types left with fewer than [`MIN_BOOTSTRAP_RESAMPLES`] resamples
+/// get no interval.
+pub fn bootstrap_intervals<'a>(data: &DataStats<'a>) -> HashMap<DataType<'a>, ConfidenceInterval> {
+    let mut entries: Vec<_> = data.iter().filter(|(_, fees)| !fees.is_empty()).collect();
+    entries.sort_by_key(|(_, fees)| fees.len());
+
+    let mut budget = BOOTSTRAP_SAMPLE_BUDGET;
+    let mut intervals = HashMap::with_capacity(entries.len());
+    for (i, (data_type, fees)) in entries.iter().enumerate() {
+        let len = fees.len().min(MAX_BOOTSTRAP_SAMPLES);
+        let resamples = (budget / (entries.len() - i) / len).min(BOOTSTRAP_RESAMPLES);
+        if resamples < MIN_BOOTSTRAP_RESAMPLES {
+            continue;
+        }
+        budget = budget.saturating_sub(resamples * len);
+        if let Some(interval) = bootstrap_interval(fees, resamples) {
+            intervals.insert((*data_type).clone(), interval);
+        }
+    }
+    intervals
+}
+
+/// Returns the bootstrap percentile interval of each estimate level of `fees` over `resamples`
+/// resamples, or `None` when there are no fees.
+///
+/// Resampling is seeded from the number of fees so that repeated requests over the same window
+/// return the same interval.
+pub fn bootstrap_interval(fees: &FeeSamples, resamples: usize) -> Option<ConfidenceInterval> {
+    if fees.is_empty() || resamples == 0 {
+        return None;
+    }
+    let mut rng = StdRng::seed_from_u64(fees.len() as u64);
+    let len = fees.len().min(MAX_BOOTSTRAP_SAMPLES);
+
+    let mut levels: [Vec<f64>; 6] = Default::default();
+    for _ in 0..resamples {
+        let mut resample = fees.resample(&mut rng, len);
+        for (values, percentile) in levels.iter_mut().zip(LEVELS) {
+            values.push(resample.percentile(percentile));
+        }
+    }
+
+    let tail = (1.0 - CONFIDENCE_LEVEL) / 2.0;
+    let bound = |values: &[f64], quantile: f64| -> f64 {
+        let index = (quantile * (values.len() - 1) as f64).round() as usize;
+        values[index]
+    };
+    let mut lower = [0.0; 6];
+    let mut upper = [0.0; 6];
+    for (i, values) in levels.iter_mut().enumerate() {
+        values.sort_by(f64::total_cmp);
+        lower[i] = bound(values, tail);
+        upper[i] = bound(values, 1.0 - tail);
+    }
+    Some(ConfidenceInterval {
+        lower: estimates_from(lower),
+        upper: estimates_from(upper),
+        resample_size: len,
+        resamples,
+    })
+}
+
+const fn estimates_from(levels: [f64; 6]) -> MicroLamportPriorityFeeEstimates {
+    let [min, low, medium, high, very_high, unsafe_max] = levels;
+    MicroLamportPriorityFeeEstimates {
+        min,
+        low,
+        medium,
+        high,
+        very_high,
+        unsafe_max,
+    }
+}
+
+/// Replaces the fees of every account, and of the accounts combined by the v1 algorithm, with
+/// fewer than `options.min_samples` fees by the global fees, marked as a fallback.
+///
+/// Fees are counted in `counted` when given, the fees of the same data types without the zeros
+/// padded for empty slots, so that only real transactions count as samples.
+///
+/// Program-level fees are not tracked, so the global fees are the only broader data available.
+/// Accounts are left untouched when there are no global fees.
+pub fn apply_min_samples<'a>(
+    mut data: DataStats<'a>,
+    counted: Option<&DataStats<'a>>,
+    options: &CalculationOptions,
+) -> DataStats<'a> {
+    let Some(min_samples) = options.min_samples else {
+        return data;
+    };
+    let Some(global) = data.get(&DataType::Global).filter(|fees| !fees.is_empty()) else {
+        return data;
+    };
+    let fallback = global.clone().into_fallback();
+    for (data_type, fees) in data.iter_mut() {
+        let count = match counted {
+            Some(counted) => counted.get(data_type).map_or(0, FeeSamples::len),
+            None => fees.len(),
+        };
+        if matches!(data_type, DataType::Account(_) | DataType::AllAccounts)
+            && count < min_samples as usize
+        {
+            *fees = fallback.clone();
+        }
+    }
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::pubkey::Pubkey;
+
+    #[test]
+    fn test_bootstrap_interval() {
+        let fees: Vec<f64> = (1..=1_000).map(|i| i as f64).collect();
+        let mut samples = FeeSamples::new(fees);
+        let interval = bootstrap_interval(&samples, BOOTSTRAP_RESAMPLES).expect("fees were added");
+        let medium = samples.percentile(50);
+        assert!(interval.lower.medium <= medium && medium <= interval.upper.medium);
+        assert!(interval.upper.medium - interval.lower.medium < 100.0);
+        assert!(interval.lower.min <= interval.upper.min);
+        assert_eq!(interval.resample_size, 1_000);
+        assert_eq!(interval.resamples, BOOTSTRAP_RESAMPLES);
+
+        // fewer fees give a wider interval
+        let few = FeeSamples::new(vec![100.0, 500.0, 900.0]);
+        let wide = bootstrap_interval(&few, BOOTSTRAP_RESAMPLES).expect("fees were added");
+        assert!(wide.upper.medium - wide.lower.medium >= 400.0);
+
+        // the same fees give the same interval
+        let again = bootstrap_interval(&few, BOOTSTRAP_RESAMPLES).expect("fees were added");
+        assert_eq!(wide.lower.medium, again.lower.medium);
+        assert!(bootstrap_interval(&FeeSamples::new(vec![]), BOOTSTRAP_RESAMPLES).is_none());
+
+        // bigger samples are resampled to the largest resample size
+        let many = FeeSamples::new(vec![1.0; MAX_BOOTSTRAP_SAMPLES + 1]);
+        let interval = bootstrap_interval(&many, 1).expect("fees were added");
+        assert_eq!(interval.resample_size, MAX_BOOTSTRAP_SAMPLES);
+    }
+
+    #[test]
+    fn test_bootstrap_intervals_share_budget() {
+        let accounts: Vec<Pubkey> = (0..500).map(|_| Pubkey::new_unique()).collect();
+        let busy: Vec<f64> = (1..=1_000).map(|i| i as f64).collect();
+        let few = vec![100.0, 500.0, 900.0];
+        let mut data = DataStats::new();
+        data.insert(DataType::Global, FeeSamples::new(few.clone()));
+        for account in &accounts {
+            data.insert(DataType::Account(account), FeeSamples::new(busy.clone()));
+        }
+        data.insert(DataType::AllAccounts, FeeSamples::new(vec![]));
+
+        let intervals = bootstrap_intervals(&data);
+        assert!(!intervals.contains_key(&DataType::AllAccounts));
+
+        // the small sample gets all of its resamples
+        let full = bootstrap_interval(&FeeSamples::new(few), BOOTSTRAP_RESAMPLES).unwrap();
+        assert_eq!(intervals[&DataType::Global].lower.medium, full.lower.medium);
+        assert_eq!(intervals[&DataType::Global].upper.medium, full.upper.medium);
+        assert_eq!(intervals[&DataType::Global].resamples, BOOTSTRAP_RESAMPLES);
+
+        // the busy ones resample their whole sample, but with fewer resamples, until the budget
+        // runs out
+        let busy_intervals: Vec<_> = accounts
+            .iter()
+            .filter_map(|account| intervals.get(&DataType::Account(account)))
+            .collect();
+        assert_eq!(
+            busy_intervals.len(),
+            (BOOTSTRAP_SAMPLE_BUDGET - 3 * BOOTSTRAP_RESAMPLES) / 1_000 / MIN_BOOTSTRAP_RESAMPLES
+        );
+        for interval in busy_intervals {
+            assert_eq!(interval.resample_size, 1_000);
+            assert!(interval.resamples >= MIN_BOOTSTRAP_RESAMPLES);
+        }
+
+        // fewer busy accounts share the budget evenly
+        let mut data = DataStats::new();
+        for account in &accounts[..10] {
+            data.insert(DataType::Account(account), FeeSamples::new(busy.clone()));
+        }
+        let intervals = bootstrap_intervals(&data);
+        assert_eq!(intervals.len(), 10);
+        assert!(intervals.values().all(|interval| interval.resamples == 100));
+    }
+
+    #[test]
+    fn test_min_samples_fallback() {
+        let busy = Pubkey::new_unique();
+        let quiet = Pubkey::new_unique();
+        let mut data = DataStats::new();
+        data.insert(DataType::Global, FeeSamples::new(vec![10.0; 100]));
+        data.insert(DataType::AllAccounts, FeeSamples::new(vec![1.0; 53]));
+        data.insert(DataType::Account(&busy), FeeSamples::new(vec![1.0; 50]));
+        data.insert(DataType::Account(&quiet), FeeSamples::new(vec![1.0; 3]));
+
+        let unchanged = apply_min_samples(data.clone(), None, &CalculationOptions::default());
+        assert_eq!(unchanged[&DataType::Account(&quiet)].len(), 3);
+
+        let options = CalculationOptions {
+            min_samples: Some(10),
+            ..Default::default()
+        };
+        let padded = data.clone();
+        let data = apply_min_samples(data, None, &options);
+        let quiet_fees = &data[&DataType::Account(&quiet)];
+        assert!(quiet_fees.is_fallback());
+        assert_eq!(quiet_fees.len(), 100);
+        assert!(!data[&DataType::Account(&busy)].is_fallback());
+        assert!(!data[&DataType::AllAccounts].is_fallback());
+        assert!(!data[&DataType::Global].is_fallback());
+
+        // only the counted fees are compared with the minimum
+        let mut counted = DataStats::new();
+        counted.insert(DataType::Account(&busy), FeeSamples::new(vec![1.0; 5]));
+        let data = apply_min_samples(padded, Some(&counted), &options);
+        assert!(data[&DataType::Account(&busy)].is_fallback());
+        assert!(data[&DataType::Account(&quiet)].is_fallback());
+        assert!(data[&DataType::AllAccounts].is_fallback());
+    }
+}
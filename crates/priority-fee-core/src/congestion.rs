@@ -0,0 +1,124 @@
+//! Congestion: Block and account utilization of the compute unit limits.
+
+use std::collections::HashMap;
+
+use solana_sdk::pubkey::Pubkey;
+
+use crate::calculation::{select_lookback_slots, CalculationOptions};
+use crate::model::{DataType, PriorityFeesBySlot, SlotPriorityFees};
+
+/// Compute unit limit of a block.
+pub const MAX_BLOCK_UNITS: u64 = 60_000_000;
+
+/// Compute unit limit of the transactions write-locking a single account within a block.
+pub const MAX_WRITABLE_ACCOUNT_UNITS: u64 = 12_000_000;
+
+/// Returns the share of [`MAX_BLOCK_UNITS`] consumed by the slot's block, capped at 1.
+pub fn block_utilization(slot_fees: &SlotPriorityFees) -> f64 {
+    utilization(slot_fees.block_compute_units(), MAX_BLOCK_UNITS)
+}
+
+/// Returns the share of [`MAX_WRITABLE_ACCOUNT_UNITS`] consumed by the slot's transactions writing
+/// to `account`, capped at 1.
+pub fn account_utilization(slot_fees: &SlotPriorityFees, account: &Pubkey) -> f64 {
+    let compute_units = slot_fees
+        .account_compute_units
+        .get(account)
+        .map_or(0, |compute_units| *compute_units);
+    utilization(compute_units, MAX_WRITABLE_ACCOUNT_UNITS)
+}
+
+fn utilization(compute_units: u64, limit: u64) -> f64 {
+    (compute_units as f64 / limit as f64).min(1.0)
+}
+
+/// Returns the congestion score of the global fees and of each account over the lookback window.
+///
+/// The score of the global fees is the mean block utilization, the score of each account its mean
+/// utilization, and the score of all accounts the highest account score. Scores are 0 when no
+/// slots are in the window.
+pub fn congestion_scores<'a>(
+    priority_fees: &PriorityFeesBySlot,
+    accounts: &'a [Pubkey],
+    lookback_period: &Option<u32>,
+    options: &CalculationOptions,
+) -> HashMap<DataType<'a>, f64> {
+    let mut block = 0.0;
+    let mut account_scores = vec![0.0; accounts.len()];
+    let mut slot_count = 0;
+    for slot in select_lookback_slots(priority_fees, lookback_period, options) {
+        let Some(slot_fees) = priority_fees.get(&slot) else {
+            continue;
+        };
+        slot_count += 1;
+        block += block_utilization(&slot_fees);
+        for (account, score) in accounts.iter().zip(account_scores.iter_mut()) {
+            *score += account_utilization(&slot_fees, account);
+        }
+    }
+
+    let mean = |total: f64| {
+        if slot_count == 0 {
+            0.0
+        } else {
+            total / slot_count as f64
+        }
+    };
+    let mut scores = HashMap::with_capacity(accounts.len() + 2);
+    scores.insert(DataType::Global, mean(block));
+    let mut all_accounts: f64 = 0.0;
+    for (account, score) in accounts.iter().zip(account_scores) {
+        let score = mean(score);
+        all_accounts = all_accounts.max(score);
+        scores.insert(DataType::Account(account), score);
+    }
+    scores.insert(DataType::AllAccounts, all_accounts);
+    scores
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::BlockMetadata;
+
+    #[test]
+    fn test_congestion_scores() {
+        let priority_fees = PriorityFeesBySlot::default();
+        let hot = Pubkey::new_unique();
+        let cold = Pubkey::new_unique();
+
+        let mut full = SlotPriorityFees::empty(1);
+        full.add_compute_units(vec![hot], 12_000_000);
+        full.add_compute_units(vec![cold], 3_000_000);
+        // the block metadata counts every transaction of the block
+        full.block = Some(BlockMetadata {
+            compute_units_consumed: 45_000_000,
+            ..Default::default()
+        });
+        priority_fees.insert(1, full);
+
+        let mut quiet = SlotPriorityFees::empty(2);
+        quiet.add_compute_units(vec![hot, cold], 3_000_000);
+        priority_fees.insert(2, quiet);
+
+        let accounts = [hot, cold];
+        let scores = congestion_scores(
+            &priority_fees,
+            &accounts,
+            &None,
+            &CalculationOptions::default(),
+        );
+        assert!((scores[&DataType::Global] - 0.4).abs() < 1e-9);
+        assert_eq!(scores[&DataType::Account(&hot)], 0.625);
+        assert_eq!(scores[&DataType::Account(&cold)], 0.25);
+        assert_eq!(scores[&DataType::AllAccounts], 0.625);
+
+        let slot_fees = priority_fees.get(&2).unwrap();
+        assert_eq!(block_utilization(&slot_fees), 0.05);
+        assert_eq!(slot_fees.summary().block_utilization, 0.05);
+
+        let empty = PriorityFeesBySlot::default();
+        let scores = congestion_scores(&empty, &accounts, &None, &CalculationOptions::default());
+        assert_eq!(scores[&DataType::Global], 0.0);
+    }
+}
@@ -0,0 +1,192 @@
+//! Fee Forecasting: Short-horizon forecasts of per-slot fee percentiles.
+
+use crate::calculation::{select_lookback_slots, CalculationOptions};
+use crate::model::{PriorityFeesBySlot, SlotFeeForecast};
+use crate::samples::FeeSamples;
+use solana_sdk::clock::Slot;
+use solana_sdk::pubkey::Pubkey;
+
+/// Maximum number of slots ahead that can be forecast.
+pub const MAX_FORECAST_SLOTS: u32 = 10;
+
+/// Holt's linear trend (double exponential smoothing) model.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HoltLinear {
+    /// Smoothing factor of the level, between 0 and 1.
+    pub alpha: f64,
+    /// Smoothing factor of the trend, between 0 and 1.
+    pub beta: f64,
+}
+
+impl Default for HoltLinear {
+    fn default() -> Self {
+        Self {
+            alpha: 0.5,
+            beta: 0.3,
+        }
+    }
+}
+
+impl HoltLinear {
+    /// Fits the model to `series`, oldest value first, and predicts the next `horizon` values.
+    ///
+    /// Returns an empty vector for an empty series. A single value is forecast as flat.
+    pub fn forecast(&self, series: &[f64], horizon: usize) -> Vec<f64> {
+        let Some(&first) = series.first() else {
+            return vec![];
+        };
+        let mut level = first;
+        let mut trend = series.get(1).map_or(0.0, |second| second - first);
+        for value in series.iter().skip(1) {
+            let previous_level = level;
+            level = self.alpha * value + (1.0 - self.alpha) * (level + trend);
+            trend = self.beta * (level - previous_level) + (1.0 - self.beta) * trend;
+        }
+        (1..=horizon)
+            .map(|slots_ahead| level + slots_ahead as f64 * trend)
+            .collect()
+    }
+}
+
+/// Returns the median, 75th and 95th percentile fee of every slot in the lookback window that has
+/// fees, oldest slot first.
+///
+/// Fees are taken from the given accounts combined, or from all transactions when `accounts` is
+/// empty.
+pub fn slot_percentile_series(
+    priority_fees: &PriorityFeesBySlot,
+    accounts: &[Pubkey],
+    include_vote: bool,
+    lookback_period: &Option<u32>,
+    options: &CalculationOptions,
+) -> Vec<(Slot, [f64; 3])> {
+    let mut slots_vec = select_lookback_slots(priority_fees, lookback_period, options);
+    slots_vec.reverse();
+
+    let mut series = Vec::with_capacity(slots_vec.len());
+    for slot in slots_vec {
+        let Some(slot_priority_fees) = priority_fees.get(&slot) else {
+            continue;
+        };
+        let mut fees = Vec::new();
+        if accounts.is_empty() {
+            slot_priority_fees.fees.collect_into(
+                &mut fees,
+                include_vote,
+                options.transaction_status,
+                options.payer_dedup,
+            );
+        } else {
+            for account in accounts {
+                if let Some(account_fees) = slot_priority_fees.account_fees.get(account) {
+                    account_fees.collect_into(
+                        &mut fees,
+                        include_vote,
+                        options.transaction_status,
+                        options.payer_dedup,
+                    );
+                }
+            }
+        }
+        if fees.is_empty() {
+            continue;
+        }
+        let mut samples = FeeSamples::new(fees);
+        series.push((
+            slot,
+            [
+                samples.percentile(50),
+                samples.percentile(75),
+                samples.percentile(95),
+            ],
+        ));
+    }
+    series
+}
+
+/// Forecasts the median, 75th and 95th percentile fee for each of the next `horizon` blocks with
+/// fees, labelled by their step ahead of the newest one.
+///
+/// Forecasts are never negative. Returns an empty vector when the window holds no fees.
+pub fn forecast_fees(
+    priority_fees: &PriorityFeesBySlot,
+    accounts: &[Pubkey],
+    include_vote: bool,
+    lookback_period: &Option<u32>,
+    options: &CalculationOptions,
+    horizon: u32,
+) -> Vec<SlotFeeForecast> {
+    let series = slot_percentile_series(
+        priority_fees,
+        accounts,
+        include_vote,
+        lookback_period,
+        options,
+    );
+    if series.is_empty() {
+        return vec![];
+    }
+
+    let model = HoltLinear::default();
+    let horizon = horizon.min(MAX_FORECAST_SLOTS) as usize;
+    let [medium, high, very_high] = [0, 1, 2].map(|index| {
+        let values: Vec<f64> = series.iter().map(|(_, values)| values[index]).collect();
+        model.forecast(&values, horizon)
+    });
+
+    (0..horizon)
+        .map(|index| SlotFeeForecast {
+            step: index as u32 + 1,
+            medium: medium[index].max(0.0).round(),
+            high: high[index].max(0.0).round(),
+            very_high: very_high[index].max(0.0).round(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::SlotPriorityFees;
+
+    #[test]
+    fn test_holt_linear_forecast() {
+        let model = HoltLinear::default();
+        assert!(model.forecast(&[], 3).is_empty());
+        assert_eq!(model.forecast(&[5.0], 2), vec![5.0, 5.0]);
+
+        // a perfect line is extrapolated exactly
+        let series: Vec<f64> = (0..20).map(|i| 100.0 + 10.0 * i as f64).collect();
+        let forecast = model.forecast(&series, 3);
+        for (index, value) in forecast.iter().enumerate() {
+            let expected = 100.0 + 10.0 * (20 + index) as f64;
+            assert!((value - expected).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_forecast_fees() {
+        let priority_fees = PriorityFeesBySlot::default();
+        let account = Pubkey::new_unique();
+        for slot in 1..=10 {
+            priority_fees.insert(
+                slot,
+                SlotPriorityFees::new(slot, vec![account], slot * 1_000, false),
+            );
+        }
+
+        let options = CalculationOptions::default();
+        let forecast = forecast_fees(&priority_fees, &[account], false, &None, &options, 3);
+        let steps: Vec<u32> = forecast.iter().map(|forecast| forecast.step).collect();
+        assert_eq!(steps, vec![1, 2, 3]);
+        assert_eq!(forecast[0].medium, 11_000.0);
+        assert_eq!(forecast[2].very_high, 13_000.0);
+
+        // capped to the maximum horizon
+        let forecast = forecast_fees(&priority_fees, &[], false, &None, &options, 50);
+        assert_eq!(forecast.len(), MAX_FORECAST_SLOTS as usize);
+
+        let other = Pubkey::new_unique();
+        assert!(forecast_fees(&priority_fees, &[other], false, &None, &options, 3).is_empty());
+    }
+}
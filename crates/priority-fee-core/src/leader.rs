@@ -0,0 +1,227 @@
+//! Leader Tracking: Leader schedule lookup and per-leader fee history.
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use solana_sdk::clock::Slot;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::calculation::LeaderBoost;
+use crate::hash::DashMap;
+use crate::model::{Fees, LeaderSlotFees, MicroLamportPriorityFeeEstimates};
+use crate::samples::FeeSamples;
+
+/// Number of upcoming slots whose leaders are favoured by leader-aware estimation.
+///
+/// Leaders produce four consecutive slots, so this covers the current and the next leader.
+pub const UPCOMING_LEADER_SLOTS: u64 = 8;
+
+/// Number of most recent slots kept in the fee history of each leader.
+pub const LEADER_HISTORY_SLOTS: usize = 32;
+
+/// Source of slot leaders.
+pub trait LeaderSchedule: Debug + Send + Sync {
+    /// Returns the leader of `slot`, if known.
+    fn slot_leader(&self, slot: Slot) -> Option<Pubkey>;
+
+    /// Returns the distinct leaders of the `count` slots following `slot`, in slot order.
+    fn upcoming_leaders(&self, slot: Slot, count: u64) -> Vec<Pubkey> {
+        let mut leaders: Vec<Pubkey> = Vec::new();
+        for upcoming_slot in slot + 1..=slot + count {
+            if let Some(leader) = self.slot_leader(upcoming_slot) {
+                if !leaders.contains(&leader) {
+                    leaders.push(leader);
+                }
+            }
+        }
+        leaders
+    }
+}
+
+/// A fixed leader schedule, mainly useful for tests.
+impl LeaderSchedule for HashMap<Slot, Pubkey> {
+    fn slot_leader(&self, slot: Slot) -> Option<Pubkey> {
+        self.get(&slot).copied()
+    }
+}
+
+/// Fee statistics of the most recent slots produced by each leader.
+///
+/// The history outlives the slot window, so that leaders that produced no slot in the window
+/// still have fee data.
+#[derive(Debug, Clone, Default)]
+pub struct LeaderFeeHistory {
+    history: Arc<DashMap<Pubkey, VecDeque<LeaderSlotFees>>>,
+}
+
+impl LeaderFeeHistory {
+    /// Records the fees of a slot produced by `leader`, replacing a previous record of the slot.
+    pub fn record(&self, leader: Pubkey, slot_fees: LeaderSlotFees) {
+        let mut history = self.history.entry(leader).or_default();
+        history.retain(|recorded| recorded.slot != slot_fees.slot);
+        let position = history.partition_point(|recorded| recorded.slot < slot_fees.slot);
+        history.insert(position, slot_fees);
+        while history.len() > LEADER_HISTORY_SLOTS {
+            history.pop_front();
+        }
+    }
+
+    /// Returns the recorded slots of `leader`, oldest first.
+    pub fn history(&self, leader: &Pubkey) -> Vec<LeaderSlotFees> {
+        self.history
+            .get(leader)
+            .map(|history| history.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Blends `estimates`, taken over `window_slots` slots, with the recorded slots of the leaders
+    /// of `boost`, every recorded slot with fees counting `boost.weight` times as much as a slot of
+    /// the window.
+    ///
+    /// The history outlives the slot window, so upcoming leaders without a slot in the window
+    /// still move the estimates. Returns `estimates` unchanged when none of the leaders has
+    /// recorded fees.
+    pub fn boost(
+        &self,
+        estimates: MicroLamportPriorityFeeEstimates,
+        window_slots: usize,
+        boost: &LeaderBoost,
+    ) -> MicroLamportPriorityFeeEstimates {
+        let weight = boost.weight as f64;
+        let mut sum = estimates.clone().map(|value| value * window_slots as f64);
+        let mut recorded_slots = 0;
+        for leader in &boost.leaders {
+            let Some(history) = self.history.get(leader) else {
+                continue;
+            };
+            for recorded in history.iter().filter(|slot| slot.transaction_count > 0) {
+                sum = sum.zip_with(&recorded.estimates, |sum, value| sum + value * weight);
+                recorded_slots += 1;
+            }
+        }
+        if recorded_slots == 0 {
+            return estimates;
+        }
+        let total_weight = window_slots as f64 + recorded_slots as f64 * weight;
+        sum.map(|sum| sum / total_weight)
+    }
+
+    /// Returns the number of leaders with recorded slots.
+    pub fn len(&self) -> usize {
+        self.history.len()
+    }
+
+    /// Returns true if no slots were recorded.
+    pub fn is_empty(&self) -> bool {
+        self.history.is_empty()
+    }
+}
+
+impl LeaderSlotFees {
+    /// Summarizes the successful non-vote fees of a slot.
+    pub fn from_fees(slot: Slot, fees: &Fees) -> Self {
+        let transaction_count = fees.non_vote_fees.len();
+        if transaction_count == 0 {
+            return Self {
+                slot,
+                ..Default::default()
+            };
+        }
+        let mut samples = FeeSamples::new(fees.non_vote_fees.clone());
+        Self {
+            slot,
+            transaction_count,
+            estimates: MicroLamportPriorityFeeEstimates {
+                min: samples.percentile(0),
+                low: samples.percentile(25),
+                medium: samples.percentile(50),
+                high: samples.percentile(75),
+                very_high: samples.percentile(95),
+                unsafe_max: samples.percentile(100),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_upcoming_leaders() {
+        let leader_1 = Pubkey::new_unique();
+        let leader_2 = Pubkey::new_unique();
+        let schedule: HashMap<Slot, Pubkey> = (0..8)
+            .map(|slot| (slot, if slot < 4 { leader_1 } else { leader_2 }))
+            .collect();
+
+        assert_eq!(schedule.upcoming_leaders(1, 2), vec![leader_1]);
+        assert_eq!(schedule.upcoming_leaders(2, 8), vec![leader_1, leader_2]);
+        assert!(schedule.upcoming_leaders(10, 4).is_empty());
+    }
+
+    #[test]
+    fn test_leader_fee_history() {
+        let history = LeaderFeeHistory::default();
+        let leader = Pubkey::new_unique();
+
+        for slot in (0..40).rev() {
+            let fees = Fees::new(slot as f64 * 10.0, false);
+            history.record(leader, LeaderSlotFees::from_fees(slot, &fees));
+        }
+        history.record(leader, LeaderSlotFees::from_fees(39, &Fees::default()));
+
+        let recorded = history.history(&leader);
+        assert_eq!(recorded.len(), LEADER_HISTORY_SLOTS);
+        assert_eq!(recorded.first().unwrap().slot, 8);
+        assert_eq!(recorded.first().unwrap().estimates.medium, 80.0);
+        // slot 39 was re-recorded without fees
+        assert_eq!(recorded.last().unwrap().slot, 39);
+        assert_eq!(recorded.last().unwrap().transaction_count, 0);
+
+        assert_eq!(history.len(), 1);
+        assert!(history.history(&Pubkey::new_unique()).is_empty());
+    }
+
+    #[test]
+    fn test_leader_boost() {
+        let history = LeaderFeeHistory::default();
+        let busy_leader = Pubkey::new_unique();
+        let idle_leader = Pubkey::new_unique();
+        for slot in 0..2 {
+            history.record(
+                busy_leader,
+                LeaderSlotFees::from_fees(slot, &Fees::new(1_000.0, false)),
+            );
+        }
+        history.record(idle_leader, LeaderSlotFees::from_fees(2, &Fees::default()));
+        let estimates = MicroLamportPriorityFeeEstimates {
+            medium: 100.0,
+            ..Default::default()
+        };
+
+        // two recorded slots weighted 4 against eight window slots
+        let boosted = history.boost(
+            estimates.clone(),
+            8,
+            &LeaderBoost {
+                leaders: vec![busy_leader, idle_leader],
+                weight: 4,
+            },
+        );
+        assert_eq!(boosted.medium, 550.0);
+        assert_eq!(boosted.min, 500.0);
+
+        // leaders without recorded fees leave the estimates unchanged
+        let boosted = history.boost(
+            estimates,
+            8,
+            &LeaderBoost {
+                leaders: vec![idle_leader, Pubkey::new_unique()],
+                weight: 4,
+            },
+        );
+        assert_eq!(boosted.medium, 100.0);
+    }
+}
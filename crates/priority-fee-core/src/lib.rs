@@ -5,7 +5,7 @@
 //! # Main Components
 //!
 //! - [`PriorityFeeTracker`] - Tracks and estimates priority fees
-//! - [`Calculations`] - Calculation algorithms (v1/v2)
+//! - [`Calculations`] - Calculation algorithms (v1/v2/v3)
 //! - [`SlotCache`] - Thread-safe slot cache
 //!
 //! # Usage Example
@@ -26,17 +26,43 @@ pub mod model;
 /// Slot cache: thread-safe slot tracking.
 pub mod slot_cache;
 
-/// Calculation algorithms: v1/v2 percentile algorithms.
+/// Fee samples: unweighted and weighted percentile inputs.
+pub mod samples;
+
+/// Aggregation: combining the estimates of the global fees and of each account.
+pub mod aggregation;
+
+/// Calculation algorithms: v1/v2 percentile algorithms and v3 time-decayed percentiles.
 pub mod calculation;
 
+/// Congestion: block and account utilization of the compute unit limits.
+pub mod congestion;
+
+/// Confidence intervals: bootstrap intervals of estimates and minimum sample fallback.
+pub mod confidence;
+
+/// Fee forecasting: short-horizon forecasts of per-slot fee percentiles.
+pub mod forecast;
+
+/// Leader tracking: leader schedule lookup and per-leader fee history.
+pub mod leader;
+
+/// Tip tracking: detection of tips paid through system transfers and tip percentiles.
+pub mod tips;
+
 /// Priority fee tracker: core tracking and estimation logic.
 pub mod tracker;
 
 // Re-export common types
-pub use calculation::Calculations;
+pub use aggregation::Aggregation;
+pub use calculation::{CalculationOptions, Calculations, LeaderBoost};
+pub use leader::{LeaderFeeHistory, LeaderSchedule};
 pub use model::{
-    DataType, Fees, MicroLamportPriorityFeeDetails, MicroLamportPriorityFeeEstimates,
-    PriorityFeesBySlot, PriorityLevel, SlotPriorityFees,
+    BlockMetadata, ConfidenceInterval, DataType, Fees, LamportTipEstimates, LeaderSlotFees,
+    MicroLamportPriorityFeeDetails, MicroLamportPriorityFeeEstimates, OutlierFilter, PayerDedup,
+    PriorityFeesBySlot, PriorityLevel, SlotCommitment, SlotFeeBreakdown, SlotFeeForecast,
+    SlotFeeSamples, SlotPriorityFees, SlotSummary, TransactionStatusFilter,
 };
+pub use samples::FeeSamples;
 pub use slot_cache::SlotCache;
 pub use tracker::PriorityFeeTracker;
@@ -1,9 +1,12 @@
 //! Data Models: Priority fee types, estimation structures, etc.
 
+use crate::congestion;
 use crate::hash::DashMap;
+use crate::samples::FeeSamples;
 use serde::{Deserialize, Serialize};
-use solana_sdk::clock::Slot;
+use solana_sdk::clock::{Slot, UnixTimestamp};
 use solana_sdk::pubkey::Pubkey;
+use std::collections::{HashMap, HashSet};
 use std::fmt::{Debug, Display, Formatter};
 
 /// Priority levels for fee estimation.
@@ -95,6 +98,32 @@ pub struct MicroLamportPriorityFeeEstimates {
     pub unsafe_max: f64,
 }
 
+impl MicroLamportPriorityFeeEstimates {
+    /// Applies `f` to the estimate of each level.
+    pub fn map(self, f: impl Fn(f64) -> f64) -> Self {
+        Self {
+            min: f(self.min),
+            low: f(self.low),
+            medium: f(self.medium),
+            high: f(self.high),
+            very_high: f(self.very_high),
+            unsafe_max: f(self.unsafe_max),
+        }
+    }
+
+    /// Combines the estimates of each level with those of `other` using `f`.
+    pub fn zip_with(self, other: &Self, f: impl Fn(f64, f64) -> f64) -> Self {
+        Self {
+            min: f(self.min, other.min),
+            low: f(self.low, other.low),
+            medium: f(self.medium, other.medium),
+            high: f(self.high, other.high),
+            very_high: f(self.very_high, other.very_high),
+            unsafe_max: f(self.unsafe_max, other.unsafe_max),
+        }
+    }
+}
+
 /// Detailed priority fee statistics.
 #[derive(Deserialize, Serialize, Debug, Clone, Default)]
 #[serde(rename_all(serialize = "camelCase", deserialize = "camelCase"))]
@@ -109,39 +138,380 @@ pub struct MicroLamportPriorityFeeDetails {
     pub skew: f64,
     /// Number of transactions included in the calculation.
     pub count: usize,
+    /// Number of successful transactions in the lookback window.
+    pub success_count: usize,
+    /// Number of failed transactions in the lookback window.
+    pub failed_count: usize,
+    /// Percentile estimates over failed transactions only.
+    pub failed_estimates: MicroLamportPriorityFeeEstimates,
+    /// Number of samples removed, or clamped when winsorizing, by the outlier filter.
+    pub outliers_removed: usize,
+    /// Bootstrap confidence interval of the percentile estimates, absent without fees.
+    pub confidence_interval: Option<ConfidenceInterval>,
+    /// Data type whose fees were used instead, when there were fewer than the minimum samples.
+    pub fallback: Option<String>,
+    /// Mean share of the compute unit limit consumed per slot, of the block for the global fees
+    /// and of the account write lock for accounts.
+    pub congestion: f64,
+}
+
+/// Bounds of the confidence interval of each percentile estimate.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(rename_all(serialize = "camelCase", deserialize = "camelCase"))]
+pub struct ConfidenceInterval {
+    /// Lower bound of each estimate.
+    pub lower: MicroLamportPriorityFeeEstimates,
+    /// Upper bound of each estimate.
+    pub upper: MicroLamportPriorityFeeEstimates,
+    /// Number of fees drawn for each resample, the sample size unless the sample was bigger than
+    /// the largest resample.
+    pub resample_size: usize,
+    /// Number of resamples the bounds were taken from.
+    pub resamples: usize,
+}
+
+/// Outlier handling applied to the fees of each data type before percentiles are taken.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum OutlierFilter {
+    /// Drops the given percentage of samples from each tail, below 50.
+    Trim(u8),
+    /// Clamps the given percentage of samples in each tail to the tail's boundary, below 50.
+    Winsorize(u8),
+    /// Drops samples further than this many scaled median absolute deviations from the median.
+    Mad(u32),
+}
+
+/// Limits the contribution of a single fee payer to the fees of each slot.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum PayerDedup {
+    /// Keeps at most this many fees, in arrival order, per payer and slot.
+    Cap(u32),
+    /// Replaces the fees of each payer and slot with their median.
+    Collapse,
+}
+
+/// Tip percentiles in lamports for different priority levels.
+///
+/// Tips share the levels of the priority fee estimates, only their unit is lamports.
+pub type LamportTipEstimates = MicroLamportPriorityFeeEstimates;
+
+/// Forecast fee percentiles in micro-lamports for an upcoming block.
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all(serialize = "camelCase", deserialize = "camelCase"))]
+pub struct SlotFeeForecast {
+    /// Number of blocks with fees ahead of the newest one the forecast applies to, starting at 1.
+    ///
+    /// Skipped slots and slots without fees are not part of the fitted series, so steps are not
+    /// slot offsets.
+    pub step: u32,
+    /// Forecast median fee.
+    pub medium: f64,
+    /// Forecast 75th percentile fee.
+    pub high: f64,
+    /// Forecast 95th percentile fee.
+    pub very_high: f64,
+}
+
+/// Selects transactions by their execution status.
+#[derive(
+    Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+#[serde(rename_all = "camelCase")]
+pub enum TransactionStatusFilter {
+    /// Only transactions that executed successfully.
+    #[default]
+    Succeeded,
+    /// Only transactions that failed during execution.
+    Failed,
+    /// Both successful and failed transactions.
+    All,
 }
 
 /// Collection of fees for a slot or account.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct Fees {
     /// Non-vote transaction fees.
     pub non_vote_fees: Vec<f64>,
     /// Vote transaction fees.
     pub vote_fees: Vec<f64>,
+    /// Fees of failed non-vote transactions.
+    pub failed_fees: Vec<f64>,
+    /// Fee payers of the non-vote transactions, in the order of `non_vote_fees`.
+    pub non_vote_payers: Vec<Option<Pubkey>>,
+    /// Fee payers of the failed transactions, in the order of `failed_fees`.
+    pub failed_payers: Vec<Option<Pubkey>>,
 }
 
 impl Fees {
     /// Creates a new Fees instance with an initial fee.
     pub fn new(fee: f64, is_vote: bool) -> Self {
-        if is_vote {
-            Self {
-                vote_fees: vec![fee],
-                non_vote_fees: vec![],
-            }
-        } else {
-            Self {
-                vote_fees: vec![],
-                non_vote_fees: vec![fee],
-            }
-        }
+        let mut fees = Self::default();
+        fees.add_fee(fee, is_vote);
+        fees
+    }
+
+    /// Creates a new Fees instance with an initial failed transaction fee.
+    pub fn new_failed(fee: f64) -> Self {
+        let mut fees = Self::default();
+        fees.add_failed_fee(fee);
+        fees
     }
 
     /// Adds a fee to the collection.
     pub fn add_fee(&mut self, fee: f64, is_vote: bool) {
+        self.add_payer_fee(fee, is_vote, None);
+    }
+
+    /// Adds a fee paid by `payer` to the collection.
+    pub fn add_payer_fee(&mut self, fee: f64, is_vote: bool, payer: Option<Pubkey>) {
         if is_vote {
             self.vote_fees.push(fee);
         } else {
             self.non_vote_fees.push(fee);
+            self.non_vote_payers.push(payer);
+        }
+    }
+
+    /// Adds a failed transaction fee to the collection.
+    pub fn add_failed_fee(&mut self, fee: f64) {
+        self.add_failed_payer_fee(fee, None);
+    }
+
+    /// Adds a failed transaction fee paid by `payer` to the collection.
+    pub fn add_failed_payer_fee(&mut self, fee: f64, payer: Option<Pubkey>) {
+        self.failed_fees.push(fee);
+        self.failed_payers.push(payer);
+    }
+
+    /// Returns the number of distinct known fee payers.
+    pub fn payer_count(&self) -> usize {
+        self.non_vote_payers
+            .iter()
+            .chain(&self.failed_payers)
+            .flatten()
+            .collect::<HashSet<_>>()
+            .len()
+    }
+
+    /// Returns true if any transaction matching `status` was recorded.
+    pub const fn has_transactions(&self, status: TransactionStatusFilter) -> bool {
+        let succeeded = !self.vote_fees.is_empty() || !self.non_vote_fees.is_empty();
+        let failed = !self.failed_fees.is_empty();
+        match status {
+            TransactionStatusFilter::Succeeded => succeeded,
+            TransactionStatusFilter::Failed => failed,
+            TransactionStatusFilter::All => succeeded || failed,
+        }
+    }
+
+    /// Appends the fees matching `include_vote` and `status` to `samples`, limiting the fees of
+    /// each payer according to `dedup`.
+    ///
+    /// Failed vote transactions are never tracked, so failed fees are all non-vote. Vote fees and
+    /// fees without a known payer are never deduplicated.
+    pub fn collect_into(
+        &self,
+        samples: &mut Vec<f64>,
+        include_vote: bool,
+        status: TransactionStatusFilter,
+        dedup: Option<PayerDedup>,
+    ) {
+        if status != TransactionStatusFilter::Failed && include_vote {
+            samples.extend_from_slice(&self.vote_fees);
+        }
+        let mut fees: Vec<(f64, Option<Pubkey>)> = Vec::new();
+        if status != TransactionStatusFilter::Failed {
+            fees.extend(with_payers(&self.non_vote_fees, &self.non_vote_payers));
+        }
+        if status != TransactionStatusFilter::Succeeded {
+            fees.extend(with_payers(&self.failed_fees, &self.failed_payers));
+        }
+        let Some(dedup) = dedup else {
+            samples.extend(fees.into_iter().map(|(fee, _)| fee));
+            return;
+        };
+
+        let mut payer_fees: HashMap<Pubkey, Vec<f64>> = HashMap::new();
+        for (fee, payer) in fees {
+            match payer {
+                Some(payer) => payer_fees.entry(payer).or_default().push(fee),
+                None => samples.push(fee),
+            }
+        }
+        for (_, mut fees) in payer_fees {
+            match dedup {
+                PayerDedup::Cap(cap) => samples.extend(fees.into_iter().take(cap as usize)),
+                PayerDedup::Collapse => {
+                    fees.sort_by(f64::total_cmp);
+                    samples.push(fees[(fees.len() - 1) / 2]);
+                }
+            }
+        }
+    }
+}
+
+/// Pairs fees with their payers, fees recorded without a payer have none.
+fn with_payers<'a>(
+    fees: &'a [f64],
+    payers: &'a [Option<Pubkey>],
+) -> impl Iterator<Item = (f64, Option<Pubkey>)> + 'a {
+    fees.iter()
+        .enumerate()
+        .map(|(index, fee)| (*fee, payers.get(index).copied().flatten()))
+}
+
+/// Commitment level of the data tracked for a slot.
+#[derive(
+    Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+#[serde(rename_all = "camelCase")]
+pub enum SlotCommitment {
+    /// Ingested from a processed block, the slot may still be abandoned by a fork.
+    Processed,
+    /// Confirmed by the cluster.
+    #[default]
+    Confirmed,
+}
+
+/// Block-level metadata reported by Geyser for a slot.
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all(serialize = "camelCase", deserialize = "camelCase"))]
+pub struct BlockMetadata {
+    /// Unix timestamp at which the block was produced, if known.
+    pub block_time: Option<UnixTimestamp>,
+    /// Block height, if known.
+    pub block_height: Option<u64>,
+    /// Slot of the parent block.
+    pub parent_slot: Slot,
+    /// Base58-encoded blockhash.
+    pub blockhash: String,
+    /// Number of transactions executed in the block.
+    pub executed_transaction_count: u64,
+    /// Total compute units consumed by the block's transactions.
+    pub compute_units_consumed: u64,
+}
+
+/// Summary of the data tracked for a single slot.
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all(serialize = "camelCase", deserialize = "camelCase"))]
+pub struct SlotSummary {
+    /// Slot number.
+    pub slot: Slot,
+    /// Block metadata, if the block update has been received.
+    #[serde(flatten)]
+    pub block: Option<BlockMetadata>,
+    /// Whether the slot was skipped by its leader.
+    pub skipped: bool,
+    /// Commitment level of the slot's data.
+    pub commitment: SlotCommitment,
+    /// Number of tracked vote transactions.
+    pub vote_transaction_count: usize,
+    /// Number of tracked non-vote transactions.
+    pub non_vote_transaction_count: usize,
+    /// Number of tracked failed non-vote transactions.
+    pub failed_transaction_count: usize,
+    /// Number of distinct writable accounts with fees in the slot.
+    pub account_count: usize,
+    /// Base58-encoded leader of the slot, if known.
+    pub leader: Option<String>,
+    /// Number of tracked transactions that paid a tip.
+    pub tip_count: usize,
+    /// Number of distinct fee payers of the tracked non-vote transactions.
+    pub payer_count: usize,
+    /// Share of the block compute unit limit consumed by the block.
+    pub block_utilization: f64,
+}
+
+impl SlotSummary {
+    /// Creates the summary of a slot that was skipped by its leader.
+    pub fn skipped(slot: Slot) -> Self {
+        Self {
+            slot,
+            skipped: true,
+            ..Default::default()
+        }
+    }
+}
+
+/// Fee statistics of a single slot produced by a leader.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(rename_all(serialize = "camelCase", deserialize = "camelCase"))]
+pub struct LeaderSlotFees {
+    /// Slot number.
+    pub slot: Slot,
+    /// Number of successful non-vote transactions in the slot.
+    pub transaction_count: usize,
+    /// Percentiles of the slot's successful non-vote transaction fees.
+    pub estimates: MicroLamportPriorityFeeEstimates,
+}
+
+/// Sample count, percentiles and raw fees of one kind of transaction in a slot.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(rename_all(serialize = "camelCase", deserialize = "camelCase"))]
+pub struct SlotFeeSamples {
+    /// Number of fees recorded.
+    pub count: usize,
+    /// Percentiles of the fees, zero when no fees are recorded.
+    pub estimates: MicroLamportPriorityFeeEstimates,
+    /// Recorded fees, in ingestion order.
+    pub fees: Vec<f64>,
+    /// Whether `fees` was cut to the requested number of raw fees.
+    pub truncated: bool,
+}
+
+impl SlotFeeSamples {
+    /// Summarizes `fees`, keeping at most `max_raw_fees` of them.
+    pub fn from_fees(fees: &[f64], max_raw_fees: usize) -> Self {
+        if fees.is_empty() {
+            return Self::default();
+        }
+        let mut samples = FeeSamples::new(fees.to_vec());
+        Self {
+            count: fees.len(),
+            estimates: MicroLamportPriorityFeeEstimates {
+                min: samples.percentile(0),
+                low: samples.percentile(25),
+                medium: samples.percentile(50),
+                high: samples.percentile(75),
+                very_high: samples.percentile(95),
+                unsafe_max: samples.percentile(100),
+            },
+            fees: fees.iter().take(max_raw_fees).copied().collect(),
+            truncated: fees.len() > max_raw_fees,
+        }
+    }
+}
+
+/// Fees recorded in a slot, globally or for one account, split by kind of transaction.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(rename_all(serialize = "camelCase", deserialize = "camelCase"))]
+pub struct SlotFeeBreakdown {
+    /// Slot number.
+    pub slot: Slot,
+    /// Base58-encoded account, `None` for the global fees of the slot.
+    pub account: Option<String>,
+    /// Whether the slot was skipped by its leader.
+    pub skipped: bool,
+    /// Commitment level of the slot's data.
+    pub commitment: SlotCommitment,
+    /// Fees of the successful non-vote transactions.
+    pub non_vote: SlotFeeSamples,
+    /// Fees of the vote transactions.
+    pub vote: SlotFeeSamples,
+    /// Fees of the failed non-vote transactions.
+    pub failed: SlotFeeSamples,
+}
+
+impl SlotFeeBreakdown {
+    /// Creates the breakdown of a slot that was skipped by its leader, which has no fees.
+    pub fn skipped(slot: Slot, account: Option<&Pubkey>) -> Self {
+        Self {
+            slot,
+            account: account.map(|account| account.to_string()),
+            skipped: true,
+            ..Default::default()
         }
     }
 }
@@ -155,20 +525,175 @@ pub struct SlotPriorityFees {
     pub fees: Fees,
     /// Per-account fees for the slot.
     pub account_fees: DashMap<Pubkey, Fees>,
+    /// Block metadata for the slot, if received.
+    pub block: Option<BlockMetadata>,
+    /// Commitment level of the slot's data.
+    pub commitment: SlotCommitment,
+    /// Leader that produced the slot, if known.
+    pub leader: Option<Pubkey>,
+    /// Tips, in lamports, paid by the slot's transactions.
+    pub tips: Vec<f64>,
+    /// Per-account tips paid by transactions writing to the account.
+    pub account_tips: DashMap<Pubkey, Vec<f64>>,
+    /// Compute units consumed by the slot's tracked transactions.
+    pub compute_units: u64,
+    /// Per-account compute units consumed by transactions writing to the account.
+    pub account_compute_units: DashMap<Pubkey, u64>,
 }
 
 impl SlotPriorityFees {
     /// Creates a new SlotPriorityFees instance.
     pub fn new(slot: Slot, accounts: Vec<Pubkey>, priority_fee: u64, is_vote: bool) -> Self {
-        let account_fees = DashMap::default();
-        let fees = Fees::new(priority_fee as f64, is_vote);
-        for account in accounts {
-            account_fees.insert(account, fees.clone());
+        let mut slot_fees = Self::empty(slot);
+        slot_fees.add_payer_fee(accounts, priority_fee, is_vote, None);
+        slot_fees
+    }
+
+    /// Creates a new SlotPriorityFees instance from a failed transaction.
+    pub fn new_failed(slot: Slot, accounts: Vec<Pubkey>, priority_fee: u64) -> Self {
+        let mut slot_fees = Self::empty(slot);
+        slot_fees.add_failed_payer_fee(accounts, priority_fee, None);
+        slot_fees
+    }
+
+    /// Creates a SlotPriorityFees instance holding only block metadata.
+    pub fn from_block_metadata(slot: Slot, block: BlockMetadata) -> Self {
+        Self {
+            block: Some(block),
+            ..Self::empty(slot)
         }
+    }
+
+    /// Creates a SlotPriorityFees instance without fees or block metadata.
+    pub fn empty(slot: Slot) -> Self {
         Self {
             slot,
-            fees,
-            account_fees,
+            fees: Fees::default(),
+            account_fees: DashMap::default(),
+            block: None,
+            commitment: SlotCommitment::Confirmed,
+            leader: None,
+            tips: Vec::new(),
+            account_tips: DashMap::default(),
+            compute_units: 0,
+            account_compute_units: DashMap::default(),
+        }
+    }
+
+    /// Creates a SlotPriorityFees instance holding only the tip of a transaction.
+    pub fn new_tip(slot: Slot, accounts: Vec<Pubkey>, tip: u64) -> Self {
+        let mut slot_fees = Self::empty(slot);
+        slot_fees.add_tip(accounts, tip);
+        slot_fees
+    }
+
+    /// Records the fee of a transaction paid by `payer` and writing to `accounts`.
+    pub fn add_payer_fee(
+        &mut self,
+        accounts: Vec<Pubkey>,
+        priority_fee: u64,
+        is_vote: bool,
+        payer: Option<Pubkey>,
+    ) {
+        let fee = priority_fee as f64;
+        self.fees.add_payer_fee(fee, is_vote, payer);
+        for account in accounts {
+            self.account_fees
+                .entry(account)
+                .or_default()
+                .add_payer_fee(fee, is_vote, payer);
+        }
+    }
+
+    /// Records the fee of a failed transaction paid by `payer` and writing to `accounts`.
+    pub fn add_failed_payer_fee(
+        &mut self,
+        accounts: Vec<Pubkey>,
+        priority_fee: u64,
+        payer: Option<Pubkey>,
+    ) {
+        let fee = priority_fee as f64;
+        self.fees.add_failed_payer_fee(fee, payer);
+        for account in accounts {
+            self.account_fees
+                .entry(account)
+                .or_default()
+                .add_failed_payer_fee(fee, payer);
+        }
+    }
+
+    /// Records the tip of a transaction writing to `accounts`.
+    pub fn add_tip(&mut self, accounts: Vec<Pubkey>, tip: u64) {
+        self.tips.push(tip as f64);
+        for account in accounts {
+            self.account_tips
+                .entry(account)
+                .or_default()
+                .push(tip as f64);
+        }
+    }
+
+    /// Records the compute units consumed by a transaction writing to `accounts`.
+    pub fn add_compute_units(&mut self, accounts: Vec<Pubkey>, compute_units: u64) {
+        self.compute_units = self.compute_units.saturating_add(compute_units);
+        for account in accounts {
+            let mut account_compute_units = self.account_compute_units.entry(account).or_default();
+            *account_compute_units = account_compute_units.saturating_add(compute_units);
+        }
+    }
+
+    /// Returns the compute units consumed by the whole block, falling back to the tracked
+    /// transactions before the block metadata is received.
+    pub fn block_compute_units(&self) -> u64 {
+        self.block
+            .as_ref()
+            .map_or(self.compute_units, |block| block.compute_units_consumed)
+    }
+
+    /// Returns the fees recorded for `account`, or the global fees of the slot, keeping at most
+    /// `max_raw_fees` raw fees of each kind. Accounts without fees in the slot have no samples.
+    pub fn fee_breakdown(&self, account: Option<&Pubkey>, max_raw_fees: usize) -> SlotFeeBreakdown {
+        let samples = |fees: &Fees| {
+            (
+                SlotFeeSamples::from_fees(&fees.non_vote_fees, max_raw_fees),
+                SlotFeeSamples::from_fees(&fees.vote_fees, max_raw_fees),
+                SlotFeeSamples::from_fees(&fees.failed_fees, max_raw_fees),
+            )
+        };
+        let (non_vote, vote, failed) = match account {
+            Some(account) => self
+                .account_fees
+                .get(account)
+                .map(|fees| samples(&fees))
+                .unwrap_or_default(),
+            None => samples(&self.fees),
+        };
+        SlotFeeBreakdown {
+            slot: self.slot,
+            account: account.map(|account| account.to_string()),
+            skipped: false,
+            commitment: self.commitment,
+            non_vote,
+            vote,
+            failed,
+        }
+    }
+
+    /// Returns a summary of the block metadata and transaction counts for this slot.
+    pub fn summary(&self) -> SlotSummary {
+        SlotSummary {
+            slot: self.slot,
+            block: self.block.clone(),
+            skipped: false,
+            commitment: self.commitment,
+            vote_transaction_count: self.fees.vote_fees.len(),
+            non_vote_transaction_count: self.fees.non_vote_fees.len(),
+            failed_transaction_count: self.fees.failed_fees.len(),
+            account_count: self.account_fees.len(),
+            leader: self.leader.map(|leader| leader.to_string()),
+            tip_count: self.tips.len(),
+            payer_count: self.fees.payer_count(),
+            block_utilization: congestion::block_utilization(self),
         }
     }
 }
@@ -0,0 +1,330 @@
+//! Fee Samples: Unweighted and weighted fee samples used for percentile estimation.
+
+use crate::model::OutlierFilter;
+use rand::Rng;
+use statrs::statistics::{Data, Distribution, OrderStatistics};
+
+/// Scale factor that makes the median absolute deviation a consistent estimator of the standard
+/// deviation of normally distributed data.
+const MAD_SCALE: f64 = 1.4826;
+
+/// Fees collected for a single data type by a calculation algorithm.
+///
+/// Unweighted samples behave exactly like [`Data`]. Weighted samples give each fee a relative
+/// importance and use weighted percentiles and moments, with every fee counting as one sample
+/// towards [`FeeSamples::len`].
+#[derive(Debug, Clone)]
+pub struct FeeSamples {
+    samples: Samples,
+    outliers: usize,
+    fallback: bool,
+}
+
+#[derive(Debug, Clone)]
+enum Samples {
+    Unweighted(Data<Vec<f64>>),
+    // (fee, weight) pairs sorted by fee
+    Weighted(Vec<(f64, f64)>),
+}
+
+impl FeeSamples {
+    /// Creates unweighted samples.
+    pub fn new(fees: Vec<f64>) -> Self {
+        Self {
+            samples: Samples::Unweighted(Data::new(fees)),
+            outliers: 0,
+            fallback: false,
+        }
+    }
+
+    /// Creates weighted samples from `(fee, weight)` pairs.
+    ///
+    /// Pairs without a positive weight are dropped.
+    pub fn weighted(mut samples: Vec<(f64, f64)>) -> Self {
+        samples.retain(|(_, weight)| *weight > 0.0);
+        samples.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Self {
+            samples: Samples::Weighted(samples),
+            outliers: 0,
+            fallback: false,
+        }
+    }
+
+    /// Returns the number of samples.
+    pub fn len(&self) -> usize {
+        match &self.samples {
+            Samples::Unweighted(data) => data.len(),
+            Samples::Weighted(samples) => samples.len(),
+        }
+    }
+
+    /// Returns true if there are no samples.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of samples removed, or clamped when winsorizing, by outlier filtering.
+    pub const fn outliers(&self) -> usize {
+        self.outliers
+    }
+
+    /// Returns true if these samples stand in for a data type with too few fees of its own.
+    pub const fn is_fallback(&self) -> bool {
+        self.fallback
+    }
+
+    /// Marks these samples as standing in for a data type with too few fees of its own.
+    pub const fn into_fallback(mut self) -> Self {
+        self.fallback = true;
+        self
+    }
+
+    /// Returns `len` fees drawn uniformly with replacement, each keeping its weight.
+    pub fn resample(&self, rng: &mut impl Rng, len: usize) -> Self {
+        if self.is_empty() {
+            return self.clone();
+        }
+        let count = self.len();
+        match &self.samples {
+            Samples::Unweighted(data) => {
+                Self::new((0..len).map(|_| data[rng.random_range(0..count)]).collect())
+            }
+            Samples::Weighted(samples) => Self::weighted(
+                (0..len)
+                    .map(|_| samples[rng.random_range(0..count)])
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Applies `filter` to the samples, keeping the weights of the remaining fees.
+    ///
+    /// MAD-based rejection is skipped when more than half of the fees are identical, as every
+    /// other fee would be rejected.
+    pub fn filter_outliers(mut self, filter: OutlierFilter) -> Self {
+        if self.is_empty() {
+            return self;
+        }
+        let (lower, upper, clamp) = match filter {
+            OutlierFilter::Trim(percent) | OutlierFilter::Winsorize(percent) => {
+                let percent = percent.min(49) as usize;
+                (
+                    self.percentile(percent),
+                    self.percentile(100 - percent),
+                    matches!(filter, OutlierFilter::Winsorize(_)),
+                )
+            }
+            OutlierFilter::Mad(threshold) => {
+                let median = self.percentile(50);
+                let deviations: Vec<f64> = match &self.samples {
+                    Samples::Unweighted(data) => {
+                        data.iter().map(|fee| (fee - median).abs()).collect()
+                    }
+                    Samples::Weighted(samples) => samples
+                        .iter()
+                        .map(|(fee, _)| (fee - median).abs())
+                        .collect(),
+                };
+                let mad = Data::new(deviations).median();
+                if mad <= 0.0 {
+                    return self;
+                }
+                let limit = threshold as f64 * MAD_SCALE * mad;
+                (median - limit, median + limit, false)
+            }
+        };
+
+        let adjust = |fee: f64| -> Option<f64> {
+            if (lower..=upper).contains(&fee) {
+                Some(fee)
+            } else if clamp {
+                Some(fee.clamp(lower, upper))
+            } else {
+                None
+            }
+        };
+        let (samples, outliers) = match self.samples {
+            Samples::Unweighted(data) => {
+                let fees: Vec<f64> = data.iter().filter_map(|fee| adjust(*fee)).collect();
+                let outliers = count_outliers(data.iter().copied(), fees.iter().copied());
+                (Samples::Unweighted(Data::new(fees)), outliers)
+            }
+            Samples::Weighted(samples) => {
+                let filtered: Vec<(f64, f64)> = samples
+                    .iter()
+                    .filter_map(|(fee, weight)| adjust(*fee).map(|fee| (fee, *weight)))
+                    .collect();
+                let outliers = count_outliers(
+                    samples.iter().map(|(fee, _)| *fee),
+                    filtered.iter().map(|(fee, _)| *fee),
+                );
+                (Samples::Weighted(filtered), outliers)
+            }
+        };
+        Self {
+            samples,
+            outliers: self.outliers + outliers,
+            fallback: self.fallback,
+        }
+    }
+
+    /// Returns the `percentile`th percentile of the fees, or NaN when there are no samples.
+    ///
+    /// Weighted samples return the smallest fee whose cumulative weight reaches the percentile.
+    pub fn percentile(&mut self, percentile: usize) -> f64 {
+        match &mut self.samples {
+            Samples::Unweighted(data) => data.percentile(percentile),
+            Samples::Weighted(samples) => {
+                let Some(&(max, _)) = samples.last() else {
+                    return f64::NAN;
+                };
+                let total: f64 = samples.iter().map(|(_, weight)| weight).sum();
+                let target = total * percentile.min(100) as f64 / 100.0;
+                let mut cumulative = 0.0;
+                for (fee, weight) in samples.iter() {
+                    cumulative += weight;
+                    if cumulative >= target {
+                        return *fee;
+                    }
+                }
+                max
+            }
+        }
+    }
+}
+
+/// Counts the fees that were removed or changed, given the fees before and after filtering in the
+/// same order.
+fn count_outliers(
+    before: impl ExactSizeIterator<Item = f64>,
+    after: impl ExactSizeIterator<Item = f64>,
+) -> usize {
+    let removed = before.len() - after.len();
+    if removed > 0 {
+        return removed;
+    }
+    before.zip(after).filter(|(a, b)| a != b).count()
+}
+
+impl Distribution<f64> for FeeSamples {
+    fn mean(&self) -> Option<f64> {
+        match &self.samples {
+            Samples::Unweighted(data) => data.mean(),
+            Samples::Weighted(samples) => {
+                if samples.is_empty() {
+                    return None;
+                }
+                let total: f64 = samples.iter().map(|(_, weight)| weight).sum();
+                Some(
+                    samples
+                        .iter()
+                        .map(|(fee, weight)| fee * weight)
+                        .sum::<f64>()
+                        / total,
+                )
+            }
+        }
+    }
+
+    /// Unbiased weighted variance using reliability weights, equal to the sample variance when all
+    /// weights are equal.
+    fn variance(&self) -> Option<f64> {
+        match &self.samples {
+            Samples::Unweighted(data) => data.variance(),
+            Samples::Weighted(samples) => {
+                let mean = self.mean()?;
+                let v1: f64 = samples.iter().map(|(_, weight)| weight).sum();
+                let v2: f64 = samples.iter().map(|(_, weight)| weight * weight).sum();
+                let denominator = v1 - v2 / v1;
+                if denominator <= 0.0 {
+                    return None;
+                }
+                let squares: f64 = samples
+                    .iter()
+                    .map(|(fee, weight)| weight * (fee - mean).powi(2))
+                    .sum();
+                Some(squares / denominator)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unweighted_matches_data() {
+        let fees: Vec<f64> = (0..=100).map(|i| i as f64).collect();
+        let mut samples = FeeSamples::new(fees.clone());
+        let mut data = Data::new(fees);
+
+        for percentile in [0, 25, 50, 75, 95, 100] {
+            assert_eq!(samples.percentile(percentile), data.percentile(percentile));
+        }
+        assert_eq!(samples.mean(), data.mean());
+        assert_eq!(samples.std_dev(), data.std_dev());
+        assert_eq!(samples.len(), 101);
+    }
+
+    #[test]
+    fn test_weighted_percentiles() {
+        // the recent fee of 100 carries three quarters of the weight
+        let mut samples = FeeSamples::weighted(vec![(100.0, 3.0), (10.0, 1.0), (50.0, 0.0)]);
+
+        assert_eq!(samples.len(), 2);
+        assert_eq!(samples.percentile(0), 10.0);
+        assert_eq!(samples.percentile(25), 10.0);
+        assert_eq!(samples.percentile(50), 100.0);
+        assert_eq!(samples.percentile(100), 100.0);
+        assert_eq!(samples.mean(), Some(77.5));
+
+        let mut empty = FeeSamples::weighted(vec![]);
+        assert!(empty.percentile(50).is_nan());
+        assert_eq!(empty.mean(), None);
+    }
+
+    #[test]
+    fn test_outlier_filters() {
+        let mut fees: Vec<f64> = (1..=99).map(|i| i as f64).collect();
+        fees.push(2_000_000_000.0);
+
+        let mut trimmed = FeeSamples::new(fees.clone()).filter_outliers(OutlierFilter::Trim(5));
+        assert!(trimmed.len() < 100);
+        assert_eq!(trimmed.outliers(), 100 - trimmed.len());
+        assert!(trimmed.percentile(100) < 100.0);
+
+        let mut winsorized =
+            FeeSamples::new(fees.clone()).filter_outliers(OutlierFilter::Winsorize(5));
+        assert_eq!(winsorized.len(), 100);
+        assert!(winsorized.outliers() > 0);
+        assert!(winsorized.percentile(100) < 100.0);
+
+        let mut rejected = FeeSamples::new(fees.clone()).filter_outliers(OutlierFilter::Mad(3));
+        assert_eq!(rejected.len(), 99);
+        assert_eq!(rejected.outliers(), 1);
+        assert_eq!(rejected.percentile(100), 99.0);
+
+        let weighted: Vec<(f64, f64)> = fees.iter().map(|fee| (*fee, 1.0)).collect();
+        let mut rejected = FeeSamples::weighted(weighted).filter_outliers(OutlierFilter::Mad(3));
+        assert_eq!(rejected.outliers(), 1);
+        assert_eq!(rejected.percentile(100), 99.0);
+
+        // identical fees have no spread, nothing is rejected
+        let mut flat = vec![5_000.0; 10];
+        flat.push(10_000.0);
+        let flat = FeeSamples::new(flat).filter_outliers(OutlierFilter::Mad(3));
+        assert_eq!(flat.len(), 11);
+        assert_eq!(flat.outliers(), 0);
+    }
+
+    #[test]
+    fn test_equal_weights_variance() {
+        let fees = vec![1.0, 2.0, 3.0, 4.0];
+        let weighted = FeeSamples::weighted(fees.iter().map(|fee| (*fee, 0.5)).collect());
+        let unweighted = FeeSamples::new(fees);
+
+        let difference = weighted.variance().unwrap() - unweighted.variance().unwrap();
+        assert!(difference.abs() < 1e-9);
+    }
+}
@@ -1,18 +1,22 @@
 //! Slot Cache: Thread-safe slot tracking.
 
+use std::collections::BTreeSet;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, RwLock};
 
 use crate::hash::DashSet;
-use queues::{CircularBuffer, IsQueue};
 use solana_sdk::slot_history::Slot;
 use tracing::error;
 
 /// A thread-safe cache for tracking recent slots.
+///
+/// Slots may be pushed out of order; once full, the oldest slot is evicted, not the one pushed
+/// first.
 #[derive(Debug, Clone)]
 pub struct SlotCache {
-    slot_queue: Arc<RwLock<CircularBuffer<Slot>>>,
+    slot_queue: Arc<RwLock<BTreeSet<Slot>>>,
     slot_set: Arc<DashSet<Slot>>,
+    capacity: usize,
     // Fast-path for the common case: many txns share the same slot.
     last_seen_slot: Arc<AtomicU64>,
 }
@@ -21,13 +25,17 @@ impl SlotCache {
     /// Creates a new SlotCache with the specified capacity.
     pub fn new(slot_cache_length: usize) -> Self {
         Self {
-            slot_queue: Arc::new(RwLock::new(CircularBuffer::new(slot_cache_length))),
+            slot_queue: Arc::new(RwLock::new(BTreeSet::new())),
             slot_set: Arc::new(DashSet::default()),
+            capacity: slot_cache_length,
             last_seen_slot: Arc::new(AtomicU64::new(u64::MAX)),
         }
     }
 
     /// Pushes a new slot into the cache and returns the oldest slot if the cache is at capacity.
+    ///
+    /// The returned slot is `slot` itself when it is older than every cached slot of a full cache,
+    /// in which case it is not cached.
     pub fn push_pop(&self, slot: Slot) -> Option<Slot> {
         if self.last_seen_slot.load(Ordering::Relaxed) == slot {
             return None;
@@ -43,20 +51,20 @@ impl SlotCache {
                     return None;
                 }
 
-                match slot_queue.add(slot) {
-                    Ok(maybe_oldest_slot) => {
-                        if let Some(oldest_slot) = maybe_oldest_slot {
-                            self.slot_set.remove(&oldest_slot);
-                        }
-                        self.slot_set.insert(slot);
-                        self.last_seen_slot.store(slot, Ordering::Relaxed);
-                        maybe_oldest_slot
-                    }
-                    Err(e) => {
-                        error!("error adding slot to slot queue: {}", e);
-                        None
-                    }
+                slot_queue.insert(slot);
+                let maybe_oldest_slot = if slot_queue.len() > self.capacity {
+                    slot_queue.pop_first()
+                } else {
+                    None
+                };
+                if let Some(oldest_slot) = maybe_oldest_slot {
+                    self.slot_set.remove(&oldest_slot);
                 }
+                if maybe_oldest_slot != Some(slot) {
+                    self.slot_set.insert(slot);
+                    self.last_seen_slot.store(slot, Ordering::Relaxed);
+                }
+                maybe_oldest_slot
             }
             Err(e) => {
                 error!("error getting write lock on slot queue: {}", e);
@@ -65,6 +73,33 @@ impl SlotCache {
         }
     }
 
+    /// Removes a slot from the cache, returning true if it was present.
+    pub fn remove(&self, slot: Slot) -> bool {
+        if !self.slot_set.contains(&slot) {
+            return false;
+        }
+        match self.slot_queue.write() {
+            Ok(mut slot_queue) => {
+                if self.slot_set.remove(&slot).is_none() {
+                    return false;
+                }
+                slot_queue.remove(&slot);
+                // forget the fast-path slot so the slot can be pushed again
+                let _ = self.last_seen_slot.compare_exchange(
+                    slot,
+                    u64::MAX,
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                );
+                true
+            }
+            Err(e) => {
+                error!("error getting write lock on slot queue: {}", e);
+                false
+            }
+        }
+    }
+
     /// Copies all slots currently in the cache into the provided vector.
     pub fn copy_slots(&self, vec: &mut Vec<Slot>) {
         vec.extend(self.slot_set.iter().map(|v| *v));
@@ -81,6 +116,90 @@ impl SlotCache {
     }
 }
 
+/// A thread-safe, bounded set of slots skipped by their leader.
+///
+/// Skipped slots are kept apart from the [`SlotCache`] so that they never take the room of slots
+/// with blocks. Once full, the oldest skipped slot is dropped.
+#[derive(Debug, Clone)]
+pub struct SkippedSlots {
+    slots: Arc<DashSet<Slot>>,
+    capacity: usize,
+}
+
+impl SkippedSlots {
+    /// Creates a new SkippedSlots with the specified capacity.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            slots: Arc::new(DashSet::default()),
+            capacity,
+        }
+    }
+
+    /// Records a skipped slot, returning true if it was not recorded yet and is kept.
+    ///
+    /// A slot older than every recorded slot is not kept once the set is full.
+    pub fn insert(&self, slot: Slot) -> bool {
+        if !self.slots.insert(slot) {
+            return false;
+        }
+        while self.slots.len() > self.capacity {
+            let Some(oldest_slot) = self.slots.iter().map(|slot| *slot).min() else {
+                break;
+            };
+            self.slots.remove(&oldest_slot);
+            if oldest_slot == slot {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Removes a slot, returning true if it was recorded.
+    pub fn remove(&self, slot: Slot) -> bool {
+        self.slots.remove(&slot).is_some()
+    }
+
+    /// Returns true if the slot is recorded as skipped.
+    pub fn contains(&self, slot: Slot) -> bool {
+        self.slots.contains(&slot)
+    }
+
+    /// Removes the slots older than `slot`, returning the number removed.
+    pub fn remove_older_than(&self, slot: Slot) -> usize {
+        let before = self.slots.len();
+        self.slots.retain(|skipped_slot| *skipped_slot >= slot);
+        before - self.slots.len()
+    }
+
+    /// Copies the recorded slots between `start_slot` and `end_slot` inclusive into the provided
+    /// vector.
+    pub fn copy_range(&self, start_slot: Slot, end_slot: Slot, vec: &mut Vec<Slot>) {
+        vec.extend(
+            self.slots
+                .iter()
+                .map(|slot| *slot)
+                .filter(|slot| (start_slot..=end_slot).contains(slot)),
+        );
+    }
+
+    /// Removes every recorded slot, returning the number removed.
+    pub fn clear(&self) -> usize {
+        let len = self.slots.len();
+        self.slots.clear();
+        len
+    }
+
+    /// Returns the number of recorded slots.
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Returns true if no slot is recorded.
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -97,6 +216,45 @@ mod tests {
         assert_eq!(slot_cache.push_pop(3), None);
     }
 
+    #[test]
+    fn test_push_pop_out_of_order() {
+        let slot_cache = SlotCache::new(3);
+        for slot in [10, 12, 11] {
+            assert_eq!(slot_cache.push_pop(slot), None);
+        }
+        // the oldest slot is evicted, not the one pushed first
+        assert_eq!(slot_cache.push_pop(14), Some(10));
+        assert_eq!(slot_cache.push_pop(13), Some(11));
+        // a late slot older than the whole window is not kept
+        assert_eq!(slot_cache.push_pop(9), Some(9));
+        assert_eq!(slot_cache.len(), 3);
+
+        let mut vec: Vec<Slot> = Vec::new();
+        slot_cache.copy_slots(&mut vec);
+        vec.sort();
+        assert_eq!(vec, vec![12, 13, 14]);
+    }
+
+    #[test]
+    fn test_remove() {
+        let slot_cache = SlotCache::new(3);
+        for i in 0..3 {
+            assert_eq!(slot_cache.push_pop(i), None);
+        }
+        assert!(slot_cache.remove(2));
+        assert!(!slot_cache.remove(2));
+        assert_eq!(slot_cache.len(), 2);
+
+        // the freed capacity is reused before the oldest slot is evicted
+        assert_eq!(slot_cache.push_pop(2), None);
+        assert_eq!(slot_cache.push_pop(3), Some(0));
+
+        let mut vec: Vec<Slot> = Vec::new();
+        slot_cache.copy_slots(&mut vec);
+        vec.sort();
+        assert_eq!(vec, vec![1, 2, 3]);
+    }
+
     #[test]
     fn test_copy() {
         let slot_cache = SlotCache::new(100);
@@ -124,4 +282,30 @@ mod tests {
         vec.sort();
         assert_eq!(vec, (0..100).collect::<Vec<Slot>>());
     }
+
+    #[test]
+    fn test_skipped_slots() {
+        let skipped_slots = SkippedSlots::new(3);
+        for slot in [10, 12, 14] {
+            assert!(skipped_slots.insert(slot));
+        }
+        assert!(!skipped_slots.insert(12));
+
+        // a late slot older than every recorded slot is not kept once full
+        assert!(!skipped_slots.insert(5));
+        assert!(skipped_slots.insert(16));
+        assert!(!skipped_slots.contains(10));
+        assert_eq!(skipped_slots.len(), 3);
+
+        let mut vec: Vec<Slot> = Vec::new();
+        skipped_slots.copy_range(13, 20, &mut vec);
+        vec.sort();
+        assert_eq!(vec, vec![14, 16]);
+
+        assert_eq!(skipped_slots.remove_older_than(14), 1);
+        assert!(skipped_slots.remove(16));
+        assert!(!skipped_slots.remove(16));
+        assert_eq!(skipped_slots.clear(), 1);
+        assert!(skipped_slots.is_empty());
+    }
 }
@@ -0,0 +1,191 @@
+//! Tip Tracking: Detection of tips paid through system transfers and tip percentiles.
+
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use solana_sdk::pubkey::Pubkey;
+
+use crate::calculation::{select_lookback_slots, CalculationOptions};
+use crate::model::{LamportTipEstimates, PriorityFeesBySlot};
+use crate::samples::FeeSamples;
+
+/// Address of the system program.
+pub const SYSTEM_PROGRAM_ID: Pubkey = Pubkey::new_from_array([0; 32]);
+
+/// Instruction discriminant of the system program's `Transfer` instruction.
+const SYSTEM_TRANSFER_DISCRIMINANT: u32 = 2;
+
+/// Base58-encoded Jito tip payment accounts.
+pub const JITO_TIP_ACCOUNTS: [&str; 8] = [
+    "96gYZGLnJYVFmbjzopPSU6QiEV5fGqZNyN9nmNhvrZU5",
+    "HFqU5x63VTqvQss8hp11i4wVV8bD44PvwucfZ2bU7gRe",
+    "Cw8CFyM9FkoMi7K7Crf6HNQqf4uEMzpKw6QNghXLvLkY",
+    "ADaUMid9yfUytqMBgopwjb2DTLSokTSzL1zt6iGPaS49",
+    "DfXygSm4jCyNCybVYYK6DwvWqjKee8pbDmJGcLWNDXjh",
+    "ADuUkR4vqLUMWXxW9gh6D6L8pMSawimctcNZ5pGwDcEt",
+    "DttWaMuVvTiduZRnguLF7jNxTgiMBZ1hyAumKUiL2KRL",
+    "3AVi9Tg9Uo68tJfuvoKvqKNWKkC5wPdSSdeBnizKZ6jT",
+];
+
+/// Returns the Jito tip payment accounts.
+pub fn jito_tip_accounts() -> HashSet<Pubkey> {
+    JITO_TIP_ACCOUNTS
+        .iter()
+        .map(|account| Pubkey::from_str(account).expect("valid tip account"))
+        .collect()
+}
+
+/// Returns the lamports moved by a system program `Transfer` instruction, given its data.
+pub fn parse_system_transfer(data: &[u8]) -> Option<u64> {
+    let discriminant = u32::from_le_bytes(data.get(..4)?.try_into().ok()?);
+    if discriminant != SYSTEM_TRANSFER_DISCRIMINANT {
+        return None;
+    }
+    Some(u64::from_le_bytes(data.get(4..12)?.try_into().ok()?))
+}
+
+/// Returns the total lamports a transaction transfers to any of `tip_accounts`.
+///
+/// Instructions are given as `(program_id_index, account_indexes, data)` into `accounts`, which
+/// holds the static account keys followed by the writable and then the readonly addresses loaded
+/// from lookup tables. Instructions referencing accounts outside of `accounts` are ignored.
+pub fn transaction_tip<'a>(
+    accounts: &[Pubkey],
+    instructions: impl IntoIterator<Item = (u8, &'a [u8], &'a [u8])>,
+    tip_accounts: &HashSet<Pubkey>,
+) -> u64 {
+    instructions
+        .into_iter()
+        .filter(|(program_id_index, _, _)| {
+            accounts.get(*program_id_index as usize) == Some(&SYSTEM_PROGRAM_ID)
+        })
+        .filter(|(_, instruction_accounts, _)| {
+            instruction_accounts
+                .get(1)
+                .and_then(|index| accounts.get(*index as usize))
+                .is_some_and(|destination| tip_accounts.contains(destination))
+        })
+        .filter_map(|(_, _, data)| parse_system_transfer(data))
+        .fold(0, u64::saturating_add)
+}
+
+/// Returns tip percentiles over the lookback window, in lamports.
+///
+/// With accounts, percentiles are taken over the tips of transactions writing to each account and
+/// the highest value of each percentile is returned. Without accounts all tips are used. Returns
+/// zeros when no tips were paid.
+pub fn tip_estimates(
+    priority_fees: &PriorityFeesBySlot,
+    accounts: &[Pubkey],
+    lookback_period: &Option<u32>,
+    options: &CalculationOptions,
+) -> LamportTipEstimates {
+    let slots_vec = select_lookback_slots(priority_fees, lookback_period, options);
+
+    let mut samples: Vec<Vec<f64>> = vec![Vec::new(); accounts.len().max(1)];
+    for slot in &slots_vec {
+        let Some(slot_priority_fees) = priority_fees.get(slot) else {
+            continue;
+        };
+        if accounts.is_empty() {
+            samples[0].extend(&slot_priority_fees.tips);
+            continue;
+        }
+        for (account, tips) in accounts.iter().zip(samples.iter_mut()) {
+            if let Some(account_tips) = slot_priority_fees.account_tips.get(account) {
+                tips.extend(account_tips.iter());
+            }
+        }
+    }
+
+    samples
+        .into_iter()
+        .filter(|tips| !tips.is_empty())
+        .map(|tips| {
+            let mut tips = FeeSamples::new(tips);
+            LamportTipEstimates {
+                min: tips.percentile(0),
+                low: tips.percentile(25),
+                medium: tips.percentile(50),
+                high: tips.percentile(75),
+                very_high: tips.percentile(95),
+                unsafe_max: tips.percentile(100),
+            }
+        })
+        .reduce(|a, b| a.zip_with(&b, f64::max))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::SlotPriorityFees;
+
+    fn transfer_data(lamports: u64) -> Vec<u8> {
+        let mut data = SYSTEM_TRANSFER_DISCRIMINANT.to_le_bytes().to_vec();
+        data.extend(lamports.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn test_transaction_tip() {
+        let payer = Pubkey::new_unique();
+        let tip_account = Pubkey::new_unique();
+        let other = Pubkey::new_unique();
+        let program = Pubkey::new_unique();
+        let accounts = vec![payer, tip_account, other, SYSTEM_PROGRAM_ID, program];
+        let tip_accounts = HashSet::from([tip_account]);
+
+        let tip = transfer_data(10_000);
+        let transfer = transfer_data(5_000_000);
+        let mut create_account = transfer_data(1_000);
+        create_account[0] = 0;
+        let instructions: Vec<(u8, &[u8], &[u8])> = vec![
+            (3, &[0, 1], &tip),
+            (3, &[0, 1], &tip),
+            // transfer to a regular account
+            (3, &[0, 2], &transfer),
+            // not a transfer
+            (3, &[0, 1], &create_account),
+            // not the system program
+            (4, &[0, 1], &tip),
+            // truncated data and out of range accounts
+            (3, &[0, 1], &tip[..8]),
+            (3, &[0, 9], &tip),
+        ];
+
+        assert_eq!(
+            transaction_tip(&accounts, instructions, &tip_accounts),
+            20_000
+        );
+        assert_eq!(parse_system_transfer(&transfer), Some(5_000_000));
+        assert_eq!(parse_system_transfer(&[]), None);
+    }
+
+    #[test]
+    fn test_tip_estimates() {
+        let priority_fees = PriorityFeesBySlot::default();
+        let account_1 = Pubkey::new_unique();
+        let account_2 = Pubkey::new_unique();
+        let mut slot_fees = SlotPriorityFees::new_tip(1, vec![account_1], 1_000);
+        slot_fees.add_tip(vec![account_1, account_2], 3_000);
+        slot_fees.add_tip(vec![account_2], 100_000);
+        priority_fees.insert(1, slot_fees);
+
+        let options = CalculationOptions::default();
+        let estimates = tip_estimates(&priority_fees, &[], &None, &options);
+        assert_eq!(estimates.min, 1_000.0);
+        assert_eq!(estimates.unsafe_max, 100_000.0);
+
+        let estimates = tip_estimates(&priority_fees, &[account_1], &None, &options);
+        assert_eq!(estimates.unsafe_max, 3_000.0);
+
+        // the highest value of each percentile across accounts
+        let estimates = tip_estimates(&priority_fees, &[account_1, account_2], &None, &options);
+        assert_eq!(estimates.min, 3_000.0);
+        assert_eq!(estimates.unsafe_max, 100_000.0);
+
+        let estimates = tip_estimates(&priority_fees, &[Pubkey::new_unique()], &None, &options);
+        assert_eq!(estimates.unsafe_max, 0.0);
+    }
+}
@@ -1,25 +1,42 @@
 //! Priority Fee Tracker: Core tracking and estimation logic.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use dashmap::mapref::entry::Entry;
 use solana_sdk::pubkey::Pubkey;
 use solana_sdk::slot_history::Slot;
-use statrs::statistics::{Distribution, OrderStatistics};
+use statrs::statistics::Distribution;
 
-use crate::calculation::{Calculations, DataStats};
+use crate::calculation::{
+    select_lookback_slots, CalculationOptions, Calculations, DataStats, StatusStats,
+};
+use crate::confidence::bootstrap_intervals;
+use crate::congestion;
+use crate::forecast;
+use crate::leader::LeaderFeeHistory;
 use crate::model::{
-    Fees, MicroLamportPriorityFeeDetails, MicroLamportPriorityFeeEstimates, PriorityFeesBySlot,
-    SlotPriorityFees,
+    BlockMetadata, DataType, LamportTipEstimates, LeaderSlotFees, MicroLamportPriorityFeeDetails,
+    MicroLamportPriorityFeeEstimates, PriorityFeesBySlot, SlotCommitment, SlotFeeBreakdown,
+    SlotFeeForecast, SlotPriorityFees, SlotSummary,
 };
-use crate::slot_cache::SlotCache;
+use crate::samples::FeeSamples;
+use crate::slot_cache::{SkippedSlots, SlotCache};
+use crate::tips;
+
+/// Maximum gap between a block and its parent that is recorded as skipped slots.
+///
+/// Larger gaps are ignored, they usually mean the parent link is not trustworthy.
+pub const MAX_SKIPPED_SLOTS_PER_BLOCK: Slot = 64;
 
 /// Tracks priority fees across slots and provides estimation methods.
 #[derive(Debug, Clone)]
 pub struct PriorityFeeTracker {
     priority_fees: Arc<PriorityFeesBySlot>,
     slot_cache: SlotCache,
+    skipped_slots: SkippedSlots,
+    leader_history: LeaderFeeHistory,
 }
 
 impl PriorityFeeTracker {
@@ -28,6 +45,8 @@ impl PriorityFeeTracker {
         Self {
             priority_fees: Arc::new(PriorityFeesBySlot::default()),
             slot_cache: SlotCache::new(slot_cache_length),
+            skipped_slots: SkippedSlots::new(slot_cache_length),
+            leader_history: LeaderFeeHistory::default(),
         }
     }
 
@@ -49,38 +68,495 @@ impl PriorityFeeTracker {
         priority_fee: u64,
         is_vote: bool,
     ) {
-        // Update the slot cache
-        if let Some(oldest_slot) = self.slot_cache.push_pop(slot) {
-            self.priority_fees.remove(&oldest_slot);
+        self.push_payer_priority_fee_for_txn(slot, None, accounts, priority_fee, is_vote);
+    }
+
+    /// Pushes a priority fee for a transaction paid by `payer` into the tracker.
+    pub fn push_payer_priority_fee_for_txn(
+        &self,
+        slot: Slot,
+        payer: Option<Pubkey>,
+        accounts: Vec<Pubkey>,
+        priority_fee: u64,
+        is_vote: bool,
+    ) {
+        if !self.track_slot(slot) {
+            return;
         }
 
         // Update or insert priority fees for this slot
+        self.priority_fees
+            .entry(slot)
+            .or_insert_with(|| SlotPriorityFees::empty(slot))
+            .add_payer_fee(accounts, priority_fee, is_vote, payer);
+    }
+
+    /// Pushes the priority fee of a failed non-vote transaction into the tracker.
+    ///
+    /// Failed fees are kept apart from successful ones and only sampled when a calculation asks
+    /// for them.
+    pub fn push_failed_priority_fee_for_txn(
+        &self,
+        slot: Slot,
+        accounts: Vec<Pubkey>,
+        priority_fee: u64,
+    ) {
+        self.push_payer_failed_priority_fee_for_txn(slot, None, accounts, priority_fee);
+    }
+
+    /// Pushes the priority fee of a failed non-vote transaction paid by `payer` into the tracker.
+    pub fn push_payer_failed_priority_fee_for_txn(
+        &self,
+        slot: Slot,
+        payer: Option<Pubkey>,
+        accounts: Vec<Pubkey>,
+        priority_fee: u64,
+    ) {
+        if !self.track_slot(slot) {
+            return;
+        }
+
+        self.priority_fees
+            .entry(slot)
+            .or_insert_with(|| SlotPriorityFees::empty(slot))
+            .add_failed_payer_fee(accounts, priority_fee, payer);
+    }
+
+    /// Pushes the tip, in lamports, paid by a transaction writing to `accounts`.
+    ///
+    /// Tips are recorded on the entry created by the fees of their slot, so a transaction's tip is
+    /// pushed after its fee. Tips of slots without fees are dropped.
+    pub fn push_tip_for_txn(&self, slot: Slot, accounts: Vec<Pubkey>, tip: u64) {
+        if let Some(mut slot_fees) = self.priority_fees.get_mut(&slot) {
+            slot_fees.add_tip(accounts, tip);
+        }
+    }
+
+    /// Pushes the compute units consumed by a transaction writing to `accounts`.
+    pub fn push_compute_units_for_txn(
+        &self,
+        slot: Slot,
+        accounts: Vec<Pubkey>,
+        compute_units: u64,
+    ) {
+        if !self.track_slot(slot) {
+            return;
+        }
+
+        self.priority_fees
+            .entry(slot)
+            .or_insert_with(|| SlotPriorityFees::empty(slot))
+            .add_compute_units(accounts, compute_units);
+    }
+
+    /// Records block metadata for a slot, creating the slot entry if no fees were pushed yet.
+    ///
+    /// Slots between the block's parent and the block itself are recorded as skipped.
+    pub fn push_block_metadata(&self, slot: Slot, block: BlockMetadata) {
+        self.push_block_metadata_with_commitment(slot, block, SlotCommitment::Confirmed);
+    }
+
+    /// Records block metadata for a slot ingested at the given commitment, returning the number of
+    /// slots recorded as skipped.
+    ///
+    /// Processed slots are provisional until [`Self::confirm_slot`] promotes them or
+    /// [`Self::abandon_slot`] drops them.
+    pub fn push_block_metadata_with_commitment(
+        &self,
+        slot: Slot,
+        block: BlockMetadata,
+        commitment: SlotCommitment,
+    ) -> usize {
+        let parent_slot = block.parent_slot;
+
+        if !self.track_slot(slot) {
+            return 0;
+        }
+        self.skipped_slots.remove(slot);
+
         match self.priority_fees.entry(slot) {
             Entry::Occupied(mut entry) => {
                 let slot_fees = entry.get_mut();
-                slot_fees.fees.add_fee(priority_fee as f64, is_vote);
-                for account in accounts {
-                    slot_fees
-                        .account_fees
-                        .entry(account)
-                        .and_modify(|fees| fees.add_fee(priority_fee as f64, is_vote))
-                        .or_insert(Fees::new(priority_fee as f64, is_vote));
-                }
+                slot_fees.block = Some(block);
+                slot_fees.commitment = slot_fees.commitment.max(commitment);
             }
             Entry::Vacant(entry) => {
-                entry.insert(SlotPriorityFees::new(slot, accounts, priority_fee, is_vote));
+                let mut slot_fees = SlotPriorityFees::from_block_metadata(slot, block);
+                slot_fees.commitment = commitment;
+                entry.insert(slot_fees);
+            }
+        }
+
+        if slot > parent_slot && slot - parent_slot <= MAX_SKIPPED_SLOTS_PER_BLOCK {
+            (parent_slot + 1..slot)
+                .filter(|skipped_slot| self.push_skipped_slot(*skipped_slot))
+                .count()
+        } else {
+            0
+        }
+    }
+
+    /// Records a slot that was skipped by its leader, unless the slot is already tracked.
+    ///
+    /// Skipped slots are kept apart from the slot window: they are listed by the slot summaries but
+    /// never count towards the lookback or as empty slots. Returns true if the slot was recorded.
+    pub fn push_skipped_slot(&self, slot: Slot) -> bool {
+        !self.priority_fees.contains_key(&slot) && self.skipped_slots.insert(slot)
+    }
+
+    /// Adds a slot to the slot cache, dropping the fees of the slot it evicts and the skipped
+    /// slots older than it. Returns false if the slot is older than every slot of a full cache
+    /// and is not tracked.
+    fn track_slot(&self, slot: Slot) -> bool {
+        match self.slot_cache.push_pop(slot) {
+            Some(oldest_slot) => {
+                self.priority_fees.remove(&oldest_slot);
+                self.skipped_slots.remove_older_than(oldest_slot);
+                oldest_slot != slot
             }
+            None => true,
         }
     }
 
+    /// Promotes a processed slot to confirmed, returning the number of processed slots abandoned.
+    ///
+    /// Processed slots older than the confirmed slot that are not on its chain, following the
+    /// parents of the tracked blocks, belonged to an abandoned fork and are dropped. Slots between
+    /// the confirmed slot and its parent are recorded as skipped.
+    pub fn confirm_slot(&self, slot: Slot, parent_slot: Option<Slot>) -> usize {
+        let promoted = self
+            .priority_fees
+            .get_mut(&slot)
+            .is_some_and(|mut slot_fees| {
+                let processed = slot_fees.commitment == SlotCommitment::Processed;
+                slot_fees.commitment = SlotCommitment::Confirmed;
+                processed
+            });
+        // the leader of a processed slot is only recorded once the slot is on the confirmed chain
+        if promoted {
+            self.record_leader_slot(slot);
+        }
+        let skipped = match parent_slot {
+            Some(parent_slot)
+                if slot > parent_slot && slot - parent_slot <= MAX_SKIPPED_SLOTS_PER_BLOCK =>
+            {
+                (parent_slot + 1..slot)
+                    .filter(|abandoned_slot| self.abandon_slot(*abandoned_slot))
+                    .count()
+            }
+            _ => 0,
+        };
+        let chain = self.chain_slots(slot, parent_slot);
+        let forks: Vec<Slot> = self
+            .priority_fees
+            .iter()
+            .filter(|entry| {
+                entry.commitment == SlotCommitment::Processed
+                    && entry.slot < slot
+                    && !chain.contains(&entry.slot)
+            })
+            .map(|entry| entry.slot)
+            .collect();
+        skipped
+            + forks
+                .into_iter()
+                .filter(|fork_slot| self.abandon_slot(*fork_slot))
+                .count()
+    }
+
+    /// Returns `slot` and its ancestors, following `parent_slot` and then the parents of the
+    /// tracked blocks.
+    fn chain_slots(&self, slot: Slot, parent_slot: Option<Slot>) -> HashSet<Slot> {
+        let block_parent = |slot: Slot| {
+            self.priority_fees
+                .get(&slot)
+                .and_then(|slot_fees| slot_fees.block.as_ref().map(|block| block.parent_slot))
+                .filter(|parent_slot| *parent_slot < slot)
+        };
+        let mut chain = HashSet::from([slot]);
+        let mut parent_slot = parent_slot.or_else(|| block_parent(slot));
+        while let Some(slot) = parent_slot.filter(|parent_slot| chain.insert(*parent_slot)) {
+            parent_slot = block_parent(slot);
+        }
+        chain
+    }
+
+    /// Drops the provisional fees of a slot that will never be confirmed and records it as skipped.
+    ///
+    /// Confirmed slots are left untouched. Returns true if provisional fees were dropped.
+    pub fn abandon_slot(&self, slot: Slot) -> bool {
+        // the shard lock keeps concurrent pushes from landing between the check and the removal
+        let abandoned = self
+            .priority_fees
+            .remove_if(&slot, |_, slot_fees| {
+                slot_fees.commitment == SlotCommitment::Processed
+            })
+            .is_some();
+        if abandoned {
+            self.slot_cache.remove(slot);
+        }
+        self.push_skipped_slot(slot);
+        abandoned
+    }
+
+    /// Records the leader of a tracked slot. Untracked slots are ignored.
+    pub fn push_slot_leader(&self, slot: Slot, leader: Pubkey) {
+        if let Some(mut slot_fees) = self.priority_fees.get_mut(&slot) {
+            slot_fees.leader = Some(leader);
+        }
+    }
+
+    /// Adds the fees of a slot to the history of its leader, if the leader is known.
+    ///
+    /// Call once all transactions of the slot were pushed. Processed slots are skipped, their
+    /// leader is recorded when [`Self::confirm_slot`] promotes them.
+    pub fn record_leader_slot(&self, slot: Slot) {
+        let Some(slot_fees) = self
+            .priority_fees
+            .get(&slot)
+            .filter(|slot_fees| slot_fees.commitment != SlotCommitment::Processed)
+        else {
+            return;
+        };
+        if let Some(leader) = slot_fees.leader {
+            self.leader_history
+                .record(leader, LeaderSlotFees::from_fees(slot, &slot_fees.fees));
+        }
+    }
+
+    /// Returns the fee statistics of the most recent slots produced by `leader`, oldest first.
+    pub fn leader_fee_history(&self, leader: &Pubkey) -> Vec<LeaderSlotFees> {
+        self.leader_history.history(leader)
+    }
+
+    /// Returns the summary for the given slot, or for the newest tracked slot if none is given.
+    pub fn slot_summary(&self, slot: Option<Slot>) -> Option<SlotSummary> {
+        let slot = match slot {
+            Some(slot) => slot,
+            None => self.newest_slot()?,
+        };
+        match self.priority_fees.get(&slot) {
+            Some(entry) => Some(entry.summary()),
+            None => self
+                .skipped_slots
+                .contains(slot)
+                .then(|| SlotSummary::skipped(slot)),
+        }
+    }
+
+    /// Returns the newest tracked slot.
+    pub fn newest_slot(&self) -> Option<Slot> {
+        self.priority_fees.iter().map(|entry| entry.slot).max()
+    }
+
+    /// Returns summaries for the slots selected by the lookback window, newest first, together
+    /// with the skipped slots between them.
+    pub fn slot_summaries(
+        &self,
+        lookback_period: &Option<u32>,
+        options: &CalculationOptions,
+    ) -> Vec<SlotSummary> {
+        let mut slots = select_lookback_slots(&self.priority_fees, lookback_period, options);
+        if let (Some(&newest_slot), Some(&oldest_slot)) = (slots.first(), slots.last()) {
+            self.skipped_slots
+                .copy_range(oldest_slot, newest_slot, &mut slots);
+            slots.sort_unstable_by(|a, b| b.cmp(a));
+        }
+        slots
+            .into_iter()
+            .filter_map(|slot| self.slot_summary(Some(slot)))
+            .collect()
+    }
+
+    /// Returns the fees recorded for `account`, or the global fees, in the given slot.
+    pub fn slot_fee_breakdown(
+        &self,
+        account: Option<&Pubkey>,
+        slot: Slot,
+        max_raw_fees: usize,
+    ) -> Option<SlotFeeBreakdown> {
+        match self.priority_fees.get(&slot) {
+            Some(entry) => Some(entry.fee_breakdown(account, max_raw_fees)),
+            None => self
+                .skipped_slots
+                .contains(slot)
+                .then(|| SlotFeeBreakdown::skipped(slot, account)),
+        }
+    }
+
+    /// Returns the fees recorded for `account`, or the global fees, in the tracked slots between
+    /// `start_slot` and `end_slot` inclusive, newest first. Skipped slots are included.
+    pub fn slot_fee_breakdowns(
+        &self,
+        account: Option<&Pubkey>,
+        start_slot: Option<Slot>,
+        end_slot: Option<Slot>,
+        max_raw_fees: usize,
+    ) -> Vec<SlotFeeBreakdown> {
+        let start_slot = start_slot.unwrap_or(Slot::MIN);
+        let end_slot = end_slot.unwrap_or(Slot::MAX);
+        let mut breakdowns: Vec<SlotFeeBreakdown> = self
+            .priority_fees
+            .iter()
+            .filter(|entry| (start_slot..=end_slot).contains(&entry.slot))
+            .map(|entry| entry.fee_breakdown(account, max_raw_fees))
+            .collect();
+        let mut skipped_slots = Vec::new();
+        self.skipped_slots
+            .copy_range(start_slot, end_slot, &mut skipped_slots);
+        breakdowns.extend(
+            skipped_slots
+                .into_iter()
+                .map(|slot| SlotFeeBreakdown::skipped(slot, account)),
+        );
+        breakdowns.sort_unstable_by(|a, b| b.slot.cmp(&a.slot));
+        breakdowns
+    }
+
+    /// Returns the tracked slots, newest first.
+    pub fn tracked_slots(&self) -> Vec<Slot> {
+        let mut slots = Vec::with_capacity(self.slot_cache.len());
+        self.slot_cache.copy_slots(&mut slots);
+        slots.sort_unstable_by(|a, b| b.cmp(a));
+        slots
+    }
+
+    /// Calls `inspect` with the fees of a tracked slot, returning `None` if the slot is not
+    /// tracked.
+    pub fn inspect_slot<R>(
+        &self,
+        slot: Slot,
+        inspect: impl FnOnce(&SlotPriorityFees) -> R,
+    ) -> Option<R> {
+        self.priority_fees
+            .get(&slot)
+            .map(|slot_fees| inspect(&slot_fees))
+    }
+
+    /// Removes a slot and its fees, returning true if it was tracked or recorded as skipped.
+    pub fn evict_slot(&self, slot: Slot) -> bool {
+        let in_cache = self.slot_cache.remove(slot);
+        let in_fees = self.priority_fees.remove(&slot).is_some();
+        let skipped = self.skipped_slots.remove(slot);
+        in_cache || in_fees || skipped
+    }
+
+    /// Removes every tracked and skipped slot, returning the number removed. Leader fee history is
+    /// kept.
+    pub fn clear(&self) -> usize {
+        let mut slots = Vec::with_capacity(self.priority_fees.len());
+        self.slot_cache.copy_slots(&mut slots);
+        slots.extend(self.priority_fees.iter().map(|entry| entry.slot));
+        slots.sort_unstable();
+        slots.dedup();
+        let evicted = slots
+            .into_iter()
+            .filter(|slot| self.evict_slot(*slot))
+            .count();
+        evicted + self.skipped_slots.clear()
+    }
+
+    /// Returns the number of slots in the window.
+    pub fn tracked_slot_count(&self) -> usize {
+        self.slot_cache.len()
+    }
+
+    /// Removes slots whose block time is older than `max_age`, returning the number removed.
+    ///
+    /// Slots without a known block time are kept. Skipped slots older than every remaining slot are
+    /// removed as well.
+    pub fn remove_slots_older_than(&self, max_age: Duration) -> usize {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs() as i64)
+            .unwrap_or_default();
+        let oldest_block_time = now - max_age.as_secs() as i64;
+        let stale_slots: Vec<Slot> = self
+            .priority_fees
+            .iter()
+            .filter(|entry| {
+                entry
+                    .block
+                    .as_ref()
+                    .and_then(|block| block.block_time)
+                    .is_some_and(|block_time| block_time < oldest_block_time)
+            })
+            .map(|entry| entry.slot)
+            .collect();
+        for slot in &stale_slots {
+            self.slot_cache.remove(*slot);
+            self.priority_fees.remove(slot);
+        }
+        let skipped = match self.priority_fees.iter().map(|entry| entry.slot).min() {
+            Some(oldest_slot) => self.skipped_slots.remove_older_than(oldest_slot),
+            None => self.skipped_slots.clear(),
+        };
+        stale_slots.len() + skipped
+    }
+
+    /// Forecasts fee percentiles for the next `horizon` slots from the per-slot fees of the given
+    /// accounts, or of all transactions when `accounts` is empty.
+    pub fn forecast_fees(
+        &self,
+        accounts: &[Pubkey],
+        include_vote: bool,
+        lookback_period: &Option<u32>,
+        options: &CalculationOptions,
+        horizon: u32,
+    ) -> Vec<SlotFeeForecast> {
+        forecast::forecast_fees(
+            &self.priority_fees,
+            accounts,
+            include_vote,
+            lookback_period,
+            options,
+            horizon,
+        )
+    }
+
+    /// Returns tip percentiles, in lamports, of the transactions writing to the given accounts, or
+    /// of all transactions when `accounts` is empty.
+    pub fn tip_estimates(
+        &self,
+        accounts: &[Pubkey],
+        lookback_period: &Option<u32>,
+        options: &CalculationOptions,
+    ) -> LamportTipEstimates {
+        tips::tip_estimates(&self.priority_fees, accounts, lookback_period, options)
+    }
+
+    /// Returns the congestion score of the global fees and of each of the given accounts.
+    pub fn congestion_scores<'a>(
+        &self,
+        accounts: &'a [Pubkey],
+        lookback_period: &Option<u32>,
+        options: &CalculationOptions,
+    ) -> HashMap<DataType<'a>, f64> {
+        congestion::congestion_scores(&self.priority_fees, accounts, lookback_period, options)
+    }
+
     /// Calculates priority fee estimates based on the provided calculation algorithm.
     pub fn calculate_priority_fee(
         &self,
         calculation: &Calculations,
     ) -> anyhow::Result<MicroLamportPriorityFeeEstimates> {
-        let mut data: DataStats = calculation.get_priority_fee_estimates(&self.priority_fees)?;
-        let estimates = estimate_max_values(&mut data, MicroLamportPriorityFeeEstimates::default());
-        Ok(estimates)
+        let data: DataStats = calculation.get_priority_fee_estimates(&self.priority_fees)?;
+        Ok(self.aggregate_estimates(data, calculation.lookback_period(), calculation.options()))
+    }
+
+    /// Combines the fees gathered by a calculation into a single estimate.
+    pub fn aggregate_estimates(
+        &self,
+        data: DataStats<'_>,
+        lookback_period: &Option<u32>,
+        options: &CalculationOptions,
+    ) -> MicroLamportPriorityFeeEstimates {
+        let entries: Vec<_> = data
+            .into_iter()
+            .map(|(key, mut fees)| (key, percentile_estimates(&mut fees), fees.len()))
+            .collect();
+        let estimates = options.aggregation.aggregate(&entries);
+        self.boost_leaders(estimates, lookback_period, options)
     }
 
     /// Calculates detailed priority fee estimates and statistics.
@@ -91,17 +567,41 @@ impl PriorityFeeTracker {
         MicroLamportPriorityFeeEstimates,
         HashMap<String, MicroLamportPriorityFeeDetails>,
     )> {
-        let mut data: DataStats = calculation.get_priority_fee_estimates(&self.priority_fees)?;
+        let stats = calculation.get_priority_fee_details(&self.priority_fees)?;
+        Ok(self.detail_estimates(
+            stats,
+            calculation.accounts(),
+            calculation.lookback_period(),
+            calculation.options(),
+        ))
+    }
+
+    /// Combines the fees gathered by a calculation into a single estimate, together with the
+    /// estimates and statistics of each data type.
+    pub fn detail_estimates(
+        &self,
+        stats: StatusStats<'_>,
+        accounts: &[Pubkey],
+        lookback_period: &Option<u32>,
+        options: &CalculationOptions,
+    ) -> (
+        MicroLamportPriorityFeeEstimates,
+        HashMap<String, MicroLamportPriorityFeeDetails>,
+    ) {
+        let StatusStats {
+            mut data,
+            succeeded,
+            mut failed,
+        } = stats;
+        let congestion =
+            congestion::congestion_scores(&self.priority_fees, accounts, lookback_period, options);
+        let mut intervals = bootstrap_intervals(&data);
         let mut res = HashMap::new();
+        let mut entries = Vec::with_capacity(data.len());
         for (key, fees) in data.iter_mut() {
-            let estimates = MicroLamportPriorityFeeEstimates {
-                min: fees.percentile(0),
-                low: fees.percentile(25),
-                medium: fees.percentile(50),
-                high: fees.percentile(75),
-                very_high: fees.percentile(95),
-                unsafe_max: fees.percentile(100),
-            };
+            let failed_fees = failed.get_mut(key);
+            let estimates = percentile_estimates(fees);
+            entries.push((key.clone(), estimates.clone(), fees.len()));
             res.insert(
                 key.to_string(),
                 MicroLamportPriorityFeeDetails {
@@ -110,51 +610,55 @@ impl PriorityFeeTracker {
                     stdev: fees.std_dev().unwrap_or(f64::NAN),
                     skew: fees.skewness().unwrap_or(f64::NAN),
                     count: fees.len(),
+                    success_count: succeeded.get(key).map_or(0, |fees| fees.len()),
+                    failed_count: failed_fees.as_ref().map_or(0, |fees| fees.len()),
+                    failed_estimates: failed_fees
+                        .filter(|fees| !fees.is_empty())
+                        .map(percentile_estimates)
+                        .unwrap_or_default(),
+                    outliers_removed: fees.outliers(),
+                    confidence_interval: intervals.remove(key),
+                    fallback: fees.is_fallback().then(|| DataType::Global.to_string()),
+                    congestion: congestion.get(key).copied().unwrap_or_default(),
                 },
             );
         }
-        let estimates = estimate_max_values(&mut data, MicroLamportPriorityFeeEstimates::default());
-        Ok((estimates, res))
+        let estimates = options.aggregation.aggregate(&entries);
+        (self.boost_leaders(estimates, lookback_period, options), res)
     }
-}
 
-fn estimate_max_values(
-    fees: &mut DataStats,
-    mut estimates: MicroLamportPriorityFeeEstimates,
-) -> MicroLamportPriorityFeeEstimates {
-    for data in fees.values_mut() {
-        let min = data.percentile(0);
-        let low = data.percentile(25);
-        let medium = data.percentile(50);
-        let high = data.percentile(75);
-        let very_high = data.percentile(95);
-        let max = data.percentile(100);
+    /// Blends the fee history of the leaders boosted by `options`, if any, into the aggregated
+    /// `estimates`.
+    fn boost_leaders(
+        &self,
+        estimates: MicroLamportPriorityFeeEstimates,
+        lookback_period: &Option<u32>,
+        options: &CalculationOptions,
+    ) -> MicroLamportPriorityFeeEstimates {
+        let Some(boost) = &options.leader_boost else {
+            return estimates;
+        };
+        let window_slots =
+            select_lookback_slots(&self.priority_fees, lookback_period, options).len();
+        self.leader_history.boost(estimates, window_slots, boost)
+    }
+}
 
-        if min > estimates.min || estimates.min.is_nan() {
-            estimates.min = min;
-        }
-        if low > estimates.low || estimates.low.is_nan() {
-            estimates.low = low;
-        }
-        if medium > estimates.medium || estimates.medium.is_nan() {
-            estimates.medium = medium;
-        }
-        if high > estimates.high || estimates.high.is_nan() {
-            estimates.high = high;
-        }
-        if very_high > estimates.very_high || estimates.very_high.is_nan() {
-            estimates.very_high = very_high;
-        }
-        if max > estimates.unsafe_max || estimates.unsafe_max.is_nan() {
-            estimates.unsafe_max = max;
-        }
+fn percentile_estimates(fees: &mut FeeSamples) -> MicroLamportPriorityFeeEstimates {
+    MicroLamportPriorityFeeEstimates {
+        min: fees.percentile(0),
+        low: fees.percentile(25),
+        medium: fees.percentile(50),
+        high: fees.percentile(75),
+        very_high: fees.percentile(95),
+        unsafe_max: fees.percentile(100),
     }
-    estimates
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::model::{OutlierFilter, PayerDedup, TransactionStatusFilter};
 
     #[test]
     fn test_priority_fee_tracker_basic() {
@@ -183,4 +687,523 @@ mod tests {
         assert_eq!(estimates.very_high, 96.0);
         assert_eq!(estimates.unsafe_max, 100.0);
     }
+
+    #[test]
+    fn test_block_metadata_summary() {
+        let tracker = PriorityFeeTracker::new(10);
+        let account = Pubkey::new_unique();
+
+        tracker.push_priority_fee_for_txn(2, vec![account], 100, false);
+        tracker.push_priority_fee_for_txn(2, vec![account], 0, true);
+        tracker.push_block_metadata(
+            2,
+            BlockMetadata {
+                block_time: Some(1_700_000_000),
+                block_height: Some(42),
+                parent_slot: 1,
+                blockhash: "hash".to_string(),
+                executed_transaction_count: 3,
+                compute_units_consumed: 1_000,
+            },
+        );
+        // a block without any transactions still registers the slot
+        tracker.push_block_metadata(
+            3,
+            BlockMetadata {
+                parent_slot: 2,
+                ..Default::default()
+            },
+        );
+
+        let newest = tracker.slot_summary(None).expect("newest slot summary");
+        assert_eq!(newest.slot, 3);
+        assert_eq!(newest.non_vote_transaction_count, 0);
+
+        let summary = tracker.slot_summary(Some(2)).expect("slot 2 summary");
+        assert_eq!(summary.vote_transaction_count, 1);
+        assert_eq!(summary.non_vote_transaction_count, 1);
+        assert_eq!(summary.account_count, 1);
+        let block = summary.block.expect("block metadata");
+        assert_eq!(block.block_height, Some(42));
+        assert_eq!(block.compute_units_consumed, 1_000);
+
+        assert!(tracker.slot_summary(Some(4)).is_none());
+        let slots: Vec<Slot> = tracker
+            .slot_summaries(&Some(1), &CalculationOptions::default())
+            .iter()
+            .map(|summary| summary.slot)
+            .collect();
+        assert_eq!(slots, vec![3]);
+    }
+
+    #[test]
+    fn test_skipped_slots_from_parent_links() {
+        let tracker = PriorityFeeTracker::new(4);
+        let account = Pubkey::new_unique();
+
+        for (slot, parent_slot) in [(10, 9), (13, 10)] {
+            tracker.push_block_metadata(
+                slot,
+                BlockMetadata {
+                    parent_slot,
+                    ..Default::default()
+                },
+            );
+            tracker.push_priority_fee_for_txn(slot, vec![account], 100, false);
+        }
+
+        let summaries = tracker.slot_summaries(&None, &CalculationOptions::default());
+        let skipped: Vec<(Slot, bool)> = summaries
+            .iter()
+            .map(|summary| (summary.slot, summary.skipped))
+            .collect();
+        assert_eq!(
+            skipped,
+            vec![(13, false), (12, true), (11, true), (10, false)]
+        );
+
+        // skipped slots are not padded as empty slots for the account
+        let accounts = vec![account];
+        let calc = Calculations::new_calculation2(&accounts, false, true, &None);
+        let (_, details) = tracker
+            .calculate_priority_fee_details(&calc)
+            .expect("calculation should succeed");
+        assert_eq!(details[&account.to_string()].count, 2);
+
+        // a block arriving for a slot marked as skipped clears the flag
+        tracker.push_block_metadata(
+            12,
+            BlockMetadata {
+                parent_slot: 10,
+                ..Default::default()
+            },
+        );
+        assert!(!tracker.slot_summary(Some(12)).unwrap().skipped);
+
+        // skipped slots take no room in the window
+        assert!(tracker.push_skipped_slot(14));
+        assert_eq!(tracker.tracked_slots(), vec![13, 12, 10]);
+        assert!(tracker.slot_summary(Some(14)).unwrap().skipped);
+
+        // evicting a slot from the window drops the skipped slots older than it
+        for slot in [15, 16, 17] {
+            tracker.push_priority_fee_for_txn(slot, vec![account], 100, false);
+        }
+        assert!(tracker.slot_summary(Some(11)).is_none());
+        assert!(tracker.slot_summary(Some(14)).is_some());
+    }
+
+    #[test]
+    fn test_out_of_order_slots_keep_newest_window() {
+        let tracker = PriorityFeeTracker::new(3);
+        let account = Pubkey::new_unique();
+        // slot 11 arrives before slot 10
+        for slot in [11, 10, 12] {
+            tracker.push_priority_fee_for_txn(slot, vec![account], slot * 100, false);
+        }
+        tracker.push_priority_fee_for_txn(13, vec![account], 1_300, false);
+        assert_eq!(tracker.tracked_slots(), vec![13, 12, 11]);
+        assert!(!tracker.priority_fees().contains_key(&10));
+
+        // a late slot older than the whole window is not tracked
+        tracker.push_priority_fee_for_txn(9, vec![account], 900, false);
+        tracker.push_block_metadata(
+            9,
+            BlockMetadata {
+                parent_slot: 8,
+                ..Default::default()
+            },
+        );
+        assert_eq!(tracker.tracked_slots(), vec![13, 12, 11]);
+        assert!(!tracker.priority_fees().contains_key(&9));
+    }
+
+    #[test]
+    fn test_skipped_slots_do_not_change_estimates() {
+        let account = Pubkey::new_unique();
+        let accounts = vec![account];
+        let estimate = |parent_gap: Slot| {
+            let tracker = PriorityFeeTracker::new(10);
+            let mut slot = 100;
+            for fee in [100, 200, 300] {
+                tracker.push_block_metadata(
+                    slot,
+                    BlockMetadata {
+                        parent_slot: slot - parent_gap,
+                        ..Default::default()
+                    },
+                );
+                tracker.push_priority_fee_for_txn(slot, vec![account], fee, false);
+                tracker.push_priority_fee_for_txn(slot, vec![Pubkey::new_unique()], 10, false);
+                slot += parent_gap;
+            }
+            let calc = Calculations::new_calculation2(&accounts, false, true, &None);
+            tracker
+                .calculate_priority_fee(&calc)
+                .expect("calculation should succeed")
+        };
+
+        let contiguous = estimate(1);
+        let with_gaps = estimate(3);
+        assert_eq!(contiguous.min, with_gaps.min);
+        assert_eq!(contiguous.medium, with_gaps.medium);
+        assert_eq!(contiguous.unsafe_max, with_gaps.unsafe_max);
+    }
+
+    #[test]
+    fn test_processed_slots() {
+        let tracker = PriorityFeeTracker::new(10);
+        let account = Pubkey::new_unique();
+
+        // slots 11 and 12 are on different forks built on slot 10
+        for (slot, fee) in [(10, 100), (11, 1_000_000), (12, 200)] {
+            tracker.push_block_metadata_with_commitment(
+                slot,
+                BlockMetadata {
+                    parent_slot: 10.min(slot - 1),
+                    ..Default::default()
+                },
+                SlotCommitment::Processed,
+            );
+            tracker.push_priority_fee_for_txn(slot, vec![account], fee, false);
+        }
+
+        let processed = CalculationOptions {
+            commitment: SlotCommitment::Processed,
+            ..Default::default()
+        };
+        let confirmed = CalculationOptions::default();
+        assert_eq!(tracker.slot_summaries(&None, &processed).len(), 3);
+        assert!(tracker.slot_summaries(&None, &confirmed).is_empty());
+
+        tracker.confirm_slot(10, Some(9));
+        tracker.confirm_slot(12, Some(10));
+
+        let summaries = tracker.slot_summaries(&None, &confirmed);
+        let slots: Vec<(Slot, bool)> = summaries
+            .iter()
+            .map(|summary| (summary.slot, summary.skipped))
+            .collect();
+        assert_eq!(slots, vec![(12, false), (11, true), (10, false)]);
+
+        let accounts = vec![account];
+        let calc = Calculations::new_calculation2(&accounts, false, false, &None);
+        let estimates = tracker
+            .calculate_priority_fee(&calc)
+            .expect("calculation should succeed");
+        assert_eq!(estimates.unsafe_max, 200.0);
+
+        // confirmed slots are never dropped
+        tracker.abandon_slot(12);
+        assert!(!tracker.slot_summary(Some(12)).unwrap().skipped);
+    }
+
+    #[test]
+    fn test_confirm_slot_drops_distant_forks() {
+        let tracker = PriorityFeeTracker::new(10);
+        let processed = |slot: Slot, parent_slot: Slot| {
+            tracker.push_block_metadata_with_commitment(
+                slot,
+                BlockMetadata {
+                    parent_slot,
+                    ..Default::default()
+                },
+                SlotCommitment::Processed,
+            );
+        };
+
+        // slot 12 forks off slot 10, the confirmed chain continues from 11 to 100
+        processed(10, 9);
+        processed(11, 10);
+        processed(12, 10);
+        processed(100, 11);
+        assert_eq!(tracker.confirm_slot(10, Some(9)), 0);
+        assert_eq!(tracker.confirm_slot(11, Some(10)), 0);
+        // the gap to slot 100 is too large to be recorded as skipped slots
+        assert_eq!(tracker.confirm_slot(100, Some(11)), 1);
+
+        assert_eq!(tracker.tracked_slots(), vec![100, 11, 10]);
+        assert!(tracker.slot_summary(Some(12)).unwrap().skipped);
+        assert!(tracker
+            .priority_fees()
+            .iter()
+            .all(|entry| entry.commitment == SlotCommitment::Confirmed));
+    }
+
+    #[test]
+    fn test_failed_transactions() {
+        let tracker = PriorityFeeTracker::new(10);
+        let account = Pubkey::new_unique();
+        let accounts = vec![account];
+
+        tracker.push_priority_fee_for_txn(1, accounts.clone(), 100, false);
+        tracker.push_priority_fee_for_txn(1, accounts.clone(), 200, false);
+        tracker.push_failed_priority_fee_for_txn(1, accounts.clone(), 10_000);
+        // slot 2 only saw a failed transaction
+        tracker.push_failed_priority_fee_for_txn(2, accounts.clone(), 20_000);
+
+        let calc = Calculations::new_calculation2(&accounts, false, true, &None);
+        let estimates = tracker
+            .calculate_priority_fee(&calc)
+            .expect("calculation should succeed");
+        assert_eq!(estimates.unsafe_max, 200.0);
+
+        let include_failed = calc.clone().with_options(CalculationOptions {
+            transaction_status: TransactionStatusFilter::All,
+            ..Default::default()
+        });
+        let estimates = tracker
+            .calculate_priority_fee(&include_failed)
+            .expect("calculation should succeed");
+        assert_eq!(estimates.unsafe_max, 20_000.0);
+
+        let (_, details) = tracker
+            .calculate_priority_fee_details(&calc)
+            .expect("calculation should succeed");
+        let account_details = &details[&account.to_string()];
+        // the empty slot 2 is padded with a zero fee
+        assert_eq!(account_details.count, 3);
+        assert_eq!(account_details.success_count, 2);
+        assert_eq!(account_details.failed_count, 2);
+        assert_eq!(account_details.failed_estimates.min, 10_000.0);
+        assert_eq!(account_details.failed_estimates.unsafe_max, 20_000.0);
+
+        // without padding the succeeded fees are the estimate's own fees
+        let unpadded = Calculations::new_calculation2(&accounts, false, false, &None);
+        let (_, details) = tracker
+            .calculate_priority_fee_details(&unpadded)
+            .expect("calculation should succeed");
+        let account_details = &details[&account.to_string()];
+        assert_eq!(account_details.count, 2);
+        assert_eq!(account_details.success_count, 2);
+        assert_eq!(account_details.failed_count, 2);
+
+        let summary = tracker.slot_summary(Some(1)).unwrap();
+        assert_eq!(summary.non_vote_transaction_count, 2);
+        assert_eq!(summary.failed_transaction_count, 1);
+    }
+
+    #[test]
+    fn test_status_counts_ignore_outlier_filter() {
+        let tracker = PriorityFeeTracker::new(10);
+        let account = Pubkey::new_unique();
+        let accounts = vec![account];
+
+        for fee in 1..=20 {
+            tracker.push_priority_fee_for_txn(1, accounts.clone(), fee * 100, false);
+        }
+        for fee in 1..=10 {
+            tracker.push_failed_priority_fee_for_txn(1, accounts.clone(), fee * 1_000);
+        }
+
+        let calc = Calculations::new_calculation2(&accounts, false, false, &None).with_options(
+            CalculationOptions {
+                outlier_filter: Some(OutlierFilter::Trim(10)),
+                ..Default::default()
+            },
+        );
+        let (_, details) = tracker
+            .calculate_priority_fee_details(&calc)
+            .expect("calculation should succeed");
+        let account_details = &details[&account.to_string()];
+        // the estimate is trimmed, the transaction counts are not
+        assert!(account_details.outliers_removed > 0);
+        assert_eq!(account_details.count + account_details.outliers_removed, 20);
+        assert_eq!(account_details.success_count, 20);
+        assert_eq!(account_details.failed_count, 10);
+        assert_eq!(account_details.failed_estimates.min, 1_000.0);
+    }
+
+    #[test]
+    fn test_min_samples_keeps_status_fees() {
+        let tracker = PriorityFeeTracker::new(10);
+        let quiet = Pubkey::new_unique();
+        let other = Pubkey::new_unique();
+
+        for _ in 0..50 {
+            tracker.push_priority_fee_for_txn(1, vec![other], 10_000, false);
+        }
+        tracker.push_priority_fee_for_txn(1, vec![quiet], 100, false);
+        tracker.push_priority_fee_for_txn(1, vec![quiet], 200, false);
+        tracker.push_failed_priority_fee_for_txn(1, vec![quiet], 300);
+        tracker.push_failed_priority_fee_for_txn(1, vec![other], 50_000);
+
+        let accounts = vec![quiet];
+        let calc = Calculations::new_calculation2(&accounts, false, false, &None).with_options(
+            CalculationOptions {
+                min_samples: Some(10),
+                ..Default::default()
+            },
+        );
+        let (_, details) = tracker
+            .calculate_priority_fee_details(&calc)
+            .expect("calculation should succeed");
+        let quiet_details = &details[&quiet.to_string()];
+        // the estimate falls back to the global fees
+        assert_eq!(quiet_details.fallback.as_deref(), Some("Global"));
+        assert_eq!(quiet_details.count, 52);
+        assert_eq!(quiet_details.estimates.medium, 10_000.0);
+        // the status fees stay the account's own
+        assert_eq!(quiet_details.success_count, 2);
+        assert_eq!(quiet_details.failed_count, 1);
+        assert_eq!(quiet_details.failed_estimates.unsafe_max, 300.0);
+    }
+
+    #[test]
+    fn test_leader_fee_history() {
+        let tracker = PriorityFeeTracker::new(10);
+        let leader = Pubkey::new_unique();
+        let account = Pubkey::new_unique();
+
+        for slot in 1..=2 {
+            tracker.push_block_metadata(
+                slot,
+                BlockMetadata {
+                    parent_slot: slot - 1,
+                    ..Default::default()
+                },
+            );
+            tracker.push_slot_leader(slot, leader);
+            for fee in [100, 200, 300] {
+                tracker.push_priority_fee_for_txn(slot, vec![account], fee * slot, false);
+            }
+            tracker.record_leader_slot(slot);
+        }
+        // untracked slots keep no leader
+        tracker.push_slot_leader(20, leader);
+        tracker.record_leader_slot(20);
+
+        let history = tracker.leader_fee_history(&leader);
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].slot, 1);
+        assert_eq!(history[0].transaction_count, 3);
+        assert_eq!(history[0].estimates.medium, 200.0);
+        assert_eq!(history[1].estimates.medium, 400.0);
+        assert_eq!(
+            tracker.slot_summary(Some(1)).unwrap().leader,
+            Some(leader.to_string())
+        );
+        assert!(tracker.leader_fee_history(&account).is_empty());
+    }
+
+    #[test]
+    fn test_processed_leader_fee_history() {
+        let tracker = PriorityFeeTracker::new(10);
+        let leader = Pubkey::new_unique();
+        let account = Pubkey::new_unique();
+
+        // slots 11 and 12 are on different forks built on slot 10
+        for slot in [11, 12] {
+            tracker.push_block_metadata_with_commitment(
+                slot,
+                BlockMetadata {
+                    parent_slot: 10,
+                    ..Default::default()
+                },
+                SlotCommitment::Processed,
+            );
+            tracker.push_slot_leader(slot, leader);
+            tracker.push_priority_fee_for_txn(slot, vec![account], 100 * slot, false);
+            tracker.record_leader_slot(slot);
+        }
+        assert!(tracker.leader_fee_history(&leader).is_empty());
+
+        // the abandoned fork never reaches the leader's history
+        assert_eq!(tracker.confirm_slot(12, Some(10)), 1);
+        let history = tracker.leader_fee_history(&leader);
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].slot, 12);
+
+        // confirming again does not record the slot twice
+        tracker.confirm_slot(12, Some(10));
+        assert_eq!(tracker.leader_fee_history(&leader).len(), 1);
+    }
+
+    #[test]
+    fn test_payer_dedup() {
+        let tracker = PriorityFeeTracker::new(10);
+        let account = Pubkey::new_unique();
+        let bot = Pubkey::new_unique();
+
+        for fee in [100, 200, 300, 400] {
+            tracker.push_payer_priority_fee_for_txn(
+                1,
+                Some(Pubkey::new_unique()),
+                vec![account],
+                fee,
+                false,
+            );
+        }
+        for _ in 0..100 {
+            tracker.push_payer_priority_fee_for_txn(1, Some(bot), vec![account], 1_000_000, false);
+        }
+        tracker.push_payer_failed_priority_fee_for_txn(1, Some(bot), vec![account], 2_000_000);
+
+        let accounts = vec![account];
+        let calc = Calculations::new_calculation2(&accounts, false, false, &None);
+        let estimates = tracker
+            .calculate_priority_fee(&calc)
+            .expect("calculation should succeed");
+        assert_eq!(estimates.medium, 1_000_000.0);
+
+        let collapsed = calc.clone().with_options(CalculationOptions {
+            payer_dedup: Some(PayerDedup::Collapse),
+            ..Default::default()
+        });
+        let estimates = tracker
+            .calculate_priority_fee(&collapsed)
+            .expect("calculation should succeed");
+        assert_eq!(estimates.medium, 300.0);
+
+        // fees without a payer are kept as they are
+        tracker.push_priority_fee_for_txn(1, vec![account], 500, false);
+        let slot_fees = tracker.priority_fees().get(&1).unwrap();
+        let mut samples = Vec::new();
+        slot_fees.account_fees.get(&account).unwrap().collect_into(
+            &mut samples,
+            false,
+            TransactionStatusFilter::All,
+            Some(PayerDedup::Cap(2)),
+        );
+        assert_eq!(samples.len(), 7);
+        assert_eq!(samples.iter().filter(|fee| **fee >= 1_000_000.0).count(), 2);
+        assert_eq!(slot_fees.summary().payer_count, 5);
+    }
+
+    #[test]
+    fn test_remove_slots_older_than() {
+        let tracker = PriorityFeeTracker::new(10);
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        for (slot, age) in [(1, 120), (2, 30), (3, 1)] {
+            tracker.push_block_metadata(
+                slot,
+                BlockMetadata {
+                    block_time: Some(now - age),
+                    ..Default::default()
+                },
+            );
+        }
+        tracker.push_priority_fee_for_txn(4, vec![], 100, false);
+
+        assert_eq!(tracker.remove_slots_older_than(Duration::from_secs(60)), 1);
+        assert!(tracker.priority_fees().get(&1).is_none());
+        assert_eq!(tracker.slot_cache().len(), 3);
+
+        let slots: Vec<Slot> = tracker
+            .slot_summaries(
+                &None,
+                &CalculationOptions {
+                    lookback_seconds: Some(10),
+                    ..Default::default()
+                },
+            )
+            .iter()
+            .map(|summary| summary.slot)
+            .collect();
+        assert_eq!(slots, vec![3]);
+    }
 }
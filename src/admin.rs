@@ -0,0 +1,419 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use cadence_macros::statsd_count;
+use jsonrpsee::core::{async_trait, RpcResult};
+use jsonrpsee::proc_macros::rpc;
+use jsonrpsee::Extensions;
+use serde::{Deserialize, Serialize};
+use solana_sdk::clock::Slot;
+use solana_sdk::pubkey::Pubkey;
+use tracing::info;
+
+use crate::cluster::ClusterPath;
+use crate::config::{RuntimeConfig, RuntimeConfigStore};
+use crate::errors::invalid_request;
+use crate::health::IngestStatus;
+use crate::priority_fee::PriorityFeeTracker;
+use crate::{SlotPriorityFees, SlotSummary};
+
+/// Request object for the `admin_getSlotFees` method.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(
+    rename_all(serialize = "camelCase", deserialize = "camelCase"),
+    deny_unknown_fields
+)]
+pub struct GetSlotFeesRequest {
+    /// Tracked slot to dump.
+    pub slot: Slot,
+    /// Base58-encoded account whose fees are dumped.
+    pub account: String,
+}
+
+/// Raw fees recorded for an account in a slot.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all(serialize = "camelCase", deserialize = "camelCase"))]
+pub struct SlotAccountFees {
+    /// Slot number.
+    pub slot: Slot,
+    /// Base58-encoded account.
+    pub account: String,
+    /// Fees of the successful non-vote transactions writing to the account, in ingestion order.
+    pub non_vote_fees: Vec<f64>,
+    /// Fees of the vote transactions writing to the account.
+    pub vote_fees: Vec<f64>,
+    /// Fees of the failed non-vote transactions writing to the account.
+    pub failed_fees: Vec<f64>,
+    /// Base58-encoded fee payers of `non_vote_fees`, if known.
+    pub non_vote_payers: Vec<Option<String>>,
+    /// Tips, in lamports, paid by transactions writing to the account.
+    pub tips: Vec<f64>,
+    /// Compute units consumed by transactions writing to the account.
+    pub compute_units: u64,
+}
+
+impl SlotAccountFees {
+    /// Copies the fees recorded for `account` in a slot. Accounts without fees in the slot have
+    /// empty fee lists.
+    pub fn from_slot_fees(slot_fees: &SlotPriorityFees, account: &Pubkey) -> Self {
+        let mut account_fees = Self {
+            slot: slot_fees.slot,
+            account: account.to_string(),
+            ..Default::default()
+        };
+        if let Some(fees) = slot_fees.account_fees.get(account) {
+            account_fees.non_vote_fees = fees.non_vote_fees.clone();
+            account_fees.vote_fees = fees.vote_fees.clone();
+            account_fees.failed_fees = fees.failed_fees.clone();
+            account_fees.non_vote_payers = fees
+                .non_vote_payers
+                .iter()
+                .map(|payer| payer.map(|payer| payer.to_string()))
+                .collect();
+        }
+        if let Some(tips) = slot_fees.account_tips.get(account) {
+            account_fees.tips = tips.clone();
+        }
+        if let Some(compute_units) = slot_fees.account_compute_units.get(account) {
+            account_fees.compute_units = *compute_units;
+        }
+        account_fees
+    }
+}
+
+/// Request object for the `admin_setDefaultLookback` method.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(
+    rename_all(serialize = "camelCase", deserialize = "camelCase"),
+    deny_unknown_fields
+)]
+pub struct SetDefaultLookbackRequest {
+    /// Number of slots to look back on when a request does not set `lookbackSlots`, all tracked
+    /// slots when unset.
+    pub lookback_slots: Option<u32>,
+}
+
+/// Admin RPC for inspecting and controlling the tracker. Served on its own port, it must not be
+/// exposed to clients.
+#[rpc(server, namespace = "admin")]
+pub trait AtlasAdminRpc {
+    /// Returns the summaries of the tracked slots, newest first.
+    #[method(name = "listSlots", with_extensions)]
+    fn list_slots(&self) -> RpcResult<Vec<SlotSummary>>;
+
+    /// Returns the raw fees recorded for an account in a tracked slot.
+    #[method(name = "getSlotFees", with_extensions)]
+    fn get_slot_fees(
+        &self,
+        get_slot_fees_request: GetSlotFeesRequest,
+    ) -> RpcResult<SlotAccountFees>;
+
+    /// Removes a slot from the tracker, returning whether it was tracked.
+    #[method(name = "evictSlot", with_extensions)]
+    fn evict_slot(&self, slot: Slot) -> RpcResult<bool>;
+
+    /// Removes every tracked slot, returning the number removed.
+    #[method(name = "clearTracker", with_extensions)]
+    fn clear_tracker(&self) -> RpcResult<usize>;
+
+    /// Sets the lookback used by requests without `lookbackSlots` in every cluster, until the
+    /// configuration file is reloaded. Returns the runtime settings in effect.
+    #[method(name = "setDefaultLookback")]
+    fn set_default_lookback(
+        &self,
+        set_default_lookback_request: SetDefaultLookbackRequest,
+    ) -> RpcResult<RuntimeConfig>;
+
+    /// Makes the Geyser client drop its stream and subscribe again.
+    #[method(name = "resubscribe", with_extensions)]
+    fn resubscribe(&self) -> RpcResult<String>;
+}
+
+/// State of a cluster controlled by the admin server, shared with its estimator.
+#[derive(Debug)]
+pub struct AdminCluster {
+    /// Tracker inspected and modified by the admin methods.
+    pub priority_fee_tracker: Arc<PriorityFeeTracker>,
+    /// Maximum number of slots to look back for estimation.
+    pub max_lookback_slots: usize,
+    /// Ingestion state shared with the Geyser client.
+    pub ingest_status: Arc<IngestStatus>,
+}
+
+/// Admin server operating on the state shared with the estimators.
+///
+/// Like the public server, a request controls the cluster of its path prefix, inserted as a
+/// [`ClusterPath`] by [`crate::cluster::ClusterPathLayer`], else the default cluster. The runtime
+/// settings are shared by every cluster.
+#[derive(Debug)]
+pub struct AtlasAdmin {
+    default_cluster: String,
+    clusters: HashMap<String, AdminCluster>,
+    runtime_config: Arc<RuntimeConfigStore>,
+}
+
+impl AtlasAdmin {
+    /// Creates an admin server controlling `cluster` as the default cluster `name`.
+    pub fn new(
+        name: String,
+        cluster: AdminCluster,
+        runtime_config: Arc<RuntimeConfigStore>,
+    ) -> Self {
+        Self {
+            default_cluster: name.clone(),
+            clusters: HashMap::from([(name, cluster)]),
+            runtime_config,
+        }
+    }
+
+    /// Returns this admin server also controlling `cluster` as the cluster `name`.
+    pub fn with_cluster(mut self, name: String, cluster: AdminCluster) -> Self {
+        self.clusters.insert(name, cluster);
+        self
+    }
+
+    /// Returns the name and state of the cluster selected by the path prefix of a request.
+    fn cluster(&self, ext: &Extensions) -> RpcResult<(&str, &AdminCluster)> {
+        let name = ext
+            .get::<ClusterPath>()
+            .map_or(self.default_cluster.as_str(), |path| path.0.as_str());
+        self.clusters
+            .get_key_value(name)
+            .map(|(name, cluster)| (name.as_str(), cluster))
+            .ok_or_else(|| invalid_request(&format!("unknown cluster {name}")))
+    }
+}
+
+#[async_trait]
+impl AtlasAdminRpcServer for AtlasAdmin {
+    fn list_slots(&self, ext: &Extensions) -> RpcResult<Vec<SlotSummary>> {
+        let (_, cluster) = self.cluster(ext)?;
+        let tracker = &cluster.priority_fee_tracker;
+        Ok(tracker
+            .tracked_slots()
+            .into_iter()
+            .filter_map(|slot| tracker.slot_summary(Some(slot)))
+            .collect())
+    }
+
+    fn get_slot_fees(
+        &self,
+        ext: &Extensions,
+        get_slot_fees_request: GetSlotFeesRequest,
+    ) -> RpcResult<SlotAccountFees> {
+        let (_, cluster) = self.cluster(ext)?;
+        let GetSlotFeesRequest { slot, account } = get_slot_fees_request;
+        let account = Pubkey::from_str(&account)
+            .map_err(|_| invalid_request("account must be a valid base58 pubkey"))?;
+        cluster
+            .priority_fee_tracker
+            .inspect_slot(slot, |slot_fees| {
+                SlotAccountFees::from_slot_fees(slot_fees, &account)
+            })
+            .ok_or_else(|| invalid_request(&format!("slot {slot} is not tracked")))
+    }
+
+    fn evict_slot(&self, ext: &Extensions, slot: Slot) -> RpcResult<bool> {
+        let (name, cluster) = self.cluster(ext)?;
+        let evicted = cluster.priority_fee_tracker.evict_slot(slot);
+        if evicted {
+            info!("admin evicted slot {} of cluster {}", slot, name);
+            statsd_count!("admin_slot_evicted", 1, "cluster" => name);
+        }
+        Ok(evicted)
+    }
+
+    fn clear_tracker(&self, ext: &Extensions) -> RpcResult<usize> {
+        let (name, cluster) = self.cluster(ext)?;
+        let cleared = cluster.priority_fee_tracker.clear();
+        info!("admin cleared {} slots of cluster {}", cleared, name);
+        statsd_count!("admin_tracker_cleared", 1, "cluster" => name);
+        Ok(cleared)
+    }
+
+    fn set_default_lookback(
+        &self,
+        set_default_lookback_request: SetDefaultLookbackRequest,
+    ) -> RpcResult<RuntimeConfig> {
+        let lookback_slots = set_default_lookback_request.lookback_slots;
+        // the setting applies to every cluster, so it must fit the smallest window
+        let max_lookback_slots = self
+            .clusters
+            .values()
+            .map(|cluster| cluster.max_lookback_slots)
+            .min()
+            .unwrap_or_default();
+        if let Some(lookback_slots) = lookback_slots {
+            if lookback_slots < 1 || lookback_slots as usize > max_lookback_slots {
+                return Err(invalid_request(&format!(
+                    "lookback_slots must be between 1 and {}",
+                    max_lookback_slots
+                )));
+            }
+        }
+        let config = RuntimeConfig {
+            default_lookback_slots: lookback_slots,
+            ..self.runtime_config.current().as_ref().clone()
+        };
+        info!("admin set default lookback to {:?}", lookback_slots);
+        self.runtime_config.replace(config.clone());
+        Ok(config)
+    }
+
+    fn resubscribe(&self, ext: &Extensions) -> RpcResult<String> {
+        let (name, cluster) = self.cluster(ext)?;
+        info!("admin requested a gRPC resubscribe of cluster {}", name);
+        cluster.ingest_status.request_resubscribe();
+        Ok("ok".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cadence::{NopMetricSink, StatsdClient};
+    use cadence_macros::set_global_default;
+
+    #[tokio::test]
+    async fn test_admin_methods() {
+        set_global_default(StatsdClient::builder("", NopMetricSink).build());
+        let account = Pubkey::new_unique();
+        let payer = Pubkey::new_unique();
+        let tracker = Arc::new(PriorityFeeTracker::new(150));
+        for slot in 1..=3 as Slot {
+            tracker.push_payer_priority_fee_for_txn(
+                slot,
+                Some(payer),
+                vec![account],
+                slot * 100,
+                false,
+            );
+        }
+        tracker.push_failed_priority_fee_for_txn(2, vec![account], 50);
+        tracker.push_compute_units_for_txn(2, vec![account], 1_000);
+
+        let runtime_config = Arc::new(RuntimeConfigStore::default());
+        let admin = AtlasAdmin::new(
+            "mainnet".to_string(),
+            AdminCluster {
+                priority_fee_tracker: tracker.clone(),
+                max_lookback_slots: 150,
+                ingest_status: Default::default(),
+            },
+            runtime_config.clone(),
+        );
+        let ext = Extensions::new();
+
+        let slots: Vec<Slot> = admin
+            .list_slots(&ext)
+            .unwrap()
+            .iter()
+            .map(|summary| summary.slot)
+            .collect();
+        assert_eq!(slots, vec![3, 2, 1]);
+
+        let fees = admin
+            .get_slot_fees(
+                &ext,
+                GetSlotFeesRequest {
+                    slot: 2,
+                    account: account.to_string(),
+                },
+            )
+            .unwrap();
+        assert_eq!(fees.non_vote_fees, vec![200.0]);
+        assert_eq!(fees.failed_fees, vec![50.0]);
+        assert_eq!(fees.non_vote_payers, vec![Some(payer.to_string())]);
+        assert_eq!(fees.compute_units, 1_000);
+        assert!(admin
+            .get_slot_fees(
+                &ext,
+                GetSlotFeesRequest {
+                    slot: 9,
+                    account: account.to_string(),
+                },
+            )
+            .is_err());
+
+        let config = admin
+            .set_default_lookback(SetDefaultLookbackRequest {
+                lookback_slots: Some(1),
+            })
+            .unwrap();
+        assert_eq!(config.default_lookback_slots, Some(1));
+        assert_eq!(runtime_config.current().default_lookback_slots, Some(1));
+        assert!(admin
+            .set_default_lookback(SetDefaultLookbackRequest {
+                lookback_slots: Some(151),
+            })
+            .is_err());
+
+        assert!(admin.evict_slot(&ext, 3).unwrap());
+        assert!(!admin.evict_slot(&ext, 3).unwrap());
+        assert_eq!(tracker.tracked_slots(), vec![2, 1]);
+        assert_eq!(admin.clear_tracker(&ext).unwrap(), 2);
+        assert!(admin.list_slots(&ext).unwrap().is_empty());
+
+        assert_eq!(admin.resubscribe(&ext).unwrap(), "ok");
+    }
+
+    #[tokio::test]
+    async fn test_admin_selects_cluster() {
+        set_global_default(StatsdClient::builder("", NopMetricSink).build());
+        let account = Pubkey::new_unique();
+        let cluster = |slot: Slot, max_lookback_slots: usize| {
+            let tracker = Arc::new(PriorityFeeTracker::new(150));
+            tracker.push_priority_fee_for_txn(slot, vec![account], 100, false);
+            AdminCluster {
+                priority_fee_tracker: tracker,
+                max_lookback_slots,
+                ingest_status: Default::default(),
+            }
+        };
+        let mainnet = cluster(1, 150);
+        let devnet = cluster(2, 50);
+        let devnet_tracker = devnet.priority_fee_tracker.clone();
+        let devnet_status = devnet.ingest_status.clone();
+        let admin = AtlasAdmin::new("mainnet".to_string(), mainnet, Default::default())
+            .with_cluster("devnet".to_string(), devnet);
+        let path = |cluster: &str| {
+            let mut ext = Extensions::new();
+            ext.insert(ClusterPath(cluster.to_string()));
+            ext
+        };
+
+        let slots = |ext: &Extensions| -> Vec<Slot> {
+            admin
+                .list_slots(ext)
+                .unwrap()
+                .iter()
+                .map(|summary| summary.slot)
+                .collect()
+        };
+        assert_eq!(slots(&Extensions::new()), vec![1]);
+        assert_eq!(slots(&path("devnet")), vec![2]);
+        assert!(admin.list_slots(&path("testnet")).is_err());
+
+        // evicting from one cluster leaves the others untouched
+        assert!(!admin.evict_slot(&Extensions::new(), 2).unwrap());
+        assert!(admin.evict_slot(&path("devnet"), 2).unwrap());
+        assert!(devnet_tracker.tracked_slots().is_empty());
+        assert_eq!(slots(&Extensions::new()), vec![1]);
+
+        assert_eq!(admin.resubscribe(&path("devnet")).unwrap(), "ok");
+        devnet_status.resubscribe_requested().await;
+
+        // the shared lookback must fit the smallest window
+        assert!(admin
+            .set_default_lookback(SetDefaultLookbackRequest {
+                lookback_slots: Some(100),
+            })
+            .is_err());
+        assert!(admin
+            .set_default_lookback(SetDefaultLookbackRequest {
+                lookback_slots: Some(50),
+            })
+            .is_ok());
+    }
+}
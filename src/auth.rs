@@ -0,0 +1,609 @@
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::{Arc, RwLock};
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+use cadence_macros::{statsd_count, statsd_time};
+use dashmap::DashMap;
+use figment::providers::{Format, Toml};
+use figment::Figment;
+use http::StatusCode;
+use http_body_util::{BodyExt, Full, Limited};
+use jsonrpsee::core::BoxError;
+use jsonrpsee::server::{HttpBody, HttpRequest, HttpResponse};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tower::{Layer, Service};
+use tracing::{error, info};
+
+use crate::config::watch_file;
+
+/// Header carrying the API key.
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Query parameter carrying the API key, used when the header is absent.
+pub const API_KEY_QUERY_PARAM: &str = "api-key";
+
+/// Methods that can be called without an API key.
+pub const UNAUTHENTICATED_METHODS: [&str; 2] = ["health", "ready"];
+
+/// Largest request body read to count calls and account keys, matching the server limit.
+const MAX_BODY_SIZE: usize = 10 * 1024 * 1024;
+
+/// API key granted to a client and its quotas.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ApiKey {
+    /// Name of the client, used to tag metrics.
+    pub name: String,
+    /// Secret sent by the client.
+    pub key: String,
+    /// Sustained number of calls per second, a batch counting each of its calls.
+    pub requests_per_second: f64,
+    /// Number of calls that can be made at once, `requests_per_second` by default.
+    #[serde(default)]
+    pub burst: Option<f64>,
+    /// Maximum number of account keys per call, only limited by the server by default.
+    #[serde(default)]
+    pub max_account_keys: Option<usize>,
+}
+
+/// API keys, as loaded from the key file.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ApiKeys {
+    /// Keys accepted by the server.
+    pub keys: Vec<ApiKey>,
+}
+
+impl ApiKeys {
+    /// Loads and validates the keys from a TOML file.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let api_keys: Self = Figment::from(Toml::file_exact(path)).extract()?;
+        let mut secrets = HashSet::with_capacity(api_keys.keys.len());
+        for api_key in &api_keys.keys {
+            if api_key.key.is_empty() {
+                anyhow::bail!("api key {}: key must not be empty", api_key.name);
+            }
+            if !secrets.insert(api_key.key.as_str()) {
+                anyhow::bail!("api key {}: key is used by another client", api_key.name);
+            }
+            if !(api_key.requests_per_second.is_finite() && api_key.requests_per_second > 0.0) {
+                anyhow::bail!(
+                    "api key {}: requests_per_second must be positive",
+                    api_key.name
+                );
+            }
+            if api_key.burst.is_some_and(|burst| burst < 1.0) {
+                anyhow::bail!("api key {}: burst must be at least 1", api_key.name);
+            }
+        }
+        Ok(api_keys)
+    }
+}
+
+impl ApiKey {
+    fn burst(&self) -> f64 {
+        self.burst
+            .unwrap_or_else(|| self.requests_per_second.max(1.0))
+    }
+}
+
+/// Token bucket refilled at the rate of a key.
+#[derive(Debug, Clone, Copy)]
+struct TokenBucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    /// Refills the bucket up to `burst` and takes `calls` tokens if available.
+    fn try_acquire(&mut self, calls: f64, rate: f64, burst: f64, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate).min(burst);
+        self.updated = now;
+        if self.tokens >= calls {
+            self.tokens -= calls;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Reason a request is rejected.
+#[derive(Debug, Clone, PartialEq)]
+enum Rejection {
+    MissingKey,
+    UnknownKey,
+    RateLimited(String),
+    TooManyAccountKeys(String, usize),
+}
+
+impl Rejection {
+    const fn status(&self) -> StatusCode {
+        match self {
+            Self::MissingKey | Self::UnknownKey => StatusCode::UNAUTHORIZED,
+            Self::RateLimited(_) => StatusCode::TOO_MANY_REQUESTS,
+            Self::TooManyAccountKeys(..) => StatusCode::FORBIDDEN,
+        }
+    }
+
+    fn message(&self) -> String {
+        match self {
+            Self::MissingKey => format!(
+                "missing api key, set the {API_KEY_HEADER} header or {API_KEY_QUERY_PARAM} query parameter"
+            ),
+            Self::UnknownKey => "invalid api key".to_string(),
+            Self::RateLimited(_) => "rate limit exceeded".to_string(),
+            Self::TooManyAccountKeys(_, max_account_keys) => {
+                format!("number of account_keys must be <= {max_account_keys} for this api key")
+            }
+        }
+    }
+
+    fn metric_tags(&self) -> (&'static str, &str) {
+        match self {
+            Self::MissingKey => ("missing_key", "none"),
+            Self::UnknownKey => ("unknown_key", "none"),
+            Self::RateLimited(name) => ("rate_limited", name),
+            Self::TooManyAccountKeys(name, _) => ("too_many_account_keys", name),
+        }
+    }
+
+    fn into_response(self) -> HttpResponse {
+        let (reason, api_key) = self.metric_tags();
+        statsd_count!("api_key_rejected", 1, "reason" => reason, "api_key" => api_key);
+        let mut response = HttpResponse::new(HttpBody::new(Full::from(self.message())));
+        *response.status_mut() = self.status();
+        response
+    }
+}
+
+/// Calls of a JSON-RPC request body, as the number of calls, the largest number of account keys
+/// in a call and whether any call needs an API key.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct CallCounts {
+    calls: usize,
+    max_account_keys: usize,
+    requires_key: bool,
+}
+
+impl CallCounts {
+    /// Counts the calls of a single or batch request. Unparseable bodies count as one call and are
+    /// left for the server to reject.
+    fn from_body(body: &[u8]) -> Self {
+        let calls = match serde_json::from_slice::<Value>(body) {
+            Ok(Value::Array(calls)) => calls,
+            Ok(call) => vec![call],
+            Err(_) => vec![Value::Null],
+        };
+        let requires_key = calls.iter().any(|call| {
+            !call
+                .get("method")
+                .and_then(Value::as_str)
+                .is_some_and(|method| UNAUTHENTICATED_METHODS.contains(&method))
+        });
+        let max_account_keys = calls
+            .iter()
+            .filter_map(|call| {
+                let params = call.get("params")?;
+                let params = params
+                    .as_array()
+                    .map_or(Some(params), |params| params.first())?;
+                params.get("accountKeys")?.as_array().map(Vec::len)
+            })
+            .max()
+            .unwrap_or_default();
+        Self {
+            calls: calls.len().max(1),
+            max_account_keys,
+            requires_key,
+        }
+    }
+}
+
+/// API keys that can be swapped while serving requests, and the rate limit state of each key.
+#[derive(Debug, Default)]
+pub struct ApiKeyStore {
+    keys: RwLock<Arc<HashMap<String, ApiKey>>>,
+    buckets: DashMap<String, TokenBucket>,
+}
+
+impl ApiKeyStore {
+    /// Creates a store holding the given keys.
+    pub fn new(api_keys: ApiKeys) -> Self {
+        let store = Self::default();
+        store.replace(api_keys);
+        store
+    }
+
+    /// Replaces the accepted keys, keeping the rate limit state of the remaining ones.
+    pub fn replace(&self, api_keys: ApiKeys) {
+        let keys: HashMap<String, ApiKey> = api_keys
+            .keys
+            .into_iter()
+            .map(|api_key| (api_key.key.clone(), api_key))
+            .collect();
+        self.buckets.retain(|key, _| keys.contains_key(key));
+        *self.keys.write().expect("api key lock poisoned") = Arc::new(keys);
+    }
+
+    /// Loads the keys from `path` and starts reloading them whenever the file changes.
+    /// Invalid files are logged and leave the current keys in place.
+    pub fn spawn(path: PathBuf) -> anyhow::Result<Arc<Self>> {
+        let store = Arc::new(Self::new(ApiKeys::load(&path)?));
+        let reloaded = store.clone();
+        let watched = path.clone();
+        watch_file(watched, move || match ApiKeys::load(&path) {
+            Ok(api_keys) => {
+                info!("reloaded api keys from {:?}", path);
+                statsd_count!("api_keys_reloaded", 1);
+                reloaded.replace(api_keys);
+            }
+            Err(e) => {
+                error!("failed to reload api keys: {:?}", e);
+                statsd_count!("api_keys_reload_failed", 1);
+            }
+        });
+        Ok(store)
+    }
+
+    /// Checks the key of a request against its quotas, returning the name of the client.
+    fn authorize(
+        &self,
+        key: Option<&str>,
+        counts: CallCounts,
+        now: Instant,
+    ) -> Result<String, Rejection> {
+        let key = key.ok_or(Rejection::MissingKey)?;
+        let keys = self.keys.read().expect("api key lock poisoned").clone();
+        let api_key = keys.get(key).ok_or(Rejection::UnknownKey)?;
+        if let Some(max_account_keys) = api_key.max_account_keys {
+            if counts.max_account_keys > max_account_keys {
+                return Err(Rejection::TooManyAccountKeys(
+                    api_key.name.clone(),
+                    max_account_keys,
+                ));
+            }
+        }
+        let burst = api_key.burst();
+        let mut bucket = self.buckets.entry(key.to_string()).or_insert(TokenBucket {
+            tokens: burst,
+            updated: now,
+        });
+        if !bucket.try_acquire(counts.calls as f64, api_key.requests_per_second, burst, now) {
+            return Err(Rejection::RateLimited(api_key.name.clone()));
+        }
+        Ok(api_key.name.clone())
+    }
+}
+
+/// Returns the API key of a request, from the header or else the query string.
+fn request_key(request: &HttpRequest) -> Option<String> {
+    if let Some(key) = request
+        .headers()
+        .get(API_KEY_HEADER)
+        .and_then(|value| value.to_str().ok())
+    {
+        return Some(key.to_string());
+    }
+    form_urlencoded::parse(request.uri().query()?.as_bytes())
+        .find_map(|(name, value)| (name == API_KEY_QUERY_PARAM).then(|| value.into_owned()))
+}
+
+/// Tower layer authenticating HTTP requests with API keys and enforcing the quotas of each key.
+///
+/// Requests only calling [`UNAUTHENTICATED_METHODS`] are let through, every request is let through
+/// when no key store is set.
+#[derive(Debug, Clone, Default)]
+pub struct ApiKeyLayer {
+    store: Option<Arc<ApiKeyStore>>,
+}
+
+impl ApiKeyLayer {
+    /// Creates a layer checking requests against `store`, or letting them through when `None`.
+    pub const fn new(store: Option<Arc<ApiKeyStore>>) -> Self {
+        Self { store }
+    }
+}
+
+impl<S> Layer<S> for ApiKeyLayer {
+    type Service = ApiKeyService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ApiKeyService {
+            inner,
+            store: self.store.clone(),
+        }
+    }
+}
+
+/// Service created by [`ApiKeyLayer`].
+#[derive(Debug, Clone)]
+pub struct ApiKeyService<S> {
+    inner: S,
+    store: Option<Arc<ApiKeyStore>>,
+}
+
+impl<S> Service<HttpRequest> for ApiKeyService<S>
+where
+    S: Service<HttpRequest, Response = HttpResponse> + Clone + Send + 'static,
+    S::Error: Into<BoxError> + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = HttpResponse;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, request: HttpRequest) -> Self::Future {
+        // the polled service is taken and replaced by a clone, as tower recommends
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let Some(store) = self.store.clone() else {
+            return Box::pin(async move { inner.call(request).await.map_err(Into::into) });
+        };
+
+        Box::pin(async move {
+            let key = request_key(&request);
+            let (parts, body) = request.into_parts();
+            let body = Limited::new(body, MAX_BODY_SIZE)
+                .collect()
+                .await?
+                .to_bytes();
+            let counts = CallCounts::from_body(&body);
+            let request = HttpRequest::from_parts(parts, HttpBody::new(Full::new(body)));
+            if !counts.requires_key {
+                return inner.call(request).await.map_err(Into::into);
+            }
+
+            let name = match store.authorize(key.as_deref(), counts, Instant::now()) {
+                Ok(name) => name,
+                Err(rejection) => return Ok(rejection.into_response()),
+            };
+            statsd_count!("api_key_requests", counts.calls as i64, "api_key" => &name);
+            let start = Instant::now();
+            let response = inner.call(request).await.map_err(Into::into);
+            statsd_time!("api_key_request_time", start.elapsed(), "api_key" => &name);
+            response
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cadence::{NopMetricSink, StatsdClient};
+    use cadence_macros::set_global_default;
+    use std::time::Duration;
+    use tower::ServiceExt;
+
+    fn api_keys() -> ApiKeys {
+        ApiKeys {
+            keys: vec![ApiKey {
+                name: "partner".to_string(),
+                key: "secret".to_string(),
+                requests_per_second: 2.0,
+                burst: None,
+                max_account_keys: Some(2),
+            }],
+        }
+    }
+
+    fn call(method: &str, account_keys: usize) -> Value {
+        let account_keys: Vec<String> = (0..account_keys).map(|i| i.to_string()).collect();
+        serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": [{ "accountKeys": account_keys }],
+        })
+    }
+
+    #[test]
+    fn test_call_counts() {
+        let single = serde_json::to_vec(&call("getPriorityFeeEstimate", 3)).unwrap();
+        assert_eq!(
+            CallCounts::from_body(&single),
+            CallCounts {
+                calls: 1,
+                max_account_keys: 3,
+                requires_key: true,
+            }
+        );
+
+        let batch = serde_json::to_vec(&Value::Array(vec![
+            call("getPriorityFeeEstimate", 1),
+            call("getTipEstimate", 4),
+            call("health", 0),
+        ]))
+        .unwrap();
+        let counts = CallCounts::from_body(&batch);
+        assert_eq!(counts.calls, 3);
+        assert_eq!(counts.max_account_keys, 4);
+        assert!(counts.requires_key);
+
+        let health = serde_json::to_vec(&call("health", 0)).unwrap();
+        assert!(!CallCounts::from_body(&health).requires_key);
+        assert!(CallCounts::from_body(b"not json").requires_key);
+    }
+
+    #[test]
+    fn test_authorize() {
+        set_global_default(StatsdClient::builder("", NopMetricSink).build());
+        let store = ApiKeyStore::new(api_keys());
+        let counts = |calls, max_account_keys| CallCounts {
+            calls,
+            max_account_keys,
+            requires_key: true,
+        };
+        let now = Instant::now();
+
+        assert_eq!(
+            store.authorize(None, counts(1, 0), now),
+            Err(Rejection::MissingKey)
+        );
+        assert_eq!(
+            store.authorize(Some("wrong"), counts(1, 0), now),
+            Err(Rejection::UnknownKey)
+        );
+        assert_eq!(
+            store.authorize(Some("secret"), counts(1, 3), now),
+            Err(Rejection::TooManyAccountKeys("partner".to_string(), 2))
+        );
+
+        // the burst defaults to the rate, a batch takes one token per call
+        assert_eq!(
+            store.authorize(Some("secret"), counts(2, 2), now),
+            Ok("partner".to_string())
+        );
+        assert_eq!(
+            store.authorize(Some("secret"), counts(1, 0), now),
+            Err(Rejection::RateLimited("partner".to_string()))
+        );
+        let later = now + Duration::from_millis(500);
+        assert!(store.authorize(Some("secret"), counts(1, 0), later).is_ok());
+        assert!(store
+            .authorize(Some("secret"), counts(1, 0), later)
+            .is_err());
+
+        // removed keys are rejected and lose their state
+        store.replace(ApiKeys::default());
+        assert_eq!(
+            store.authorize(Some("secret"), counts(1, 0), later),
+            Err(Rejection::UnknownKey)
+        );
+        assert!(store.buckets.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_api_key_layer() {
+        set_global_default(StatsdClient::builder("", NopMetricSink).build());
+        let service = ApiKeyLayer::new(Some(Arc::new(ApiKeyStore::new(api_keys())))).layer(
+            tower::service_fn(|request: HttpRequest| async move {
+                // the body is passed on untouched
+                let body = request.into_body().collect().await?.to_bytes();
+                Ok::<_, BoxError>(HttpResponse::new(HttpBody::new(Full::new(body))))
+            }),
+        );
+        let request = |uri: &str, header: Option<&str>, body: Value| {
+            let mut builder = http::Request::post(uri);
+            if let Some(key) = header {
+                builder = builder.header(API_KEY_HEADER, key);
+            }
+            builder
+                .body(HttpBody::new(Full::from(
+                    serde_json::to_vec(&body).unwrap(),
+                )))
+                .unwrap()
+        };
+
+        let body = call("getPriorityFeeEstimate", 1);
+        let response = service
+            .clone()
+            .oneshot(request("/", None, body.clone()))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = service
+            .clone()
+            .oneshot(request("/", Some("secret"), body.clone()))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let echoed = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(serde_json::from_slice::<Value>(&echoed).unwrap(), body);
+
+        let response = service
+            .clone()
+            .oneshot(request("/?api-key=secret", None, body.clone()))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = service
+            .clone()
+            .oneshot(request("/?api-key=secret", None, body))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+
+        // health checks do not need a key
+        let response = service
+            .clone()
+            .oneshot(request("/", None, call("ready", 0)))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = ApiKeyLayer::default()
+            .layer(service)
+            .oneshot(request("/", Some("wrong"), call("health", 0)))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn test_request_key() {
+        let request = |uri: &str| {
+            http::Request::get(uri)
+                .body(HttpBody::new(Full::default()))
+                .unwrap()
+        };
+
+        // base64 keys are percent-encoded in the query string
+        assert_eq!(
+            request_key(&request("/?cluster=mainnet&api-key=a%2Bb%2Fc%3D%3D")).as_deref(),
+            Some("a+b/c==")
+        );
+        assert_eq!(
+            request_key(&request("/?api-key=secret+key")).as_deref(),
+            Some("secret key")
+        );
+        assert_eq!(request_key(&request("/?other=secret")), None);
+        assert_eq!(request_key(&request("/")), None);
+    }
+
+    #[test]
+    fn test_load_api_keys() {
+        figment::Jail::expect_with(|jail| {
+            jail.create_file(
+                "keys.toml",
+                r#"
+                [[keys]]
+                name = "partner"
+                key = "secret"
+                requests_per_second = 50
+                max_account_keys = 100
+                "#,
+            )?;
+            let api_keys = ApiKeys::load(Path::new("keys.toml")).map_err(|e| e.to_string())?;
+            assert_eq!(api_keys.keys[0].max_account_keys, Some(100));
+            assert_eq!(api_keys.keys[0].burst(), 50.0);
+
+            jail.create_file(
+                "duplicate.toml",
+                r#"
+                [[keys]]
+                name = "a"
+                key = "secret"
+                requests_per_second = 1
+                [[keys]]
+                name = "b"
+                key = "secret"
+                requests_per_second = 1
+                "#,
+            )?;
+            assert!(ApiKeys::load(Path::new("duplicate.toml")).is_err());
+            Ok(())
+        });
+    }
+}
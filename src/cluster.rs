@@ -0,0 +1,379 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+use cadence_macros::{statsd_count, statsd_time};
+use http::{Request, Uri};
+use jsonrpsee::core::RpcResult;
+use jsonrpsee::Extensions;
+use tower::{Layer, Service};
+
+use crate::errors::invalid_request;
+use crate::health::HealthReport;
+use crate::rpc_server::{
+    AtlasPriorityFeeEstimator, AtlasPriorityFeeEstimatorRpcServer, GetLeaderFeeHistoryRequest,
+    GetPriorityFeeEstimateRequest, GetPriorityFeeEstimateResponse, GetSlotFeesRequest,
+    GetSlotSummaryRequest, GetTipEstimateRequest,
+};
+use crate::{LamportTipEstimates, LeaderSlotFees, SlotFeeBreakdown, SlotSummary};
+
+/// Cluster selected by the path prefix of an HTTP request, inserted in its extensions by
+/// [`ClusterPathLayer`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClusterPath(pub String);
+
+/// Serves the RPC methods from the estimator of the cluster selected by a request.
+///
+/// A request is served by the cluster of its path prefix, else the cluster named by its `cluster`
+/// field, else the default cluster. Requests naming a cluster other than the one of their path
+/// are rejected.
+#[derive(Debug)]
+pub struct ClusterRouter {
+    default_cluster: String,
+    clusters: HashMap<String, AtlasPriorityFeeEstimator>,
+}
+
+impl ClusterRouter {
+    /// Creates a router serving `estimator` as the default cluster `name`.
+    pub fn new(name: String, estimator: AtlasPriorityFeeEstimator) -> Self {
+        Self {
+            default_cluster: name.clone(),
+            clusters: HashMap::from([(name, estimator)]),
+        }
+    }
+
+    /// Returns this router also serving `estimator` as the cluster `name`.
+    pub fn with_cluster(mut self, name: String, estimator: AtlasPriorityFeeEstimator) -> Self {
+        self.clusters.insert(name, estimator);
+        self
+    }
+
+    /// Returns the names of the served clusters, sorted.
+    pub fn cluster_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.clusters.keys().cloned().collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Calls `method` on the estimator of the cluster selected by the path prefix or `requested`.
+    fn route<T>(
+        &self,
+        ext: &Extensions,
+        requested: Option<&str>,
+        method: &str,
+        call: impl FnOnce(&AtlasPriorityFeeEstimator) -> RpcResult<T>,
+    ) -> RpcResult<T> {
+        let path = ext.get::<ClusterPath>().map(|path| path.0.as_str());
+        let name = match (path, requested) {
+            (Some(path), Some(requested)) if path != requested => {
+                return Err(invalid_request(&format!(
+                    "cluster {requested} does not match the path of cluster {path}"
+                )));
+            }
+            (Some(name), _) | (None, Some(name)) => name,
+            (None, None) => self.default_cluster.as_str(),
+        };
+        let Some(estimator) = self.clusters.get(name) else {
+            statsd_count!("cluster_unknown", 1, "method" => method);
+            return Err(invalid_request(&format!("unknown cluster {name}")));
+        };
+        statsd_count!("cluster_requests", 1, "cluster" => name, "method" => method);
+        let start = Instant::now();
+        let result = call(estimator);
+        statsd_time!("cluster_request_time", start.elapsed(), "cluster" => name, "method" => method);
+        result
+    }
+}
+
+impl AtlasPriorityFeeEstimatorRpcServer for ClusterRouter {
+    fn health(&self) -> String {
+        "ok".to_string()
+    }
+
+    fn ready(&self, ext: &Extensions) -> RpcResult<String> {
+        self.route(ext, None, "ready", |estimator| estimator.ready())
+    }
+
+    fn get_health(&self, ext: &Extensions) -> RpcResult<HealthReport> {
+        self.route(ext, None, "getHealth", |estimator| {
+            Ok(estimator.get_health())
+        })
+    }
+
+    fn get_priority_fee_estimate(
+        &self,
+        ext: &Extensions,
+        get_priority_fee_estimate_request: GetPriorityFeeEstimateRequest,
+    ) -> RpcResult<GetPriorityFeeEstimateResponse> {
+        let requested = estimate_cluster(&get_priority_fee_estimate_request);
+        self.route(
+            ext,
+            requested.as_deref(),
+            "getPriorityFeeEstimate",
+            |estimator| estimator.get_priority_fee_estimate(get_priority_fee_estimate_request),
+        )
+    }
+
+    fn get_priority_fee_estimate_v1(
+        &self,
+        ext: &Extensions,
+        get_priority_fee_estimate_request: GetPriorityFeeEstimateRequest,
+    ) -> RpcResult<GetPriorityFeeEstimateResponse> {
+        let requested = estimate_cluster(&get_priority_fee_estimate_request);
+        self.route(
+            ext,
+            requested.as_deref(),
+            "getPriorityFeeEstimateV1",
+            |estimator| estimator.get_priority_fee_estimate_v1(get_priority_fee_estimate_request),
+        )
+    }
+
+    fn get_priority_fee_estimate_v2(
+        &self,
+        ext: &Extensions,
+        get_priority_fee_estimate_request: GetPriorityFeeEstimateRequest,
+    ) -> RpcResult<GetPriorityFeeEstimateResponse> {
+        let requested = estimate_cluster(&get_priority_fee_estimate_request);
+        self.route(
+            ext,
+            requested.as_deref(),
+            "getPriorityFeeEstimateV2",
+            |estimator| estimator.get_priority_fee_estimate_v2(get_priority_fee_estimate_request),
+        )
+    }
+
+    fn get_slot_summary(
+        &self,
+        ext: &Extensions,
+        get_slot_summary_request: Option<GetSlotSummaryRequest>,
+    ) -> RpcResult<SlotSummary> {
+        let requested = get_slot_summary_request
+            .as_ref()
+            .and_then(|request| request.cluster.clone());
+        self.route(ext, requested.as_deref(), "getSlotSummary", |estimator| {
+            estimator.get_slot_summary(get_slot_summary_request)
+        })
+    }
+
+    fn get_leader_fee_history(
+        &self,
+        ext: &Extensions,
+        get_leader_fee_history_request: GetLeaderFeeHistoryRequest,
+    ) -> RpcResult<Vec<LeaderSlotFees>> {
+        let requested = get_leader_fee_history_request.cluster.clone();
+        self.route(
+            ext,
+            requested.as_deref(),
+            "getLeaderFeeHistory",
+            |estimator| estimator.get_leader_fee_history(get_leader_fee_history_request),
+        )
+    }
+
+    fn get_slot_fees(
+        &self,
+        ext: &Extensions,
+        get_slot_fees_request: Option<GetSlotFeesRequest>,
+    ) -> RpcResult<Vec<SlotFeeBreakdown>> {
+        let requested = get_slot_fees_request
+            .as_ref()
+            .and_then(|request| request.cluster.clone());
+        self.route(ext, requested.as_deref(), "getSlotFees", |estimator| {
+            estimator.get_slot_fees(get_slot_fees_request)
+        })
+    }
+
+    fn get_tip_estimate(
+        &self,
+        ext: &Extensions,
+        get_tip_estimate_request: Option<GetTipEstimateRequest>,
+    ) -> RpcResult<LamportTipEstimates> {
+        let requested = get_tip_estimate_request
+            .as_ref()
+            .and_then(|request| request.cluster.clone());
+        self.route(ext, requested.as_deref(), "getTipEstimate", |estimator| {
+            estimator.get_tip_estimate(get_tip_estimate_request)
+        })
+    }
+}
+
+fn estimate_cluster(request: &GetPriorityFeeEstimateRequest) -> Option<String> {
+    request
+        .options
+        .as_ref()
+        .and_then(|options| options.cluster.clone())
+}
+
+/// Tower layer removing a known cluster name from the start of the request path and recording it
+/// as a [`ClusterPath`] extension, so that `/devnet/ready` is served as `/ready` by the devnet
+/// cluster.
+///
+/// Paths not starting with a known cluster name are left untouched.
+#[derive(Debug, Clone, Default)]
+pub struct ClusterPathLayer {
+    clusters: Arc<HashSet<String>>,
+}
+
+impl ClusterPathLayer {
+    /// Creates a layer recognizing the given cluster names.
+    pub fn new(clusters: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            clusters: Arc::new(clusters.into_iter().collect()),
+        }
+    }
+}
+
+impl<S> Layer<S> for ClusterPathLayer {
+    type Service = ClusterPathService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ClusterPathService {
+            inner,
+            clusters: self.clusters.clone(),
+        }
+    }
+}
+
+/// Service created by [`ClusterPathLayer`].
+#[derive(Debug, Clone)]
+pub struct ClusterPathService<S> {
+    inner: S,
+    clusters: Arc<HashSet<String>>,
+}
+
+impl<S, B> Service<Request<B>> for ClusterPathService<S>
+where
+    S: Service<Request<B>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<B>) -> Self::Future {
+        if let Some((cluster, uri)) = strip_cluster(request.uri(), &self.clusters) {
+            *request.uri_mut() = uri;
+            request.extensions_mut().insert(ClusterPath(cluster));
+        }
+        self.inner.call(request)
+    }
+}
+
+/// Returns the cluster named by the first segment of the path of `uri` and the uri without it,
+/// keeping the query.
+fn strip_cluster(uri: &Uri, clusters: &HashSet<String>) -> Option<(String, Uri)> {
+    let path = uri.path().strip_prefix('/')?;
+    let (cluster, rest) = path.split_once('/').unwrap_or((path, ""));
+    if !clusters.contains(cluster) {
+        return None;
+    }
+    let path_and_query = match uri.query() {
+        Some(query) => format!("/{rest}?{query}"),
+        None => format!("/{rest}"),
+    };
+    let mut parts = uri.clone().into_parts();
+    parts.path_and_query = Some(path_and_query.parse().ok()?);
+    let uri = Uri::from_parts(parts).ok()?;
+    Some((cluster.to_string(), uri))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::priority_fee::PriorityFeeTracker;
+    use crate::rpc_server::GetPriorityFeeEstimateOptions;
+    use cadence::{NopMetricSink, StatsdClient};
+    use cadence_macros::set_global_default;
+    use solana_client::rpc_client::RpcClient;
+    use solana_sdk::pubkey::Pubkey;
+    use tower::ServiceExt;
+
+    fn estimator(account: Pubkey, fee: u64) -> AtlasPriorityFeeEstimator {
+        let tracker = PriorityFeeTracker::new(150);
+        tracker.push_priority_fee_for_txn(1, vec![account], fee, false);
+        AtlasPriorityFeeEstimator::new(
+            Arc::new(tracker),
+            Arc::new(RpcClient::new("http://localhost:8899".to_string())),
+            150,
+        )
+    }
+
+    fn request(account: Pubkey, cluster: Option<&str>) -> GetPriorityFeeEstimateRequest {
+        GetPriorityFeeEstimateRequest {
+            account_keys: Some(vec![account.to_string()]),
+            options: Some(GetPriorityFeeEstimateOptions {
+                cluster: cluster.map(str::to_string),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn path(cluster: &str) -> Extensions {
+        let mut ext = Extensions::new();
+        ext.insert(ClusterPath(cluster.to_string()));
+        ext
+    }
+
+    #[test]
+    fn test_router_selects_cluster() {
+        set_global_default(StatsdClient::builder("", NopMetricSink).build());
+        let account = Pubkey::new_unique();
+        let router = ClusterRouter::new("mainnet".to_string(), estimator(account, 100))
+            .with_cluster("devnet".to_string(), estimator(account, 200));
+        assert_eq!(router.cluster_names(), vec!["devnet", "mainnet"]);
+
+        let estimate = |ext: &Extensions, cluster: Option<&str>| {
+            router
+                .get_priority_fee_estimate(ext, request(account, cluster))
+                .map(|response| response.priority_fee_estimate.unwrap())
+        };
+        assert_eq!(estimate(&Extensions::new(), None).unwrap(), 100.0);
+        assert_eq!(estimate(&Extensions::new(), Some("devnet")).unwrap(), 200.0);
+        assert_eq!(estimate(&path("devnet"), None).unwrap(), 200.0);
+        assert_eq!(estimate(&path("devnet"), Some("devnet")).unwrap(), 200.0);
+        assert!(estimate(&path("devnet"), Some("mainnet")).is_err());
+        assert!(estimate(&Extensions::new(), Some("testnet")).is_err());
+
+        let summary = router
+            .get_slot_summary(
+                &Extensions::new(),
+                Some(GetSlotSummaryRequest {
+                    cluster: Some("devnet".to_string()),
+                    ..Default::default()
+                }),
+            )
+            .unwrap();
+        assert_eq!(summary.slot, 1);
+    }
+
+    #[tokio::test]
+    async fn test_path_layer() {
+        let service = ClusterPathLayer::new(["devnet".to_string()]).layer(tower::service_fn(
+            |request: Request<()>| async move {
+                let cluster = request.extensions().get::<ClusterPath>().cloned();
+                Ok::<_, std::convert::Infallible>((request.uri().to_string(), cluster))
+            },
+        ));
+        let call = |uri: &'static str| {
+            let request = Request::builder().uri(uri).body(()).unwrap();
+            service.clone().oneshot(request)
+        };
+
+        let (uri, cluster) = call("/devnet/ready?api-key=secret").await.unwrap();
+        assert_eq!(uri, "/ready?api-key=secret");
+        assert_eq!(cluster, Some(ClusterPath("devnet".to_string())));
+        let (uri, cluster) = call("/devnet").await.unwrap();
+        assert_eq!(uri, "/");
+        assert_eq!(cluster, Some(ClusterPath("devnet".to_string())));
+        let (uri, cluster) = call("/devnets/ready").await.unwrap();
+        assert_eq!(uri, "/devnets/ready");
+        assert_eq!(cluster, None);
+        let (uri, cluster) = call("/").await.unwrap();
+        assert_eq!(uri, "/");
+        assert_eq!(cluster, None);
+    }
+}
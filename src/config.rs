@@ -0,0 +1,534 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use cadence_macros::statsd_count;
+use figment::providers::{Env, Format, Toml};
+use figment::Figment;
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::{ParsePubkeyError, Pubkey};
+use tracing::{error, info, warn};
+
+use crate::csv_export::CsvExportOptions;
+use crate::ingest_pipeline::IngestOptions;
+use crate::recommended_policy::{
+    RecommendedFeePolicies, RecommendedFeePolicy, RecommendedPolicyStore,
+};
+use crate::SlotCommitment;
+
+/// Interval between two checks of a watched file for changes.
+pub const RELOAD_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Configuration of the service, loaded from an optional TOML file and overridden by environment
+/// variables of the same name in upper case.
+///
+/// Only the [`RuntimeConfig`] settings are reloaded when the file changes, the others require a
+/// restart.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct EstimatorConfig {
+    /// Name of the cluster served by the top-level settings, the default cluster of requests.
+    #[serde(default = "default_cluster_name")]
+    pub cluster_name: String,
+    /// RPC url of a Solana node.
+    pub rpc_url: String,
+    /// Yellowstone gRPC url.
+    pub grpc_url: String,
+    /// Yellowstone gRPC token.
+    #[serde(default)]
+    pub grpc_x_token: Option<String>,
+    /// Commitment of the blocks streamed from Geyser.
+    #[serde(default)]
+    pub grpc_commitment: SlotCommitment,
+    /// Largest gRPC message accepted from Geyser, in bytes.
+    #[serde(default = "default_grpc_max_decoding_message_size")]
+    pub grpc_max_decoding_message_size: usize,
+    /// Number of slots kept in memory.
+    #[serde(default = "default_max_lookback_slots")]
+    pub max_lookback_slots: usize,
+    /// Drop slots whose block time is older than this many seconds.
+    #[serde(default)]
+    pub max_lookback_seconds: Option<u64>,
+    /// Port of the JSON-RPC server.
+    #[serde(default = "default_port")]
+    pub port: u16,
+    /// Maximum number of concurrent connections to the JSON-RPC server.
+    #[serde(default = "default_max_connections")]
+    pub max_connections: u32,
+    /// Interval between two runs of the tracker's background task, in milliseconds.
+    #[serde(default = "default_poll_interval_ms")]
+    pub poll_interval_ms: u64,
+    /// Number of worker threads consuming Geyser updates.
+    #[serde(default = "default_ingest_workers")]
+    pub ingest_workers: usize,
+    /// Number of updates queued per ingest worker before the stream is held back.
+    #[serde(default = "default_ingest_queue_capacity")]
+    pub ingest_queue_capacity: usize,
+    /// How long a full ingest queue holds back the stream before the update is dropped, in
+    /// milliseconds.
+    #[serde(default = "default_ingest_max_enqueue_wait_ms")]
+    pub ingest_max_enqueue_wait_ms: u64,
+    /// Whether the leader schedule is fetched from `rpc_url`, disabled by default.
+    #[serde(default)]
+    pub leader_schedule_enabled: bool,
+    /// Comma-separated tip accounts, the Jito tip accounts by default.
+    #[serde(default)]
+    pub tip_accounts: Option<String>,
+    /// Path of the recommended fee policy file.
+    #[serde(default)]
+    pub recommended_policy_file: Option<String>,
+    /// Path of the API key file, requests are not authenticated when unset.
+    #[serde(default)]
+    pub api_keys_file: Option<String>,
+    /// Port of the admin JSON-RPC server, which is not started when unset.
+    #[serde(default)]
+    pub admin_port: Option<u16>,
+    /// Address the admin JSON-RPC server binds to.
+    #[serde(default = "default_admin_host")]
+    pub admin_host: String,
+    /// OTLP gRPC endpoint spans are exported to, spans are not exported when unset.
+    #[serde(default)]
+    pub otlp_endpoint: Option<String>,
+    /// Directory per-slot fee aggregates are exported to as CSV, nothing is exported when unset.
+    #[serde(default)]
+    pub csv_export_dir: Option<String>,
+    /// Comma-separated accounts whose fee percentiles are added to every exported row.
+    #[serde(default)]
+    pub csv_export_accounts: Option<String>,
+    /// Number of rows written to a CSV file before the next one is started.
+    #[serde(default = "default_csv_export_rows_per_file")]
+    pub csv_export_rows_per_file: usize,
+    /// Number of CSV files kept per cluster.
+    #[serde(default = "default_csv_export_max_files")]
+    pub csv_export_max_files: usize,
+    /// Additional clusters served by the same process.
+    #[serde(default)]
+    pub clusters: Vec<ClusterConfig>,
+    /// Settings applied without a restart.
+    #[serde(flatten)]
+    pub runtime: RuntimeConfig,
+}
+
+/// Settings of a cluster, each cluster having its own tracker, Geyser stream and RPC client.
+///
+/// Omitted settings take the same defaults as the top-level ones.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ClusterConfig {
+    /// Name of the cluster, selected by the `cluster` field of requests or the path prefix.
+    pub name: String,
+    /// RPC url of a Solana node of the cluster.
+    pub rpc_url: String,
+    /// Yellowstone gRPC url of the cluster.
+    pub grpc_url: String,
+    /// Yellowstone gRPC token.
+    #[serde(default)]
+    pub grpc_x_token: Option<String>,
+    /// Commitment of the blocks streamed from Geyser.
+    #[serde(default)]
+    pub grpc_commitment: SlotCommitment,
+    /// Number of slots kept in memory.
+    #[serde(default = "default_max_lookback_slots")]
+    pub max_lookback_slots: usize,
+    /// Drop slots whose block time is older than this many seconds.
+    #[serde(default)]
+    pub max_lookback_seconds: Option<u64>,
+    /// Whether the leader schedule is fetched from `rpc_url`, disabled by default.
+    #[serde(default)]
+    pub leader_schedule_enabled: bool,
+    /// Comma-separated tip accounts, the Jito tip accounts by default.
+    #[serde(default)]
+    pub tip_accounts: Option<String>,
+}
+
+/// Settings that are reloaded when the configuration file changes.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct RuntimeConfig {
+    /// Maximum number of account keys per request.
+    pub max_account_keys: usize,
+    /// Floor of the recommended fee, unless a recommended fee policy file is used.
+    pub recommended_fee_floor: f64,
+    /// Share of the window that must hold slots before the estimator is ready.
+    pub ready_min_window_fill: f64,
+    /// Block time age of the newest slot beyond which the estimator is not ready, in seconds.
+    pub ready_max_slot_age_seconds: u64,
+    /// Number of slots to look back on when a request does not set `lookbackSlots`, all tracked
+    /// slots when unset.
+    pub default_lookback_slots: Option<u32>,
+}
+
+impl Default for RuntimeConfig {
+    fn default() -> Self {
+        Self {
+            max_account_keys: 500,
+            recommended_fee_floor: RecommendedFeePolicy::default().floor,
+            ready_min_window_fill: 1.0,
+            ready_max_slot_age_seconds: 30,
+            default_lookback_slots: None,
+        }
+    }
+}
+
+fn default_cluster_name() -> String {
+    "default".to_string()
+}
+
+const fn default_grpc_max_decoding_message_size() -> usize {
+    50_000_000
+}
+
+const fn default_max_lookback_slots() -> usize {
+    150
+}
+
+fn default_admin_host() -> String {
+    "127.0.0.1".to_string()
+}
+
+const fn default_port() -> u16 {
+    4141
+}
+
+const fn default_max_connections() -> u32 {
+    100_000
+}
+
+const fn default_poll_interval_ms() -> u64 {
+    1_000
+}
+
+fn default_ingest_workers() -> usize {
+    IngestOptions::default().workers
+}
+
+fn default_ingest_queue_capacity() -> usize {
+    IngestOptions::default().queue_capacity
+}
+
+fn default_ingest_max_enqueue_wait_ms() -> u64 {
+    IngestOptions::default().max_enqueue_wait.as_millis() as u64
+}
+
+fn default_csv_export_rows_per_file() -> usize {
+    CsvExportOptions::default().rows_per_file
+}
+
+fn default_csv_export_max_files() -> usize {
+    CsvExportOptions::default().max_files
+}
+
+impl EstimatorConfig {
+    /// Loads the configuration from the TOML file at `path`, if any, and the environment.
+    pub fn load(path: Option<&Path>) -> Result<Self, figment::Error> {
+        let figment = match path {
+            Some(path) => Figment::from(Toml::file_exact(path)),
+            None => Figment::new(),
+        };
+        let config: Self = figment.merge(Env::raw()).extract()?;
+        config.validate_clusters()?;
+        Ok(config)
+    }
+
+    /// Returns the options of the ingest worker pool of `cluster`.
+    pub fn ingest_options(&self, cluster: &str) -> IngestOptions {
+        IngestOptions {
+            workers: self.ingest_workers,
+            queue_capacity: self.ingest_queue_capacity,
+            max_enqueue_wait: Duration::from_millis(self.ingest_max_enqueue_wait_ms),
+            cluster: cluster.to_string(),
+        }
+    }
+
+    /// Returns the options of the CSV export of `cluster`, `None` when the export is disabled.
+    pub fn csv_export_options(
+        &self,
+        cluster: &str,
+    ) -> Result<Option<CsvExportOptions>, ParsePubkeyError> {
+        let Some(directory) = &self.csv_export_dir else {
+            return Ok(None);
+        };
+        let watch_accounts = self
+            .csv_export_accounts
+            .as_deref()
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|account| !account.is_empty())
+            .map(Pubkey::from_str)
+            .collect::<Result<_, _>>()?;
+        Ok(Some(CsvExportOptions {
+            directory: directory.into(),
+            file_prefix: format!("slot_fees_{cluster}"),
+            watch_accounts,
+            rows_per_file: self.csv_export_rows_per_file,
+            max_files: self.csv_export_max_files,
+            interval: Duration::from_millis(self.poll_interval_ms),
+        }))
+    }
+
+    /// Returns every served cluster, the one of the top-level settings first.
+    pub fn clusters(&self) -> Vec<ClusterConfig> {
+        let default = ClusterConfig {
+            name: self.cluster_name.clone(),
+            rpc_url: self.rpc_url.clone(),
+            grpc_url: self.grpc_url.clone(),
+            grpc_x_token: self.grpc_x_token.clone(),
+            grpc_commitment: self.grpc_commitment,
+            max_lookback_slots: self.max_lookback_slots,
+            max_lookback_seconds: self.max_lookback_seconds,
+            leader_schedule_enabled: self.leader_schedule_enabled,
+            tip_accounts: self.tip_accounts.clone(),
+        };
+        std::iter::once(default)
+            .chain(self.clusters.iter().cloned())
+            .collect()
+    }
+
+    fn validate_clusters(&self) -> Result<(), figment::Error> {
+        let mut names = HashSet::new();
+        for cluster in self.clusters() {
+            if cluster.name.is_empty() || cluster.name.contains('/') {
+                return Err(format!("invalid cluster name {:?}", cluster.name).into());
+            }
+            if !names.insert(cluster.name.clone()) {
+                return Err(format!("duplicate cluster name {:?}", cluster.name).into());
+            }
+        }
+        Ok(())
+    }
+}
+
+impl RuntimeConfig {
+    /// Returns the recommended fee policies used without a policy file.
+    pub fn recommended_policies(&self) -> RecommendedFeePolicies {
+        RecommendedFeePolicies {
+            default: RecommendedFeePolicy {
+                floor: self.recommended_fee_floor,
+                ..Default::default()
+            },
+            overrides: vec![],
+        }
+    }
+}
+
+/// Runtime settings that can be swapped while serving requests.
+#[derive(Debug, Default)]
+pub struct RuntimeConfigStore {
+    config: RwLock<Arc<RuntimeConfig>>,
+}
+
+impl RuntimeConfigStore {
+    /// Creates a store holding the given settings.
+    pub fn new(config: RuntimeConfig) -> Self {
+        Self {
+            config: RwLock::new(Arc::new(config)),
+        }
+    }
+
+    /// Returns the current settings.
+    pub fn current(&self) -> Arc<RuntimeConfig> {
+        self.config.read().expect("config lock poisoned").clone()
+    }
+
+    /// Replaces the current settings.
+    pub fn replace(&self, config: RuntimeConfig) {
+        *self.config.write().expect("config lock poisoned") = Arc::new(config);
+    }
+
+    /// Creates a store holding the runtime settings of `config` and starts reloading them whenever
+    /// the file at `path` changes. The default policy of `policies`, when given, follows the
+    /// recommended fee floor. Invalid files are logged and leave the current settings in place.
+    pub fn spawn(
+        path: PathBuf,
+        config: EstimatorConfig,
+        policies: Option<Arc<RecommendedPolicyStore>>,
+    ) -> Arc<Self> {
+        let store = Arc::new(Self::new(config.runtime.clone()));
+        let reloaded = store.clone();
+        let watched = path.clone();
+        watch_file(watched, move || match EstimatorConfig::load(Some(&path)) {
+            Ok(new_config) => {
+                if !restart_settings_match(&config, &new_config) {
+                    warn!("configuration changes other than runtime settings require a restart");
+                }
+                info!("reloaded runtime configuration from {:?}", path);
+                statsd_count!("config_reloaded", 1);
+                if let Some(policies) = &policies {
+                    policies.replace(new_config.runtime.recommended_policies());
+                }
+                reloaded.replace(new_config.runtime);
+            }
+            Err(e) => {
+                error!("failed to reload configuration: {:?}", e);
+                statsd_count!("config_reload_failed", 1);
+            }
+        });
+        store
+    }
+}
+
+fn restart_settings_match(a: &EstimatorConfig, b: &EstimatorConfig) -> bool {
+    let without_runtime = |config: &EstimatorConfig| EstimatorConfig {
+        runtime: RuntimeConfig::default(),
+        ..config.clone()
+    };
+    without_runtime(a) == without_runtime(b)
+}
+
+/// Calls `on_change` from a background thread whenever the modification time of the file at
+/// `path` changes, checking every [`RELOAD_INTERVAL`].
+pub fn watch_file(path: PathBuf, mut on_change: impl FnMut() + Send + 'static) {
+    let mut modified = modified_time(&path);
+    thread::spawn(move || loop {
+        thread::sleep(RELOAD_INTERVAL);
+        let current = modified_time(&path);
+        if current != modified {
+            modified = current;
+            on_change();
+        }
+    });
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layered_config() {
+        figment::Jail::expect_with(|jail| {
+            jail.create_file(
+                "config.toml",
+                r#"
+                rpc_url = "http://localhost:8899"
+                grpc_url = "http://localhost:10000"
+                port = 8080
+                max_account_keys = 100
+                "#,
+            )?;
+            jail.set_env("GRPC_URL", "http://geyser:10000");
+            jail.set_env("RECOMMENDED_FEE_FLOOR", "5000");
+
+            let config = EstimatorConfig::load(Some(Path::new("config.toml")))?;
+            assert_eq!(config.rpc_url, "http://localhost:8899");
+            // environment variables override the file
+            assert_eq!(config.grpc_url, "http://geyser:10000");
+            assert_eq!(config.port, 8080);
+            assert_eq!(config.max_connections, 100_000);
+            assert_eq!(config.poll_interval_ms, 1_000);
+            assert_eq!(config.grpc_max_decoding_message_size, 50_000_000);
+            assert_eq!(
+                config.runtime,
+                RuntimeConfig {
+                    max_account_keys: 100,
+                    recommended_fee_floor: 5_000.0,
+                    ..Default::default()
+                }
+            );
+            assert_eq!(config.runtime.recommended_policies().default.floor, 5_000.0);
+
+            // without a file only the environment is used
+            jail.set_env("RPC_URL", "http://rpc:8899");
+            let config = EstimatorConfig::load(None)?;
+            assert_eq!(config.port, 4141);
+            assert_eq!(config.runtime.max_account_keys, 500);
+            assert!(EstimatorConfig::load(Some(Path::new("missing.toml"))).is_err());
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_restart_settings() {
+        figment::Jail::expect_with(|jail| {
+            jail.set_env("RPC_URL", "http://rpc:8899");
+            jail.set_env("GRPC_URL", "http://geyser:10000");
+            let config = EstimatorConfig::load(None)?;
+
+            let mut runtime_change = config.clone();
+            runtime_change.runtime.max_account_keys = 10;
+            assert!(restart_settings_match(&config, &runtime_change));
+
+            let mut port_change = config.clone();
+            port_change.port = 8080;
+            assert!(!restart_settings_match(&config, &port_change));
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_clusters() {
+        figment::Jail::expect_with(|jail| {
+            jail.create_file(
+                "config.toml",
+                r#"
+                cluster_name = "mainnet"
+                rpc_url = "http://mainnet:8899"
+                grpc_url = "http://mainnet:10000"
+                max_lookback_slots = 300
+
+                [[clusters]]
+                name = "devnet"
+                rpc_url = "http://devnet:8899"
+                grpc_url = "http://devnet:10000"
+                leader_schedule_enabled = true
+                "#,
+            )?;
+            let config = EstimatorConfig::load(Some(Path::new("config.toml")))?;
+            let clusters = config.clusters();
+            assert_eq!(clusters.len(), 2);
+            assert_eq!(clusters[0].name, "mainnet");
+            assert_eq!(clusters[0].max_lookback_slots, 300);
+            assert_eq!(clusters[1].name, "devnet");
+            assert_eq!(clusters[1].grpc_url, "http://devnet:10000");
+            // omitted settings take the top-level defaults, not the top-level values
+            assert_eq!(clusters[1].max_lookback_slots, 150);
+            assert!(!clusters[0].leader_schedule_enabled);
+            assert!(clusters[1].leader_schedule_enabled);
+            assert_eq!(config.ingest_options("devnet").cluster, "devnet");
+            assert!(config.csv_export_options("devnet").unwrap().is_none());
+
+            jail.create_file(
+                "duplicate.toml",
+                r#"
+                cluster_name = "devnet"
+                rpc_url = "http://mainnet:8899"
+                grpc_url = "http://mainnet:10000"
+
+                [[clusters]]
+                name = "devnet"
+                rpc_url = "http://devnet:8899"
+                grpc_url = "http://devnet:10000"
+                "#,
+            )?;
+            assert!(EstimatorConfig::load(Some(Path::new("duplicate.toml"))).is_err());
+
+            jail.set_env("CSV_EXPORT_DIR", "exports");
+            jail.set_env(
+                "CSV_EXPORT_ACCOUNTS",
+                "JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4, ",
+            );
+            let config = EstimatorConfig::load(Some(Path::new("config.toml")))?;
+            let options = config.csv_export_options("devnet").unwrap().unwrap();
+            assert_eq!(options.directory, PathBuf::from("exports"));
+            assert_eq!(options.file_prefix, "slot_fees_devnet");
+            assert_eq!(options.watch_accounts.len(), 1);
+            assert_eq!(options.rows_per_file, 10_000);
+            jail.set_env("CSV_EXPORT_ACCOUNTS", "not a pubkey");
+            let config = EstimatorConfig::load(Some(Path::new("config.toml")))?;
+            assert!(config.csv_export_options("devnet").is_err());
+
+            jail.set_env("RPC_URL", "http://rpc:8899");
+            jail.set_env("GRPC_URL", "http://geyser:10000");
+            jail.set_env("CLUSTER_NAME", "main/net");
+            assert!(EstimatorConfig::load(None).is_err());
+            Ok(())
+        });
+    }
+}
@@ -0,0 +1,335 @@
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use cadence_macros::statsd_count;
+use solana_sdk::clock::Slot;
+use solana_sdk::pubkey::Pubkey;
+use tracing::{error, info, warn};
+
+use crate::priority_fee::PriorityFeeTracker;
+use crate::{MicroLamportPriorityFeeEstimates, SlotCommitment, SlotFeeSamples};
+
+/// Number of slots a slot must be behind the newest tracked slot before it is exported, so that
+/// blocks consumed out of order by the ingest workers are not passed over.
+pub const SETTLE_SLOTS: Slot = 4;
+
+/// Options of the CSV export of per-slot fee aggregates.
+#[derive(Debug, Clone)]
+pub struct CsvExportOptions {
+    /// Directory the CSV files are written to.
+    pub directory: PathBuf,
+    /// Prefix of the file names, followed by the first slot of each file.
+    pub file_prefix: String,
+    /// Accounts whose non-vote fee percentiles are added to every row.
+    pub watch_accounts: Vec<Pubkey>,
+    /// Number of rows written to a file before the next one is started.
+    pub rows_per_file: usize,
+    /// Number of files kept, the oldest file written by this process is removed beyond it.
+    pub max_files: usize,
+    /// Interval between two exports of the completed slots.
+    pub interval: Duration,
+}
+
+impl Default for CsvExportOptions {
+    fn default() -> Self {
+        Self {
+            directory: PathBuf::from("slot_fees"),
+            file_prefix: "slot_fees".to_string(),
+            watch_accounts: vec![],
+            rows_per_file: 10_000,
+            max_files: 24,
+            interval: Duration::from_secs(1),
+        }
+    }
+}
+
+/// Appends a row of fee aggregates to rotating CSV files for each completed slot of a tracker.
+///
+/// A slot is completed once its block is recorded at confirmed commitment and it is
+/// [`SETTLE_SLOTS`] behind the newest tracked slot. Slots are exported in order, a provisional slot
+/// holds back the export until it is confirmed or abandoned, and skipped slots are passed over.
+#[derive(Debug)]
+pub struct CsvExporter {
+    tracker: Arc<PriorityFeeTracker>,
+    options: CsvExportOptions,
+    last_exported: Option<Slot>,
+    writer: Option<csv::Writer<File>>,
+    rows_in_file: usize,
+    files: VecDeque<PathBuf>,
+}
+
+impl CsvExporter {
+    /// Creates an exporter of the slots of `tracker`, creating the export directory.
+    pub fn new(
+        tracker: Arc<PriorityFeeTracker>,
+        options: CsvExportOptions,
+    ) -> std::io::Result<Self> {
+        fs::create_dir_all(&options.directory)?;
+        Ok(Self {
+            tracker,
+            options,
+            last_exported: None,
+            writer: None,
+            rows_in_file: 0,
+            files: VecDeque::new(),
+        })
+    }
+
+    /// Creates an exporter and starts exporting the completed slots every `interval` from a
+    /// background thread. Export errors are logged and retried on the next run.
+    pub fn spawn(
+        tracker: Arc<PriorityFeeTracker>,
+        options: CsvExportOptions,
+    ) -> std::io::Result<()> {
+        let mut exporter = Self::new(tracker, options)?;
+        info!(
+            "exporting slot fees to {:?}",
+            exporter
+                .options
+                .directory
+                .join(&exporter.options.file_prefix)
+        );
+        thread::Builder::new()
+            .name("csv-export".to_string())
+            .spawn(move || loop {
+                thread::sleep(exporter.options.interval);
+                match exporter.export_completed() {
+                    Ok(rows) => statsd_count!("csv_rows_exported", rows as i64),
+                    Err(e) => {
+                        error!("failed to export slot fees: {}", e);
+                        statsd_count!("csv_export_error", 1);
+                    }
+                }
+            })?;
+        Ok(())
+    }
+
+    /// Appends a row for every slot completed since the last export, returning the number of rows
+    /// written.
+    pub fn export_completed(&mut self) -> csv::Result<usize> {
+        let Some(newest_slot) = self.tracker.newest_slot() else {
+            return Ok(0);
+        };
+        let mut slots = self.tracker.tracked_slots();
+        slots.sort_unstable();
+        let mut rows = 0;
+        for slot in slots {
+            if self.last_exported.is_some_and(|last| slot <= last)
+                || slot + SETTLE_SLOTS > newest_slot
+            {
+                continue;
+            }
+            let Some(summary) = self.tracker.slot_summary(Some(slot)) else {
+                continue;
+            };
+            if summary.commitment == SlotCommitment::Processed {
+                break;
+            }
+            self.last_exported = Some(slot);
+            let Some(block) = summary.block.filter(|_| !summary.skipped) else {
+                continue;
+            };
+            let Some(global) = self.tracker.slot_fee_breakdown(None, slot, 0) else {
+                continue;
+            };
+
+            let mut record = vec![
+                slot.to_string(),
+                block
+                    .block_time
+                    .map(|time| time.to_string())
+                    .unwrap_or_default(),
+                block.executed_transaction_count.to_string(),
+            ];
+            push_samples(&mut record, &global.vote);
+            push_samples(&mut record, &global.non_vote);
+            for account in &self.options.watch_accounts {
+                let samples = self
+                    .tracker
+                    .slot_fee_breakdown(Some(account), slot, 0)
+                    .map(|breakdown| breakdown.non_vote)
+                    .unwrap_or_default();
+                push_samples(&mut record, &samples);
+            }
+            self.writer(slot)?.write_record(&record)?;
+            self.rows_in_file += 1;
+            rows += 1;
+        }
+        if let Some(writer) = &mut self.writer {
+            writer.flush()?;
+        }
+        Ok(rows)
+    }
+
+    /// Returns the writer of the current file, starting a new file at `slot` when the current one
+    /// is full.
+    fn writer(&mut self, slot: Slot) -> csv::Result<&mut csv::Writer<File>> {
+        if self.writer.is_none() || self.rows_in_file >= self.options.rows_per_file.max(1) {
+            if let Some(mut writer) = self.writer.take() {
+                writer.flush()?;
+            }
+            let path = self
+                .options
+                .directory
+                .join(format!("{}_{}.csv", self.options.file_prefix, slot));
+            let mut writer = csv::Writer::from_path(&path)?;
+            writer.write_record(self.header())?;
+            self.files.push_back(path);
+            while self.files.len() > self.options.max_files.max(1) {
+                if let Some(oldest) = self.files.pop_front() {
+                    if let Err(e) = fs::remove_file(&oldest) {
+                        warn!("failed to remove {:?}: {}", oldest, e);
+                    }
+                }
+            }
+            self.writer = Some(writer);
+            self.rows_in_file = 0;
+        }
+        Ok(self.writer.as_mut().expect("writer was just set"))
+    }
+
+    fn header(&self) -> Vec<String> {
+        let mut header = vec![
+            "slot".to_string(),
+            "block_time".to_string(),
+            "transaction_count".to_string(),
+        ];
+        let prefixes = ["vote".to_string(), "non_vote".to_string()]
+            .into_iter()
+            .chain(self.options.watch_accounts.iter().map(Pubkey::to_string));
+        for prefix in prefixes {
+            header.extend(
+                SAMPLE_COLUMNS
+                    .iter()
+                    .map(|column| format!("{prefix}_{column}")),
+            );
+        }
+        header
+    }
+}
+
+/// Columns written for each kind of transaction and each watched account.
+const SAMPLE_COLUMNS: [&str; 7] = [
+    "count",
+    "min",
+    "low",
+    "medium",
+    "high",
+    "very_high",
+    "unsafe_max",
+];
+
+/// Appends the count and percentiles of `samples`, leaving the percentiles empty without fees.
+fn push_samples(record: &mut Vec<String>, samples: &SlotFeeSamples) {
+    record.push(samples.count.to_string());
+    let MicroLamportPriorityFeeEstimates {
+        min,
+        low,
+        medium,
+        high,
+        very_high,
+        unsafe_max,
+    } = &samples.estimates;
+    for estimate in [min, low, medium, high, very_high, unsafe_max] {
+        record.push(if samples.count == 0 {
+            String::new()
+        } else {
+            estimate.to_string()
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BlockMetadata;
+    use cadence::{NopMetricSink, StatsdClient};
+    use cadence_macros::set_global_default;
+    use std::path::Path;
+
+    fn push_block(tracker: &PriorityFeeTracker, slot: Slot, parent_slot: Slot) {
+        tracker.push_block_metadata(
+            slot,
+            BlockMetadata {
+                block_time: Some(1_700_000_000 + slot as i64),
+                parent_slot,
+                executed_transaction_count: 3,
+                ..Default::default()
+            },
+        );
+    }
+
+    fn read_rows(path: &Path) -> Vec<Vec<String>> {
+        csv::Reader::from_path(path)
+            .unwrap()
+            .records()
+            .map(|record| record.unwrap().iter().map(str::to_string).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_export_completed_slots() {
+        set_global_default(StatsdClient::builder("", NopMetricSink).build());
+        figment::Jail::expect_with(|_jail| {
+            let account = Pubkey::new_unique();
+            let tracker = Arc::new(PriorityFeeTracker::new(150));
+            // slot 4 is skipped by the block of slot 5
+            for (slot, parent_slot) in [(1, 0), (2, 1), (3, 2), (5, 3), (6, 5), (7, 6), (8, 7)] {
+                tracker.push_priority_fee_for_txn(slot, vec![account], 100 * slot, false);
+                tracker.push_priority_fee_for_txn(slot, vec![], 5, true);
+                push_block(&tracker, slot, parent_slot);
+            }
+            let mut exporter = CsvExporter::new(
+                tracker.clone(),
+                CsvExportOptions {
+                    directory: PathBuf::from("export"),
+                    watch_accounts: vec![account],
+                    rows_per_file: 2,
+                    max_files: 2,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+            // slots within SETTLE_SLOTS of the newest slot are not completed yet
+            assert_eq!(exporter.export_completed().unwrap(), 3);
+            let rows = read_rows(Path::new("export/slot_fees_1.csv"));
+            assert_eq!(rows.len(), 2);
+            assert_eq!(rows[0][..5], ["1", "1700000001", "3", "1", "5"]);
+            // non-vote and account percentiles of slot 2
+            assert_eq!(rows[1][10], "1");
+            assert_eq!(rows[1][12], "200");
+            assert_eq!(rows[1][19], "200");
+            assert_eq!(read_rows(Path::new("export/slot_fees_3.csv")).len(), 1);
+
+            // a provisional slot holds back the export
+            tracker.push_block_metadata_with_commitment(
+                10,
+                BlockMetadata {
+                    parent_slot: 8,
+                    ..Default::default()
+                },
+                SlotCommitment::Processed,
+            );
+            push_block(&tracker, 14, 10);
+            assert_eq!(exporter.export_completed().unwrap(), 4);
+            // only the newest files are kept
+            assert!(!Path::new("export/slot_fees_1.csv").exists());
+            assert!(!Path::new("export/slot_fees_3.csv").exists());
+            assert_eq!(read_rows(Path::new("export/slot_fees_6.csv")).len(), 2);
+            let rows = read_rows(Path::new("export/slot_fees_8.csv"));
+            assert_eq!(rows.len(), 1);
+            assert_eq!(rows[0][0], "8");
+
+            tracker.confirm_slot(10, Some(8));
+            assert_eq!(exporter.export_completed().unwrap(), 1);
+            let rows = read_rows(Path::new("export/slot_fees_8.csv"));
+            assert_eq!(rows[1][..2], ["10", ""]);
+            Ok(())
+        });
+    }
+}
@@ -32,3 +32,15 @@ pub fn invalid_request(reason: &str) -> ErrorObjectOwned {
         None::<String>,
     )
 }
+
+/// Error code returned while the estimator is not ready to serve estimates.
+pub const NOT_READY_CODE: i32 = -32000;
+
+/// Creates an error object listing why the estimator is not ready.
+pub fn not_ready(reasons: &[String]) -> ErrorObjectOwned {
+    ErrorObjectOwned::owned(
+        NOT_READY_CODE,
+        format!("Not Ready: {}", reasons.join(", ")),
+        None::<String>,
+    )
+}
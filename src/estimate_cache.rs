@@ -0,0 +1,179 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use cadence_macros::statsd_count;
+use dashmap::DashMap;
+
+/// Maximum number of distinct requests cached per data version, further requests are computed
+/// without being cached.
+pub const MAX_CACHE_ENTRIES: usize = 10_000;
+
+/// Cache of computations over the tracked data, keyed by the normalized request and the data
+/// version of the tracker.
+///
+/// Entries of older versions are dropped as soon as a newer version is seen, so the cache is
+/// invalidated whenever the tracker completes a slot. Values are computed outside of the map, so
+/// callers never wait on each other: identical requests arriving while a computation is running
+/// compute as well, and the first value stored wins.
+#[derive(Debug)]
+pub struct EstimateCache<V> {
+    version: AtomicU64,
+    entries: DashMap<(u64, String), V>,
+}
+
+impl<V> Default for EstimateCache<V> {
+    fn default() -> Self {
+        Self {
+            version: AtomicU64::new(0),
+            entries: DashMap::new(),
+        }
+    }
+}
+
+impl<V: Clone> EstimateCache<V> {
+    /// Returns the cached value for `key` at `version`, or computes it with `compute`.
+    ///
+    /// Failed computations, returning `None`, are not cached.
+    pub fn get_or_compute(
+        &self,
+        version: u64,
+        key: String,
+        compute: impl FnOnce() -> Option<V>,
+    ) -> Option<V> {
+        let current = self.version.fetch_max(version, Ordering::Relaxed);
+        if version > current {
+            self.entries
+                .retain(|(entry_version, _), _| *entry_version >= version);
+        } else if version < current {
+            // computed against data that is already outdated, not worth caching
+            return compute();
+        }
+
+        if let Some(value) = self.entries.get(&(version, key.clone())) {
+            statsd_count!("estimate_cache_hit", 1);
+            return Some(value.clone());
+        }
+        if self.entries.len() >= MAX_CACHE_ENTRIES {
+            statsd_count!("estimate_cache_full", 1);
+            return compute();
+        }
+        statsd_count!("estimate_cache_miss", 1);
+        let value = compute()?;
+        // the data may have moved on while computing
+        if self.version.load(Ordering::Relaxed) == version {
+            return Some(self.entries.entry((version, key)).or_insert(value).clone());
+        }
+        Some(value)
+    }
+
+    /// Returns the number of cached entries.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns whether the cache is empty.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cadence::{NopMetricSink, StatsdClient};
+    use cadence_macros::set_global_default;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::mpsc;
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_cache_invalidation() {
+        set_global_default(StatsdClient::builder("", NopMetricSink).build());
+        let cache = EstimateCache::default();
+        let computations = AtomicUsize::new(0);
+        let compute = |value: u64| {
+            computations.fetch_add(1, Ordering::Relaxed);
+            Some(value)
+        };
+
+        assert_eq!(
+            cache.get_or_compute(1, "a".to_string(), || compute(10)),
+            Some(10)
+        );
+        assert_eq!(
+            cache.get_or_compute(1, "a".to_string(), || compute(20)),
+            Some(10)
+        );
+        assert_eq!(
+            cache.get_or_compute(1, "b".to_string(), || compute(30)),
+            Some(30)
+        );
+        assert_eq!(computations.load(Ordering::Relaxed), 2);
+        assert_eq!(cache.len(), 2);
+
+        // new data drops the previous entries
+        assert_eq!(
+            cache.get_or_compute(2, "a".to_string(), || compute(40)),
+            Some(40)
+        );
+        assert_eq!(cache.len(), 1);
+        // late requests against outdated data are computed but not cached
+        assert_eq!(
+            cache.get_or_compute(1, "a".to_string(), || compute(50)),
+            Some(50)
+        );
+        assert_eq!(cache.len(), 1);
+        assert_eq!(computations.load(Ordering::Relaxed), 4);
+    }
+
+    #[test]
+    fn test_failures_are_not_cached() {
+        set_global_default(StatsdClient::builder("", NopMetricSink).build());
+        let cache = EstimateCache::default();
+
+        assert_eq!(cache.get_or_compute(1, "a".to_string(), || None), None);
+        assert!(cache.is_empty());
+        assert_eq!(
+            cache.get_or_compute(1, "a".to_string(), || Some(7)),
+            Some(7)
+        );
+        assert_eq!(cache.get_or_compute(1, "a".to_string(), || None), Some(7));
+    }
+
+    #[test]
+    fn test_computations_do_not_block() {
+        set_global_default(StatsdClient::builder("", NopMetricSink).build());
+        let cache = Arc::new(EstimateCache::default());
+        let (started, wait_started) = mpsc::channel();
+        let (finish, wait_finish) = mpsc::channel::<()>();
+
+        let slow = {
+            let cache = cache.clone();
+            thread::spawn(move || {
+                cache.get_or_compute(1, "same".to_string(), || {
+                    started.send(()).unwrap();
+                    wait_finish.recv().unwrap();
+                    Some(1)
+                })
+            })
+        };
+        wait_started.recv().unwrap();
+
+        // an identical request computes on its own while the first one is running
+        assert_eq!(
+            cache.get_or_compute(1, "same".to_string(), || Some(2)),
+            Some(2)
+        );
+        finish.send(()).unwrap();
+        // the value stored first wins
+        assert_eq!(slow.join().unwrap(), Some(2));
+        assert_eq!(
+            cache.get_or_compute(1, "same".to_string(), || {
+                thread::sleep(Duration::from_millis(50));
+                Some(3)
+            }),
+            Some(2)
+        );
+    }
+}
@@ -6,44 +6,96 @@ use futures::{sink::SinkExt, stream::StreamExt};
 use rand::distr::Alphanumeric;
 use rand::Rng;
 use tokio::time::sleep;
-use tracing::error;
+use tracing::{error, info, info_span, Span};
 use yellowstone_grpc_client::GeyserGrpcClient;
 use yellowstone_grpc_proto::prelude::{
     subscribe_update::UpdateOneof, CommitmentLevel, SubscribeRequest, SubscribeRequestFilterBlocks,
-    SubscribeRequestPing,
+    SubscribeRequestFilterSlots, SubscribeRequestPing,
 };
 use yellowstone_grpc_proto::tonic::codec::CompressionEncoding;
 
 use crate::grpc_consumer::GrpcConsumer;
+use crate::health::IngestStatus;
+use crate::ingest_pipeline::{IngestOptions, IngestPipeline};
+use crate::SlotCommitment;
 
 /// Implementation of a gRPC Geyser client that streams updates to consumers.
 pub struct GrpcGeyserImpl {
     endpoint: String,
     auth_header: Option<String>,
+    commitment: SlotCommitment,
+    max_decoding_message_size: usize,
     consumers: Vec<Arc<dyn GrpcConsumer>>,
+    ingest_options: IngestOptions,
+    status: Arc<IngestStatus>,
 }
 
 impl GrpcGeyserImpl {
-    /// Creates a new GrpcGeyserImpl and starts polling for blocks.
+    /// Creates a new GrpcGeyserImpl and starts polling for blocks at the given commitment,
+    /// accepting messages of up to `max_decoding_message_size` bytes.
     pub fn new(
         endpoint: String,
         auth_header: Option<String>,
+        commitment: SlotCommitment,
+        max_decoding_message_size: usize,
         consumers: Vec<Arc<dyn GrpcConsumer>>,
+    ) -> Self {
+        Self::new_with_options(
+            endpoint,
+            auth_header,
+            commitment,
+            max_decoding_message_size,
+            consumers,
+            IngestOptions::default(),
+        )
+    }
+
+    /// Creates a new GrpcGeyserImpl whose updates are consumed by a worker pool configured by
+    /// `ingest_options`.
+    pub fn new_with_options(
+        endpoint: String,
+        auth_header: Option<String>,
+        commitment: SlotCommitment,
+        max_decoding_message_size: usize,
+        consumers: Vec<Arc<dyn GrpcConsumer>>,
+        ingest_options: IngestOptions,
     ) -> Self {
         let grpc_geyser = Self {
             endpoint,
             auth_header,
+            commitment,
+            max_decoding_message_size,
             consumers,
+            ingest_options,
+            status: Arc::new(IngestStatus::default()),
         };
-        // polling with confirmed commitment to get confirmed transactions
+        // polling with confirmed commitment to get confirmed transactions, processed commitment
+        // trades fork safety for latency and relies on slot status updates to reconcile
         grpc_geyser.poll_blocks();
         grpc_geyser
     }
 
+    /// Returns the connection state and last error of the stream.
+    pub fn status(&self) -> Arc<IngestStatus> {
+        self.status.clone()
+    }
+
     fn poll_blocks(&self) {
         let endpoint = self.endpoint.clone();
         let auth_header = self.auth_header.clone();
-        let consumers = self.consumers.clone();
+        let commitment = match self.commitment {
+            SlotCommitment::Processed => CommitmentLevel::Processed,
+            SlotCommitment::Confirmed => CommitmentLevel::Confirmed,
+        };
+        let max_decoding_message_size = self.max_decoding_message_size;
+        let status = self.status.clone();
+        let cluster = self.ingest_options.cluster.clone();
+        // consumers run on the pipeline workers so that a slow block does not stall the stream
+        let pipeline = IngestPipeline::spawn(
+            self.consumers.clone(),
+            self.ingest_options.clone(),
+            status.clone(),
+        );
         tokio::spawn(async move {
             loop {
                 let mut grpc_tx;
@@ -54,45 +106,65 @@ impl GrpcGeyserImpl {
                         .x_token(auth_header.clone())
                         .unwrap()
                         .connect_timeout(Duration::from_secs(10))
-                        .max_decoding_message_size(50_000_000)
+                        .max_decoding_message_size(max_decoding_message_size)
                         .accept_compressed(CompressionEncoding::Gzip)
                         .connect()
                         .await;
 
                     if let Err(e) = grpc_client {
                         error!("Error connecting to gRPC, waiting one second then retrying connect: {}", e);
-                        statsd_count!("grpc_connect_error", 1);
+                        statsd_count!("grpc_connect_error", 1, "cluster" => &cluster);
+                        status.record_error(format!("error connecting to gRPC: {e}"));
                         sleep(Duration::from_secs(1)).await;
                         continue;
                     }
                     let mut grpc_client = grpc_client.unwrap();
                     let subscription = grpc_client
-                        .subscribe_with_request(Some(get_block_subscribe_request()))
+                        .subscribe_with_request(Some(get_block_subscribe_request(commitment)))
                         .await;
                     if let Err(e) = subscription {
                         error!("Error subscribing to gRPC stream, waiting one second then retrying connect: {}", e);
-                        statsd_count!("grpc_subscribe_error", 1);
+                        statsd_count!("grpc_subscribe_error", 1, "cluster" => &cluster);
+                        status.record_error(format!("error subscribing to gRPC stream: {e}"));
                         sleep(Duration::from_secs(1)).await;
                         continue;
                     }
                     (grpc_tx, grpc_rx) = subscription.unwrap();
+                    status.set_connected(true);
                 }
-                while let Some(update) = grpc_rx.next().await {
+                loop {
+                    let update = tokio::select! {
+                        update = grpc_rx.next() => update,
+                        _ = status.resubscribe_requested() => {
+                            info!("resubscribing to gRPC stream on request");
+                            statsd_count!("grpc_resubscribe_requested", 1, "cluster" => &cluster);
+                            break;
+                        }
+                    };
+                    let Some(update) = update else {
+                        break;
+                    };
                     match update {
                         Ok(update) => {
-                            for consumer in consumers.clone() {
-                                if let Err(e) = consumer.consume(&update) {
-                                    error!("Error consuming update: {}", e);
-                                    statsd_count!("grpc_consume_error", 1);
-                                }
-                            }
-                            if let Some(UpdateOneof::Ping(_)) = update.update_oneof {
+                            // each block is traced from its receipt to its insertion in the trackers
+                            let span = match &update.update_oneof {
+                                Some(UpdateOneof::Block(block)) => info_span!(
+                                    "geyser_block",
+                                    slot = block.slot,
+                                    transaction_count = block.transactions.len()
+                                ),
+                                _ => Span::none(),
+                            };
+                            let is_ping = matches!(update.update_oneof, Some(UpdateOneof::Ping(_)));
+                            pipeline.submit(update, span).await;
+                            if is_ping {
                                 // This is necessary to keep load balancers that expect client pings alive. If your load balancer doesn't
                                 // require periodic client pings then this is unnecessary
                                 let ping = grpc_tx.send(ping()).await;
                                 if let Err(e) = ping {
                                     error!("Error sending ping: {}", e);
-                                    statsd_count!("grpc_ping_error", 1);
+                                    statsd_count!("grpc_ping_error", 1, "cluster" => &cluster);
+                                    status.record_error(format!("error sending ping: {e}"));
                                     break;
                                 }
                             }
@@ -101,18 +173,20 @@ impl GrpcGeyserImpl {
                             error!(
                                 "error in block subscribe, resubscribing in 1 second: {error:?}"
                             );
-                            statsd_count!("grpc_resubscribe", 1);
+                            statsd_count!("grpc_resubscribe", 1, "cluster" => &cluster);
+                            status.record_error(format!("error in block subscribe: {error}"));
                             break;
                         }
                     }
                 }
+                status.set_connected(false);
                 sleep(Duration::from_secs(1)).await;
             }
         });
     }
 }
 
-fn get_block_subscribe_request() -> SubscribeRequest {
+fn get_block_subscribe_request(commitment: CommitmentLevel) -> SubscribeRequest {
     SubscribeRequest {
         blocks: HashMap::from_iter(vec![(
             generate_random_string(20),
@@ -123,7 +197,16 @@ fn get_block_subscribe_request() -> SubscribeRequest {
                 include_entries: Some(false),
             },
         )]),
-        commitment: Some(CommitmentLevel::Confirmed.into()),
+        // slot status updates for every commitment level, dead slots are reported without
+        // interslot updates
+        slots: HashMap::from_iter(vec![(
+            generate_random_string(20),
+            SubscribeRequestFilterSlots {
+                filter_by_commitment: Some(false),
+                ..Default::default()
+            },
+        )]),
+        commitment: Some(commitment.into()),
         ..Default::default()
     }
 }
@@ -0,0 +1,207 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use solana_sdk::clock::Slot;
+use tokio::sync::Notify;
+
+use crate::config::RuntimeConfig;
+use crate::priority_fee::PriorityFeeTracker;
+
+/// State of the gRPC ingestion, updated by the Geyser client.
+#[derive(Debug, Default)]
+pub struct IngestStatus {
+    connected: AtomicBool,
+    last_error: RwLock<Option<IngestError>>,
+    resubscribe: Notify,
+}
+
+/// Error raised while ingesting Geyser updates.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all(serialize = "camelCase", deserialize = "camelCase"))]
+pub struct IngestError {
+    /// Description of the error.
+    pub message: String,
+    /// Unix timestamp of the error, in seconds.
+    pub timestamp: i64,
+}
+
+impl IngestStatus {
+    /// Returns whether the gRPC stream is currently subscribed.
+    pub fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::Relaxed)
+    }
+
+    /// Records whether the gRPC stream is subscribed.
+    pub fn set_connected(&self, connected: bool) {
+        self.connected.store(connected, Ordering::Relaxed);
+    }
+
+    /// Returns the most recent ingest error.
+    pub fn last_error(&self) -> Option<IngestError> {
+        self.last_error
+            .read()
+            .expect("status lock poisoned")
+            .clone()
+    }
+
+    /// Asks the Geyser client to drop its stream and subscribe again. A request made while
+    /// disconnected applies to the next stream.
+    pub fn request_resubscribe(&self) {
+        self.resubscribe.notify_one();
+    }
+
+    /// Completes once a resubscribe is requested.
+    pub async fn resubscribe_requested(&self) {
+        self.resubscribe.notified().await;
+    }
+
+    /// Records an ingest error.
+    pub fn record_error(&self, message: impl Into<String>) {
+        *self.last_error.write().expect("status lock poisoned") = Some(IngestError {
+            message: message.into(),
+            timestamp: unix_timestamp(),
+        });
+    }
+}
+
+/// Detailed health of the estimator, returned by `getHealth`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all(serialize = "camelCase", deserialize = "camelCase"))]
+pub struct HealthReport {
+    /// Whether the estimator is ready to serve estimates.
+    pub ready: bool,
+    /// Reasons the estimator is not ready, empty when ready.
+    pub not_ready_reasons: Vec<String>,
+    /// Whether the gRPC stream is currently subscribed.
+    pub grpc_connected: bool,
+    /// Newest tracked slot.
+    pub newest_slot: Option<Slot>,
+    /// Seconds since the block time of the newest tracked slot, if known.
+    pub newest_slot_age_seconds: Option<i64>,
+    /// Number of slots in the window.
+    pub window_slots: usize,
+    /// Number of slots the window holds when full.
+    pub window_capacity: usize,
+    /// Share of the window holding slots.
+    pub window_fill: f64,
+    /// Most recent ingest error.
+    pub last_ingest_error: Option<IngestError>,
+}
+
+impl HealthReport {
+    /// Builds the health report of the tracker, checking readiness against the runtime settings.
+    ///
+    /// The estimator is ready once the window is filled to `ready_min_window_fill` and the block
+    /// time of the newest slot is at most `ready_max_slot_age_seconds` old.
+    pub fn new(
+        tracker: &PriorityFeeTracker,
+        window_capacity: usize,
+        status: &IngestStatus,
+        config: &RuntimeConfig,
+    ) -> Self {
+        let newest = tracker.slot_summary(None);
+        let newest_slot_age_seconds = newest
+            .as_ref()
+            .and_then(|summary| summary.block.as_ref()?.block_time)
+            .map(|block_time| (unix_timestamp() - block_time).max(0));
+        let window_slots = tracker.tracked_slot_count();
+        let window_fill = if window_capacity == 0 {
+            0.0
+        } else {
+            (window_slots as f64 / window_capacity as f64).min(1.0)
+        };
+
+        let mut not_ready_reasons = vec![];
+        if window_fill < config.ready_min_window_fill {
+            not_ready_reasons.push(format!(
+                "window holds {window_slots} of {window_capacity} slots"
+            ));
+        }
+        match newest_slot_age_seconds {
+            Some(age) if age > config.ready_max_slot_age_seconds as i64 => {
+                not_ready_reasons.push(format!("newest slot is {age} seconds old"))
+            }
+            Some(_) => {}
+            None => not_ready_reasons.push("block time of the newest slot is unknown".to_string()),
+        }
+
+        Self {
+            ready: not_ready_reasons.is_empty(),
+            not_ready_reasons,
+            grpc_connected: status.is_connected(),
+            newest_slot: newest.map(|summary| summary.slot),
+            newest_slot_age_seconds,
+            window_slots,
+            window_capacity,
+            window_fill,
+            last_ingest_error: status.last_error(),
+        }
+    }
+}
+
+fn unix_timestamp() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BlockMetadata;
+    use cadence::{NopMetricSink, StatsdClient};
+    use cadence_macros::set_global_default;
+
+    #[tokio::test]
+    async fn test_health_report() {
+        set_global_default(StatsdClient::builder("", NopMetricSink).build());
+
+        let tracker = PriorityFeeTracker::new(4);
+        let status = IngestStatus::default();
+        let config = RuntimeConfig::default();
+
+        let report = HealthReport::new(&tracker, 4, &status, &config);
+        assert!(!report.ready);
+        assert_eq!(report.newest_slot, None);
+        assert_eq!(report.not_ready_reasons.len(), 2);
+
+        let now = unix_timestamp();
+        for slot in 1..=4 {
+            tracker.push_block_metadata(
+                slot,
+                BlockMetadata {
+                    block_time: Some(now - 60 + slot as i64),
+                    parent_slot: slot - 1,
+                    ..Default::default()
+                },
+            );
+        }
+        status.set_connected(true);
+        status.record_error("stream reset");
+
+        // the window is warm but the newest block is stale
+        let report = HealthReport::new(&tracker, 4, &status, &config);
+        assert_eq!(report.newest_slot, Some(4));
+        assert_eq!(report.window_slots, 4);
+        assert_eq!(report.window_fill, 1.0);
+        assert!(report.grpc_connected);
+        assert_eq!(report.last_ingest_error.unwrap().message, "stream reset");
+        assert!(!report.ready);
+        assert_eq!(report.not_ready_reasons.len(), 1);
+
+        let report = HealthReport::new(
+            &tracker,
+            4,
+            &status,
+            &RuntimeConfig {
+                ready_max_slot_age_seconds: 120,
+                ..Default::default()
+            },
+        );
+        assert!(report.ready);
+        assert!(report.not_ready_reasons.is_empty());
+    }
+}
@@ -0,0 +1,307 @@
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use cadence_macros::{statsd_count, statsd_gauge};
+use crossbeam_channel::{bounded, Receiver, Sender, TrySendError};
+use tokio::time::sleep;
+use tracing::{error, warn, Span};
+use yellowstone_grpc_proto::geyser::subscribe_update::UpdateOneof;
+use yellowstone_grpc_proto::geyser::SubscribeUpdate;
+
+use crate::grpc_consumer::GrpcConsumer;
+use crate::health::IngestStatus;
+
+/// Interval between two attempts to enqueue an update into a full queue.
+const ENQUEUE_RETRY_INTERVAL: Duration = Duration::from_millis(5);
+
+/// Options controlling how Geyser updates are handed to the consumers.
+#[derive(Debug, Clone)]
+pub struct IngestOptions {
+    /// Number of worker threads running the consumers.
+    pub workers: usize,
+    /// Number of updates each worker queues before the stream is held back.
+    pub queue_capacity: usize,
+    /// How long the stream is held back by a full queue before the update is dropped.
+    pub max_enqueue_wait: Duration,
+    /// Cluster the updates come from, tagging the ingestion metrics.
+    pub cluster: String,
+}
+
+impl Default for IngestOptions {
+    fn default() -> Self {
+        Self {
+            workers: 4,
+            queue_capacity: 32,
+            max_enqueue_wait: Duration::from_secs(1),
+            cluster: "default".to_string(),
+        }
+    }
+}
+
+/// Update queued for the workers, with the span started on its receipt.
+type QueuedUpdate = (SubscribeUpdate, Span);
+
+/// Pool of worker threads consuming Geyser updates from bounded queues.
+///
+/// Updates are sharded by slot, so the block and status updates of a slot are consumed in order
+/// by the same worker while different slots are consumed in parallel. When the queue of a worker
+/// is full the receive loop waits for up to `max_enqueue_wait`, holding back the stream, before
+/// dropping the update.
+///
+/// Slots are therefore consumed out of order: with more than one worker, slot N + 1 may be
+/// consumed before slot N. Consumers must not assume monotone slots; the tracker's slot cache
+/// evicts its oldest slot rather than the first one pushed, and ignores late slots older than a
+/// full window.
+#[derive(Debug)]
+pub struct IngestPipeline {
+    queues: Vec<Sender<QueuedUpdate>>,
+    options: IngestOptions,
+    status: Arc<IngestStatus>,
+}
+
+impl IngestPipeline {
+    /// Starts the workers, each consuming its queue with every consumer.
+    pub fn spawn(
+        consumers: Vec<Arc<dyn GrpcConsumer>>,
+        options: IngestOptions,
+        status: Arc<IngestStatus>,
+    ) -> Self {
+        let queues = (0..options.workers.max(1))
+            .map(|worker| {
+                let (sender, receiver) = bounded(options.queue_capacity.max(1));
+                let consumers = consumers.clone();
+                let status = status.clone();
+                let cluster = options.cluster.clone();
+                thread::Builder::new()
+                    .name(format!("ingest-{worker}"))
+                    .spawn(move || consume_queue(receiver, consumers, status, cluster))
+                    .expect("expected ingest worker to start");
+                sender
+            })
+            .collect();
+        Self {
+            queues,
+            options,
+            status,
+        }
+    }
+
+    /// Queues an update for its worker, waiting while the queue is full. Returns false if the
+    /// update was dropped.
+    pub async fn submit(&self, update: SubscribeUpdate, span: Span) -> bool {
+        let slot = update_slot(&update);
+        let cluster = self.options.cluster.as_str();
+        let queue = &self.queues[(slot % self.queues.len() as u64) as usize];
+        let deadline = Instant::now() + self.options.max_enqueue_wait;
+        let mut queued = (update, span);
+        let mut held_back = false;
+        loop {
+            match queue.try_send(queued) {
+                Ok(()) => {
+                    statsd_gauge!(
+                        "ingest_queue_depth",
+                        self.queue_depth() as u64,
+                        "cluster" => cluster
+                    );
+                    return true;
+                }
+                Err(TrySendError::Full(update)) if Instant::now() < deadline => {
+                    if !held_back {
+                        held_back = true;
+                        statsd_count!("ingest_queue_full", 1, "cluster" => cluster);
+                    }
+                    queued = update;
+                    sleep(ENQUEUE_RETRY_INTERVAL).await;
+                }
+                Err(TrySendError::Full(_)) => {
+                    warn!("ingest queue full, dropping update for slot {}", slot);
+                    statsd_count!("ingest_update_dropped", 1, "cluster" => cluster);
+                    self.status
+                        .record_error(format!("ingest queue full, dropped update for slot {slot}"));
+                    return false;
+                }
+                Err(TrySendError::Disconnected(_)) => {
+                    error!("ingest worker stopped, dropping update for slot {}", slot);
+                    statsd_count!("ingest_update_dropped", 1, "cluster" => cluster);
+                    return false;
+                }
+            }
+        }
+    }
+
+    /// Returns the number of updates waiting in the queues.
+    pub fn queue_depth(&self) -> usize {
+        self.queues.iter().map(Sender::len).sum()
+    }
+}
+
+/// Returns the slot an update belongs to, 0 for updates without a slot.
+fn update_slot(update: &SubscribeUpdate) -> u64 {
+    match &update.update_oneof {
+        Some(UpdateOneof::Block(block)) => block.slot,
+        Some(UpdateOneof::Slot(slot)) => slot.slot,
+        _ => 0,
+    }
+}
+
+fn consume_queue(
+    receiver: Receiver<QueuedUpdate>,
+    consumers: Vec<Arc<dyn GrpcConsumer>>,
+    status: Arc<IngestStatus>,
+    cluster: String,
+) {
+    for (update, span) in receiver {
+        span.in_scope(|| {
+            for consumer in &consumers {
+                if let Err(e) = consumer.consume(&update) {
+                    error!("Error consuming update: {}", e);
+                    statsd_count!("grpc_consume_error", 1, "cluster" => &cluster);
+                    status.record_error(format!("error consuming update: {e}"));
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::priority_fee::PriorityFeeTracker;
+    use cadence::{NopMetricSink, StatsdClient};
+    use cadence_macros::set_global_default;
+    use std::sync::Mutex;
+    use yellowstone_grpc_proto::prelude::{SlotStatus, SubscribeUpdateBlock, SubscribeUpdateSlot};
+
+    /// Records the consumed slots, consuming an update for each token received on `gate`.
+    #[derive(Debug)]
+    struct RecordingConsumer {
+        slots: Mutex<Vec<u64>>,
+        gate: Receiver<()>,
+    }
+
+    impl RecordingConsumer {
+        fn new() -> (Arc<Self>, Sender<()>) {
+            let (sender, gate) = crossbeam_channel::unbounded();
+            let consumer = Self {
+                slots: Mutex::default(),
+                gate,
+            };
+            (Arc::new(consumer), sender)
+        }
+    }
+
+    impl GrpcConsumer for RecordingConsumer {
+        fn consume(&self, message: &SubscribeUpdate) -> Result<(), String> {
+            self.gate.recv().map_err(|e| e.to_string())?;
+            self.slots.lock().unwrap().push(update_slot(message));
+            Ok(())
+        }
+    }
+
+    fn slot_update(slot: u64) -> SubscribeUpdate {
+        SubscribeUpdate {
+            update_oneof: Some(UpdateOneof::Slot(SubscribeUpdateSlot {
+                slot,
+                status: SlotStatus::SlotConfirmed as i32,
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+    }
+
+    async fn wait_for_slots(consumer: &RecordingConsumer, count: usize) -> Vec<u64> {
+        for _ in 0..200 {
+            let slots = consumer.slots.lock().unwrap().clone();
+            if slots.len() >= count {
+                return slots;
+            }
+            sleep(Duration::from_millis(5)).await;
+        }
+        panic!("expected {count} consumed updates");
+    }
+
+    #[tokio::test]
+    async fn test_updates_stay_ordered_per_worker() {
+        set_global_default(StatsdClient::builder("", NopMetricSink).build());
+        let (consumer, gate) = RecordingConsumer::new();
+        for _ in 0..3 {
+            gate.send(()).unwrap();
+        }
+        let pipeline = IngestPipeline::spawn(
+            vec![consumer.clone()],
+            IngestOptions {
+                workers: 1,
+                ..Default::default()
+            },
+            Arc::default(),
+        );
+        for slot in [3, 1, 2] {
+            assert!(pipeline.submit(slot_update(slot), Span::none()).await);
+        }
+        assert_eq!(wait_for_slots(&consumer, 3).await, vec![3, 1, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_out_of_order_blocks_keep_newest_window() {
+        set_global_default(StatsdClient::builder("", NopMetricSink).build());
+        let tracker = Arc::new(PriorityFeeTracker::new(2));
+        let pipeline = IngestPipeline::spawn(
+            vec![tracker.clone()],
+            IngestOptions {
+                workers: 1,
+                ..Default::default()
+            },
+            Arc::default(),
+        );
+        let block_update = |slot| SubscribeUpdate {
+            update_oneof: Some(UpdateOneof::Block(SubscribeUpdateBlock {
+                slot,
+                parent_slot: slot - 1,
+                ..Default::default()
+            })),
+            ..Default::default()
+        };
+
+        // slot 11 is consumed before slot 10, as it may be when sharded across workers
+        for slot in [11, 10, 12] {
+            assert!(pipeline.submit(block_update(slot), Span::none()).await);
+        }
+        for _ in 0..200 {
+            if tracker.newest_slot() == Some(12) {
+                break;
+            }
+            sleep(Duration::from_millis(5)).await;
+        }
+        assert_eq!(tracker.tracked_slots(), vec![12, 11]);
+    }
+
+    #[tokio::test]
+    async fn test_full_queue_drops_updates() {
+        set_global_default(StatsdClient::builder("", NopMetricSink).build());
+        let (consumer, gate) = RecordingConsumer::new();
+        let status = Arc::new(IngestStatus::default());
+        let pipeline = IngestPipeline::spawn(
+            vec![consumer.clone()],
+            IngestOptions {
+                workers: 1,
+                queue_capacity: 1,
+                max_enqueue_wait: Duration::from_millis(20),
+                ..Default::default()
+            },
+            status.clone(),
+        );
+
+        // the worker blocks on the first update, the second one fills the queue
+        assert!(pipeline.submit(slot_update(1), Span::none()).await);
+        sleep(Duration::from_millis(20)).await;
+        assert!(pipeline.submit(slot_update(2), Span::none()).await);
+        assert!(!pipeline.submit(slot_update(3), Span::none()).await);
+        assert_eq!(pipeline.queue_depth(), 1);
+        assert!(status.last_error().unwrap().message.contains("slot 3"));
+        gate.send(()).unwrap();
+        gate.send(()).unwrap();
+
+        assert_eq!(wait_for_slots(&consumer, 2).await, vec![1, 2]);
+    }
+}
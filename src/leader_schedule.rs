@@ -0,0 +1,126 @@
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+use std::time::Duration;
+
+use cadence_macros::{statsd_count, statsd_gauge};
+use dashmap::DashMap;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::clock::Slot;
+use solana_sdk::pubkey::Pubkey;
+use tracing::error;
+
+use crate::LeaderSchedule;
+
+/// Number of slots fetched ahead of the current slot on every refresh.
+const LEADER_SLOTS_AHEAD: u64 = 2_000;
+/// Number of slots behind the current slot whose leaders are kept, so that late blocks still
+/// find their leader.
+const LEADER_SLOTS_BEHIND: u64 = 500;
+/// Interval between two refreshes of the schedule.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Leader schedule fetched from a Solana RPC node and refreshed in the background.
+pub struct RpcLeaderSchedule {
+    rpc_client: Arc<RpcClient>,
+    leaders: DashMap<Slot, Pubkey>,
+}
+
+impl Debug for RpcLeaderSchedule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RpcLeaderSchedule")
+            .field("rpc_client", &"RpcClient { ... }") // RpcClient does not implement Debug
+            .field("leaders", &self.leaders.len())
+            .finish()
+    }
+}
+
+impl RpcLeaderSchedule {
+    /// Creates an empty schedule fetched with `rpc_client`.
+    pub fn new(rpc_client: Arc<RpcClient>) -> Self {
+        Self {
+            rpc_client,
+            leaders: DashMap::new(),
+        }
+    }
+
+    /// Creates a schedule and starts refreshing it from a tokio task.
+    ///
+    /// The RPC client is blocking, so every refresh runs on the blocking thread pool.
+    pub fn spawn(rpc_client: Arc<RpcClient>) -> Arc<Self> {
+        let schedule = Arc::new(Self::new(rpc_client));
+        let refreshed = schedule.clone();
+        tokio::spawn(async move {
+            loop {
+                let schedule = refreshed.clone();
+                let result = tokio::task::spawn_blocking(move || schedule.refresh())
+                    .await
+                    .unwrap_or_else(|e| Err(e.into()));
+                if let Err(e) = result {
+                    error!("failed to refresh leader schedule: {:?}", e);
+                    statsd_count!("leader_schedule_refresh_failed", 1);
+                }
+                tokio::time::sleep(REFRESH_INTERVAL).await;
+            }
+        });
+        schedule
+    }
+
+    /// Fetches the leaders of the upcoming slots and drops the leaders of old slots.
+    pub fn refresh(&self) -> anyhow::Result<()> {
+        let current_slot = self.rpc_client.get_slot()?;
+        let leaders = self
+            .rpc_client
+            .get_slot_leaders(current_slot, LEADER_SLOTS_AHEAD)?;
+        self.store(current_slot, leaders);
+        Ok(())
+    }
+
+    /// Records `leaders` as the leaders of the slots starting at `current_slot`, dropping the
+    /// leaders of slots more than [`LEADER_SLOTS_BEHIND`] slots older.
+    fn store(&self, current_slot: Slot, leaders: Vec<Pubkey>) {
+        for (offset, leader) in leaders.into_iter().enumerate() {
+            self.leaders.insert(current_slot + offset as Slot, leader);
+        }
+        let oldest_slot = current_slot.saturating_sub(LEADER_SLOTS_BEHIND);
+        self.leaders.retain(|slot, _| *slot >= oldest_slot);
+        statsd_gauge!("leader_schedule_slots", self.leaders.len() as u64);
+    }
+}
+
+impl LeaderSchedule for RpcLeaderSchedule {
+    fn slot_leader(&self, slot: Slot) -> Option<Pubkey> {
+        self.leaders.get(&slot).map(|leader| *leader)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cadence::{NopMetricSink, StatsdClient};
+    use cadence_macros::set_global_default;
+
+    #[test]
+    fn test_store_leaders() {
+        set_global_default(StatsdClient::builder("", NopMetricSink).build());
+        let schedule = RpcLeaderSchedule::new(Arc::new(RpcClient::new(
+            "http://localhost:8899".to_string(),
+        )));
+        let leader_1 = Pubkey::new_unique();
+        let leader_2 = Pubkey::new_unique();
+
+        schedule.store(1_000, vec![leader_1, leader_1, leader_2]);
+        assert_eq!(schedule.slot_leader(1_000), Some(leader_1));
+        assert_eq!(schedule.slot_leader(1_002), Some(leader_2));
+        assert_eq!(schedule.slot_leader(1_003), None);
+        assert_eq!(schedule.upcoming_leaders(999, 4), vec![leader_1, leader_2]);
+
+        // leaders of slots far behind the current slot are dropped
+        schedule.store(1_000 + LEADER_SLOTS_BEHIND + 1, vec![leader_2]);
+        assert_eq!(schedule.slot_leader(1_000), None);
+        assert_eq!(schedule.slot_leader(1_002), Some(leader_2));
+        assert_eq!(
+            schedule.slot_leader(1_000 + LEADER_SLOTS_BEHIND + 1),
+            Some(leader_2)
+        );
+    }
+}
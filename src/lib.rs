@@ -5,24 +5,50 @@
 
 // Re-export core types from priority-fee-core
 pub use priority_fee_core::{
-    Calculations, DataType, Fees, MicroLamportPriorityFeeDetails, MicroLamportPriorityFeeEstimates,
-    PriorityFeeTracker, PriorityFeesBySlot, PriorityLevel, SlotCache, SlotPriorityFees,
+    Aggregation, BlockMetadata, CalculationOptions, Calculations, ConfidenceInterval, DataType,
+    FeeSamples, Fees, LamportTipEstimates, LeaderBoost, LeaderFeeHistory, LeaderSchedule,
+    LeaderSlotFees, MicroLamportPriorityFeeDetails, MicroLamportPriorityFeeEstimates,
+    OutlierFilter, PayerDedup, PriorityFeeTracker, PriorityFeesBySlot, PriorityLevel, SlotCache,
+    SlotCommitment, SlotFeeBreakdown, SlotFeeForecast, SlotFeeSamples, SlotPriorityFees,
+    SlotSummary, TransactionStatusFilter,
 };
 
+/// Admin JSON-RPC methods for inspecting and controlling the tracker.
+pub mod admin;
+/// API key authentication and per-key quotas.
+pub mod auth;
+/// Routing of requests to the estimators of several clusters.
+pub mod cluster;
+/// Layered configuration loaded from a TOML file and the environment.
+pub mod config;
+/// Periodic CSV export of per-slot fee aggregates.
+pub mod csv_export;
 /// Error types for the priority fee estimator.
 pub mod errors;
+/// Per-data-version cache of estimates coalescing identical requests.
+pub mod estimate_cache;
 /// gRPC consumer trait and implementations.
 pub mod grpc_consumer;
 /// gRPC Geyser client implementation.
 pub mod grpc_geyser;
+/// Ingestion state and readiness reporting.
+pub mod health;
+/// Worker pool consuming Geyser updates behind bounded queues.
+pub mod ingest_pipeline;
+/// Leader schedule fetched from a Solana RPC node.
+pub mod leader_schedule;
 /// Core priority fee tracking and estimation logic with gRPC integration.
 pub mod priority_fee;
 /// Statistical calculation algorithms with metrics integration.
 pub mod priority_fee_calculation;
+/// Configurable policies deriving the recommended fee from the estimates.
+pub mod recommended_policy;
 /// JSON-RPC server implementation.
 pub mod rpc_server;
 /// Solana-specific utilities and RPC helpers.
 pub mod solana;
+/// Logging and OpenTelemetry span export.
+pub mod telemetry;
 
 // Re-exports for model types are now from priority-fee-core
 // slot_cache is also from priority-fee-core
@@ -3,66 +3,192 @@
 //! This binary starts the priority fee estimator service, which consumes
 //! Solana Geyser data via gRPC and provides a JSON-RPC API for fee estimation.
 
-use std::{env, net::UdpSocket, sync::Arc};
+use std::{env, net::UdpSocket, path::PathBuf, str::FromStr, sync::Arc, time::Duration};
 
+use atlas_priority_fee_estimator::admin::{AdminCluster, AtlasAdmin, AtlasAdminRpcServer};
+use atlas_priority_fee_estimator::auth::{ApiKeyLayer, ApiKeyStore};
+use atlas_priority_fee_estimator::cluster::{ClusterPathLayer, ClusterRouter};
+use atlas_priority_fee_estimator::config::{ClusterConfig, EstimatorConfig, RuntimeConfigStore};
+use atlas_priority_fee_estimator::csv_export::CsvExporter;
 use atlas_priority_fee_estimator::grpc_geyser::GrpcGeyserImpl;
-use atlas_priority_fee_estimator::priority_fee::PriorityFeeTracker;
+use atlas_priority_fee_estimator::leader_schedule::RpcLeaderSchedule;
+use atlas_priority_fee_estimator::priority_fee::{PriorityFeeTracker, TrackerOptions};
+use atlas_priority_fee_estimator::recommended_policy::RecommendedPolicyStore;
 use atlas_priority_fee_estimator::rpc_server::{
     AtlasPriorityFeeEstimator, AtlasPriorityFeeEstimatorRpcServer,
 };
+use atlas_priority_fee_estimator::telemetry;
+use atlas_priority_fee_estimator::LeaderSchedule;
 use cadence::{BufferedUdpMetricSink, QueuingMetricSink, StatsdClient};
 use cadence_macros::set_global_default;
-use figment::{providers::Env, Figment};
 use jsonrpsee::server::middleware::http::ProxyGetRequestLayer;
 use jsonrpsee::server::{ServerBuilder, ServerConfig};
-use serde::Deserialize;
+use priority_fee_core::tips;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
 use tracing::{error, info};
 
-#[derive(Debug, Deserialize, Clone)]
-struct EstimatorEnv {
-    max_lookback_slots: Option<usize>,
-    port: Option<u16>,
-    grpc_url: String,
-    grpc_x_token: Option<String>,
-    rpc_url: String,
-}
-
 #[tokio::main]
 async fn main() {
     // Init metrics/logging
-    let env: EstimatorEnv = Figment::from(Env::raw()).extract().unwrap();
+    let config_file = env::var("CONFIG_FILE").ok().map(PathBuf::from);
+    let config = EstimatorConfig::load(config_file.as_deref()).unwrap();
     let env_filter = env::var("RUST_LOG").unwrap_or_else(|_| "info".to_string());
-    tracing_subscriber::fmt()
-        .with_env_filter(env_filter)
-        .json()
-        .init();
+    let tracer_provider = telemetry::init(&env_filter, config.otlp_endpoint.as_deref())
+        .expect("expected valid otlp endpoint");
     new_metrics_client();
-    let max_lookback_slots = env.max_lookback_slots.unwrap_or(150);
-    let priority_fee_tracker = Arc::new(PriorityFeeTracker::new(max_lookback_slots));
-    // start grpc consumer
-    let _ = GrpcGeyserImpl::new(
-        env.grpc_url,
-        env.grpc_x_token,
-        vec![priority_fee_tracker.clone()],
-    );
-
-    let port = env.port.unwrap_or(4141);
-    let config = ServerConfig::builder().max_connections(100_000).build();
-    let server = ServerBuilder::with_config(config)
+    let clusters = config.clusters();
+    let api_keys = config
+        .api_keys_file
+        .as_deref()
+        .map(|path| ApiKeyStore::spawn(path.into()).expect("expected valid api key file"));
+    let port = config.port;
+    let server_config = ServerConfig::builder()
+        .max_connections(config.max_connections)
+        .build();
+    let server = ServerBuilder::with_config(server_config)
         .set_http_middleware(
             tower::ServiceBuilder::new()
-                // Proxy `GET /health` requests to internal `health` method.
+                // Serve `/<cluster>/...` paths, such as `/devnet/ready`, from the named cluster.
+                .layer(ClusterPathLayer::new(
+                    clusters.iter().map(|cluster| cluster.name.clone()),
+                ))
+                // Proxy `GET /health` and `GET /ready` requests to internal `health` and `ready`
+                // methods, `/ready` fails while the estimator is not ready.
                 .layer(
-                    ProxyGetRequestLayer::new([("/health", "health")])
+                    ProxyGetRequestLayer::new([("/health", "health"), ("/ready", "ready")])
                         .expect("expected health check to initialize"),
-                ),
+                )
+                // Authenticate requests and enforce per-key quotas when api keys are configured.
+                .layer(ApiKeyLayer::new(api_keys)),
         )
         .build(format!("0.0.0.0:{}", port))
         .await
         .unwrap_or_else(|_| panic!("failed to start server on port {}", port));
-    let rpc = AtlasPriorityFeeEstimator::new(priority_fee_tracker, env.rpc_url, max_lookback_slots);
-    let handle = server.start(rpc.into_rpc());
+    // without a policy file, the default policy follows the configured recommended fee floor
+    let (recommended_policies, floor_policies) = match config.recommended_policy_file.as_deref() {
+        Some(path) => (
+            RecommendedPolicyStore::spawn(path.into())
+                .expect("expected valid recommended fee policy file"),
+            None,
+        ),
+        None => {
+            let policies = Arc::new(RecommendedPolicyStore::new(
+                config.runtime.recommended_policies(),
+            ));
+            (policies.clone(), Some(policies))
+        }
+    };
+    let runtime_config = match config_file {
+        Some(path) => RuntimeConfigStore::spawn(path, config.clone(), floor_policies),
+        None => Arc::new(RuntimeConfigStore::new(config.runtime.clone())),
+    };
+    // every cluster has its own tracker, gRPC stream and RPC client, and shares the settings
+    let mut router: Option<ClusterRouter> = None;
+    let mut admin: Option<AtlasAdmin> = None;
+    for cluster in &clusters {
+        let rpc_client = Arc::new(RpcClient::new(cluster.rpc_url.clone()));
+        let priority_fee_tracker = new_tracker(&config, cluster, &rpc_client);
+        // start grpc consumer
+        let grpc_geyser = GrpcGeyserImpl::new_with_options(
+            cluster.grpc_url.clone(),
+            cluster.grpc_x_token.clone(),
+            cluster.grpc_commitment,
+            config.grpc_max_decoding_message_size,
+            vec![priority_fee_tracker.clone()],
+            config.ingest_options(&cluster.name),
+        );
+        let csv_export_options = config
+            .csv_export_options(&cluster.name)
+            .expect("expected valid csv export accounts");
+        if let Some(options) = csv_export_options {
+            CsvExporter::spawn(priority_fee_tracker.clone(), options)
+                .expect("expected writable csv export directory");
+        }
+        let admin_cluster = AdminCluster {
+            priority_fee_tracker: priority_fee_tracker.clone(),
+            max_lookback_slots: cluster.max_lookback_slots,
+            ingest_status: grpc_geyser.status(),
+        };
+        admin = Some(match admin {
+            Some(admin) => admin.with_cluster(cluster.name.clone(), admin_cluster),
+            None => AtlasAdmin::new(cluster.name.clone(), admin_cluster, runtime_config.clone()),
+        });
+        let estimator = AtlasPriorityFeeEstimator::new(
+            priority_fee_tracker,
+            rpc_client,
+            cluster.max_lookback_slots,
+        )
+        .with_recommended_policies(recommended_policies.clone())
+        .with_runtime_config(runtime_config.clone())
+        .with_ingest_status(grpc_geyser.status());
+        router = Some(match router {
+            Some(router) => router.with_cluster(cluster.name.clone(), estimator),
+            None => ClusterRouter::new(cluster.name.clone(), estimator),
+        });
+        info!("serving cluster {}", cluster.name);
+    }
+    let router = router.expect("expected at least one cluster");
+    let admin = admin.expect("expected at least one cluster");
+    // the admin server is bound separately so that it is never exposed with the public port, and
+    // like the public server serves `/<cluster>` paths from the named cluster
+    let admin_handle = match config.admin_port {
+        Some(admin_port) => {
+            let admin_server = ServerBuilder::default()
+                .set_http_middleware(tower::ServiceBuilder::new().layer(ClusterPathLayer::new(
+                    clusters.iter().map(|cluster| cluster.name.clone()),
+                )))
+                .build(format!("{}:{}", config.admin_host, admin_port))
+                .await
+                .unwrap_or_else(|_| panic!("failed to start admin server on port {}", admin_port));
+            info!(
+                "admin server listening on {}:{}",
+                config.admin_host, admin_port
+            );
+            Some(admin_server.start(admin.into_rpc()))
+        }
+        None => None,
+    };
+    let handle = server.start(router.into_rpc());
     handle.stopped().await;
+    if let Some(admin_handle) = admin_handle {
+        let _ = admin_handle.stop();
+    }
+    if let Some(tracer_provider) = tracer_provider {
+        if let Err(e) = tracer_provider.shutdown() {
+            error!("failed to flush spans: {}", e);
+        }
+    }
+}
+
+/// Creates the tracker of `cluster`, fetching its leader schedule with `rpc_client` when enabled.
+fn new_tracker(
+    config: &EstimatorConfig,
+    cluster: &ClusterConfig,
+    rpc_client: &Arc<RpcClient>,
+) -> Arc<PriorityFeeTracker> {
+    let leader_schedule = cluster
+        .leader_schedule_enabled
+        .then(|| RpcLeaderSchedule::spawn(rpc_client.clone()) as Arc<dyn LeaderSchedule>);
+    let tip_accounts = match cluster.tip_accounts.as_deref() {
+        Some(tip_accounts) => tip_accounts
+            .split(',')
+            .map(str::trim)
+            .filter(|account| !account.is_empty())
+            .map(|account| Pubkey::from_str(account).expect("expected valid tip account"))
+            .collect(),
+        None => tips::jito_tip_accounts(),
+    };
+    Arc::new(PriorityFeeTracker::new_with_options(
+        cluster.max_lookback_slots,
+        TrackerOptions {
+            max_slot_age: cluster.max_lookback_seconds.map(Duration::from_secs),
+            ingest_commitment: cluster.grpc_commitment,
+            leader_schedule,
+            tip_accounts,
+            poll_interval: Some(Duration::from_millis(config.poll_interval_ms)),
+        },
+    ))
 }
 
 fn new_metrics_client() {
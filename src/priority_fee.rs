@@ -1,90 +1,139 @@
 use crate::errors::TransactionValidationError;
 use crate::grpc_consumer::GrpcConsumer;
-use crate::priority_fee_calculation::Calculations;
 use crate::priority_fee_calculation::Calculations::Calculation2;
+use crate::priority_fee_calculation::{self, Calculations};
 use crate::rpc_server::get_recommended_fee;
-use crate::SlotCache;
 use crate::{
-    Fees, MicroLamportPriorityFeeDetails, MicroLamportPriorityFeeEstimates, PriorityFeesBySlot,
-    PriorityLevel, SlotPriorityFees,
+    BlockMetadata, CalculationOptions, LamportTipEstimates, LeaderSchedule, LeaderSlotFees,
+    MicroLamportPriorityFeeDetails, MicroLamportPriorityFeeEstimates, PriorityFeesBySlot,
+    SlotCommitment, SlotFeeBreakdown, SlotFeeForecast, SlotPriorityFees, SlotSummary,
 };
 use agave_feature_set::FeatureSet;
 use cadence_macros::statsd_count;
 use cadence_macros::statsd_gauge;
-use dashmap::mapref::entry::Entry;
+use priority_fee_core::leader::UPCOMING_LEADER_SLOTS;
+use priority_fee_core::tips;
+use priority_fee_core::PriorityFeeTracker as CoreTracker;
+use rayon::prelude::*;
 use solana::storage::confirmed_block::Message;
 use solana_compute_budget_instruction::instructions_processor::process_compute_budget_instructions;
 use solana_message::compiled_instruction::CompiledInstruction;
 use solana_sdk::transaction::TransactionError;
 use solana_sdk::{pubkey::Pubkey, slot_history::Slot};
 use solana_svm_transaction::instruction::SVMInstruction;
-use statrs::statistics::{Data, Distribution, OrderStatistics};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
-use tracing::error;
+use tracing::{error, info_span};
 use yellowstone_grpc_proto::geyser::subscribe_update::UpdateOneof;
 use yellowstone_grpc_proto::geyser::{SubscribeUpdate, SubscribeUpdateTransactionInfo};
-use yellowstone_grpc_proto::prelude::{MessageHeader, Transaction, TransactionStatusMeta};
+use yellowstone_grpc_proto::prelude::{
+    MessageHeader, SlotStatus, Transaction, TransactionStatusMeta,
+};
 use yellowstone_grpc_proto::solana;
 
 /// Tracks priority fees across slots and provides estimation methods.
+///
+/// Wraps the core tracker with Geyser ingestion, metrics and the background maintenance task.
 #[derive(Debug, Clone)]
 pub struct PriorityFeeTracker {
-    priority_fees: Arc<PriorityFeesBySlot>,
-    slot_cache: SlotCache,
+    tracker: CoreTracker,
+    options: TrackerOptions,
+    data_version: Arc<AtomicU64>,
+}
+
+/// Options controlling how a [`PriorityFeeTracker`] ingests and retains slots.
+#[derive(Debug, Clone, Default)]
+pub struct TrackerOptions {
+    /// Drop slots whose block time is older than this, even when ingestion stalls.
+    pub max_slot_age: Option<Duration>,
+    /// Commitment of the blocks streamed from Geyser. Processed blocks are kept provisionally
+    /// until slot status updates confirm or abandon them.
+    pub ingest_commitment: SlotCommitment,
+    /// Source of slot leaders, enables leader-aware estimation and per-leader fee history.
+    pub leader_schedule: Option<Arc<dyn LeaderSchedule>>,
+    /// Accounts receiving tips. System transfers to these accounts are tracked as tips, none are
+    /// tracked when empty.
+    pub tip_accounts: HashSet<Pubkey>,
+    /// Interval between two runs of the background task, one second when unset.
+    pub poll_interval: Option<Duration>,
 }
 
+type MetaDetails = (Vec<Pubkey>, Vec<Pubkey>, bool);
+
+/// Returns the writable and readonly accounts loaded from lookup tables and whether the
+/// transaction failed.
 fn extract_from_meta(
-    transaction_meta: Option<TransactionStatusMeta>,
-) -> Result<Vec<Pubkey>, TransactionValidationError> {
+    transaction_meta: Option<&TransactionStatusMeta>,
+) -> Result<MetaDetails, TransactionValidationError> {
     match transaction_meta {
-        None => Ok(Vec::with_capacity(0)),
+        None => Ok((Vec::with_capacity(0), Vec::with_capacity(0), false)),
         Some(meta) => {
-            if meta.err.is_some() {
-                // statsd_count!("txn_failed", 1);
-                return Result::Err(TransactionValidationError::TransactionFailed);
-            }
+            let failed = meta.err.is_some();
             let writable = meta
                 .loaded_writable_addresses
-                .into_iter()
-                .filter_map(|v| Pubkey::try_from(v).ok())
+                .iter()
+                .filter_map(|v| Pubkey::try_from(v.as_slice()).ok())
+                .collect::<Vec<Pubkey>>();
+            let readonly = meta
+                .loaded_readonly_addresses
+                .iter()
+                .filter_map(|v| Pubkey::try_from(v.as_slice()).ok())
                 .collect::<Vec<Pubkey>>();
 
-            Ok(writable)
+            Ok((writable, readonly, failed))
         }
     }
 }
+
+type TransactionDetails<'a> = (&'a Message, Vec<Pubkey>, Vec<Pubkey>, bool, bool);
+
+/// Returns the message, lookup-table writable and readonly accounts, vote flag and failure flag.
+///
+/// Failed vote transactions carry no contention signal and are rejected.
 fn extract_from_transaction(
-    mut transaction: SubscribeUpdateTransactionInfo,
-) -> Result<(Message, Vec<Pubkey>, bool), TransactionValidationError> {
-    let writable_accounts = extract_from_meta(transaction.meta.take())?;
-    let tran: &mut Transaction = transaction
+    transaction: &SubscribeUpdateTransactionInfo,
+) -> Result<TransactionDetails<'_>, TransactionValidationError> {
+    let (writable_accounts, readonly_accounts, failed) =
+        extract_from_meta(transaction.meta.as_ref())?;
+    let is_vote = transaction.is_vote;
+    if failed && is_vote {
+        return Err(TransactionValidationError::TransactionFailed);
+    }
+    let tran: &Transaction = transaction
         .transaction
-        .as_mut()
+        .as_ref()
         .ok_or(TransactionValidationError::TransactionMissing)?;
-    let message: Message = tran
+    let message: &Message = tran
         .message
-        .take()
+        .as_ref()
         .ok_or(TransactionValidationError::MessageMissing)?;
-    let is_vote = transaction.is_vote;
 
-    Ok((message, writable_accounts, is_vote))
+    Ok((
+        message,
+        writable_accounts,
+        readonly_accounts,
+        is_vote,
+        failed,
+    ))
 }
 
 type MessageDetails = (Vec<Pubkey>, Vec<CompiledInstruction>, Option<MessageHeader>);
 
-fn extract_from_message(message: Message) -> Result<MessageDetails, TransactionValidationError> {
-    let account_keys = message.account_keys;
-    let compiled_instructions = message.instructions;
-
-    let accounts: Result<Vec<Pubkey>, _> = account_keys.into_iter().map(Pubkey::try_from).collect();
+fn extract_from_message(message: &Message) -> Result<MessageDetails, TransactionValidationError> {
+    let accounts: Result<Vec<Pubkey>, _> = message
+        .account_keys
+        .iter()
+        .map(|key| Pubkey::try_from(key.as_slice()))
+        .collect();
     if accounts.is_err() {
         return Err(TransactionValidationError::InvalidAccount);
     }
     let accounts = accounts.unwrap();
 
-    let compiled_instructions: Vec<CompiledInstruction> = compiled_instructions
+    let compiled_instructions: Vec<CompiledInstruction> = message
+        .instructions
         .iter()
         .map(|ix| {
             CompiledInstruction::new_from_raw_parts(
@@ -95,7 +144,28 @@ fn extract_from_message(message: Message) -> Result<MessageDetails, TransactionV
         })
         .collect();
 
-    Ok((accounts, compiled_instructions, message.header))
+    Ok((accounts, compiled_instructions, message.header.clone()))
+}
+
+/// Returns the name of the metric counting transactions rejected with `error`.
+const fn validation_error_metric(error: &TransactionValidationError) -> &'static str {
+    match error {
+        TransactionValidationError::TransactionFailed => "txn_failed",
+        TransactionValidationError::TransactionMissing => "txn_missing",
+        TransactionValidationError::MessageMissing => "message_missing",
+        TransactionValidationError::InvalidAccount => "invalid_pubkey",
+    }
+}
+
+/// Fee data extracted from a block transaction, ready to be inserted into the tracker.
+struct ExtractedTransaction {
+    payer: Option<Pubkey>,
+    writable_accounts: Vec<Pubkey>,
+    priority_fee: Result<u64, TransactionError>,
+    tip: u64,
+    compute_units: Option<u64>,
+    is_vote: bool,
+    failed: bool,
 }
 
 fn calculate_priority_fee_details(
@@ -150,54 +220,100 @@ pub(crate) fn construct_writable_accounts<T>(
 
 impl GrpcConsumer for PriorityFeeTracker {
     fn consume(&self, update: &SubscribeUpdate) -> Result<(), String> {
-        match update.update_oneof.clone() {
+        match &update.update_oneof {
             Some(UpdateOneof::Block(block)) => {
+                let _span = info_span!(
+                    "consume_block",
+                    slot = block.slot,
+                    transaction_count = block.transactions.len()
+                )
+                .entered();
                 statsd_count!("blocks_processed", 1);
                 statsd_count!("txns_received", block.transactions.len() as i64);
                 let slot = block.slot;
-                for txn in block.transactions {
-                    let res = extract_from_transaction(txn);
-                    if let Err(error) = res {
-                        let err_str: &str = match error {
-                            TransactionValidationError::TransactionFailed => "txn_failed",
-                            TransactionValidationError::TransactionMissing => "txn_missing",
-                            TransactionValidationError::MessageMissing => "message_missing",
-                            TransactionValidationError::InvalidAccount => "invalid_pubkey",
-                        };
-                        statsd_count!(err_str, 1);
-                        continue;
-                    }
-                    let (message, writable_accounts, is_vote) = res.unwrap();
-
-                    let res = extract_from_message(message);
-                    if let Err(error) = res {
-                        let err_str: &str = match error {
-                            TransactionValidationError::TransactionFailed => "txn_failed",
-                            TransactionValidationError::TransactionMissing => "txn_missing",
-                            TransactionValidationError::MessageMissing => "message_missing",
-                            TransactionValidationError::InvalidAccount => "invalid_pubkey",
-                        };
-                        statsd_count!(err_str, 1);
-                        continue;
+                let block_metadata = BlockMetadata {
+                    block_time: block.block_time.map(|time| time.timestamp),
+                    block_height: block.block_height.map(|height| height.block_height),
+                    parent_slot: block.parent_slot,
+                    blockhash: block.blockhash.clone(),
+                    executed_transaction_count: block.executed_transaction_count,
+                    compute_units_consumed: block
+                        .transactions
+                        .iter()
+                        .filter_map(|txn| txn.meta.as_ref()?.compute_units_consumed)
+                        .sum(),
+                };
+                // extraction does not touch the tracker, so transactions are decoded in parallel
+                // and inserted in block order
+                let transactions: Vec<ExtractedTransaction> = info_span!("extract_transactions")
+                    .in_scope(|| {
+                        block
+                            .transactions
+                            .par_iter()
+                            .filter_map(|txn| self.extract_transaction(txn))
+                            .collect()
+                    });
+
+                let insert_span = info_span!(
+                    "tracker_insert",
+                    slot,
+                    transactions_processed = transactions.len()
+                );
+                let _insert = insert_span.enter();
+                self.push_block_metadata_with_commitment(
+                    slot,
+                    block_metadata,
+                    self.options.ingest_commitment,
+                );
+                let leader = self
+                    .options
+                    .leader_schedule
+                    .as_ref()
+                    .and_then(|schedule| schedule.slot_leader(slot));
+                match leader {
+                    Some(leader) => self.push_slot_leader(slot, leader),
+                    None if self.options.leader_schedule.is_some() => {
+                        statsd_count!("slot_leader_unknown", 1);
                     }
-                    let (accounts, instructions, header) = res.unwrap();
-                    let priority_fee_details =
-                        calculate_priority_fee_details(&accounts, &instructions);
-
-                    let writable_accounts = [
-                        construct_writable_accounts(accounts, &header),
+                    None => {}
+                }
+                for txn in transactions {
+                    let ExtractedTransaction {
+                        payer,
                         writable_accounts,
-                    ]
-                    .concat();
-
-                    statsd_count!(
-                        "priority_fee_tracker.accounts_processed",
-                        writable_accounts.len() as i64
-                    );
-                    statsd_count!("txns_processed", 1);
-                    match priority_fee_details {
-                        Ok(priority_fee) => self.push_priority_fee_for_txn(
+                        priority_fee,
+                        tip,
+                        compute_units,
+                        is_vote,
+                        failed,
+                    } = txn;
+                    let tip_accounts: Option<Vec<Pubkey>> = (tip > 0).then(|| {
+                        writable_accounts
+                            .iter()
+                            .filter(|account| !self.options.tip_accounts.contains(account))
+                            .copied()
+                            .collect()
+                    });
+                    if let Some(compute_units) = compute_units.filter(|units| *units > 0) {
+                        self.push_compute_units_for_txn(
                             slot,
+                            writable_accounts.clone(),
+                            compute_units,
+                        );
+                    }
+                    match priority_fee {
+                        Ok(priority_fee) if failed => {
+                            statsd_count!("txn_failed", 1);
+                            self.push_payer_failed_priority_fee_for_txn(
+                                slot,
+                                payer,
+                                writable_accounts,
+                                priority_fee,
+                            )
+                        }
+                        Ok(priority_fee) => self.push_payer_priority_fee_for_txn(
+                            slot,
+                            payer,
                             writable_accounts,
                             priority_fee,
                             is_vote,
@@ -206,8 +322,27 @@ impl GrpcConsumer for PriorityFeeTracker {
                             error!("error processing priority fee details: {:?}", e);
                         }
                     }
+                    // tips are recorded on the slot entry created by the fee
+                    if let Some(tip_accounts) = tip_accounts {
+                        statsd_count!("tips_processed", 1);
+                        self.push_tip_for_txn(slot, tip_accounts, tip);
+                    }
                 }
+                self.complete_slot(slot);
             }
+            Some(UpdateOneof::Slot(slot_update)) => match slot_update.status() {
+                // dead slots never produce a block that reaches the confirmed chain
+                SlotStatus::SlotDead => {
+                    statsd_count!("dead_slots_received", 1);
+                    self.abandon_slot(slot_update.slot);
+                }
+                SlotStatus::SlotConfirmed
+                    if self.options.ingest_commitment == SlotCommitment::Processed =>
+                {
+                    self.confirm_slot(slot_update.slot, slot_update.parent);
+                }
+                _ => {}
+            },
             _ => return Ok(()),
         }
         Ok(())
@@ -217,20 +352,100 @@ impl GrpcConsumer for PriorityFeeTracker {
 impl PriorityFeeTracker {
     /// Creates a new PriorityFeeTracker with the specified slot cache length.
     pub fn new(slot_cache_length: usize) -> Self {
+        Self::new_with_options(slot_cache_length, TrackerOptions::default())
+    }
+
+    /// Creates a new PriorityFeeTracker with the specified slot cache length and options.
+    pub fn new_with_options(slot_cache_length: usize, options: TrackerOptions) -> Self {
         let tracker = Self {
-            priority_fees: Arc::new(PriorityFeesBySlot::default()),
-            slot_cache: SlotCache::new(slot_cache_length),
+            tracker: CoreTracker::new(slot_cache_length),
+            options,
+            data_version: Arc::new(AtomicU64::new(0)),
         };
         tracker.poll_fees();
         tracker
     }
 
+    /// Extracts the fee data of a block transaction, returning `None` for transactions that
+    /// cannot be decoded.
+    fn extract_transaction(
+        &self,
+        txn: &SubscribeUpdateTransactionInfo,
+    ) -> Option<ExtractedTransaction> {
+        let compute_units = txn
+            .meta
+            .as_ref()
+            .and_then(|meta| meta.compute_units_consumed);
+        let (message, writable_accounts, readonly_accounts, is_vote, failed) =
+            extract_from_transaction(txn)
+                .inspect_err(|error| {
+                    statsd_count!(validation_error_metric(error), 1);
+                })
+                .ok()?;
+        let (accounts, instructions, header) = extract_from_message(message)
+            .inspect_err(|error| {
+                statsd_count!(validation_error_metric(error), 1);
+            })
+            .ok()?;
+        // the fee payer is always the first static account key
+        let payer = accounts.first().copied();
+        let priority_fee = calculate_priority_fee_details(&accounts, &instructions);
+        let tip = if failed || self.options.tip_accounts.is_empty() {
+            0
+        } else {
+            // instructions index the static account keys followed by the writable and then the
+            // readonly addresses loaded from lookup tables
+            let transaction_accounts =
+                [accounts.as_slice(), &writable_accounts, &readonly_accounts].concat();
+            tips::transaction_tip(
+                &transaction_accounts,
+                instructions.iter().map(|ix| {
+                    (
+                        ix.program_id_index,
+                        ix.accounts.as_slice(),
+                        ix.data.as_slice(),
+                    )
+                }),
+                &self.options.tip_accounts,
+            )
+        };
+
+        let writable_accounts = [
+            construct_writable_accounts(accounts, &header),
+            writable_accounts,
+        ]
+        .concat();
+
+        statsd_count!(
+            "priority_fee_tracker.accounts_processed",
+            writable_accounts.len() as i64
+        );
+        statsd_count!("txns_processed", 1);
+        Some(ExtractedTransaction {
+            payer,
+            writable_accounts,
+            priority_fee,
+            tip,
+            compute_units,
+            is_vote,
+            failed,
+        })
+    }
+
     fn poll_fees(&self) {
         let priority_fee_tracker = self.clone();
-        // task to run global fee comparison every 1 second
+        let poll_interval = self
+            .options
+            .poll_interval
+            .unwrap_or(Duration::from_millis(1_000));
+        // task to run global fee comparison every poll interval, 1 second by default
         tokio::spawn(async move {
             loop {
-                tokio::time::sleep(Duration::from_millis(1_000)).await;
+                tokio::time::sleep(poll_interval).await;
+                if let Some(max_slot_age) = priority_fee_tracker.options.max_slot_age {
+                    let removed = priority_fee_tracker.remove_slots_older_than(max_slot_age);
+                    statsd_count!("stale_slots_removed", removed as i64);
+                }
                 priority_fee_tracker.record_general_fees();
             }
         });
@@ -242,6 +457,7 @@ impl PriorityFeeTracker {
             include_vote: false,
             include_empty_slots: false,
             lookback_period: &None,
+            options: CalculationOptions::default(),
         });
         if let Ok(global_fees) = global_fees {
             statsd_gauge!(
@@ -278,6 +494,11 @@ impl PriorityFeeTracker {
         }
     }
 
+    /// Returns a reference to the underlying priority fees data.
+    pub fn priority_fees(&self) -> &PriorityFeesBySlot {
+        self.tracker.priority_fees()
+    }
+
     /// Pushes a priority fee for a transaction into the tracker.
     pub fn push_priority_fee_for_txn(
         &self,
@@ -286,45 +507,274 @@ impl PriorityFeeTracker {
         priority_fee: u64,
         is_vote: bool,
     ) {
-        // update the slot cache so we can keep track of the slots we have processed in order
-        // for removal later
-        let slot_to_remove = self.slot_cache.push_pop(slot);
-        match self.priority_fees.entry(slot) {
-            Entry::Occupied(mut entry) => {
-                let priority_fees = entry.get_mut();
-                priority_fees.fees.add_fee(priority_fee as f64, is_vote);
-                for account in accounts {
-                    priority_fees
-                        .account_fees
-                        .entry(account)
-                        .and_modify(|fees: &mut Fees| fees.add_fee(priority_fee as f64, is_vote))
-                        .or_insert(Fees::new(priority_fee as f64, is_vote));
-                }
-            }
-            Entry::Vacant(entry) => {
-                entry.insert(SlotPriorityFees::new(slot, accounts, priority_fee, is_vote));
-            }
+        self.push_payer_priority_fee_for_txn(slot, None, accounts, priority_fee, is_vote);
+    }
+
+    /// Pushes a priority fee for a transaction paid by `payer` into the tracker.
+    pub fn push_payer_priority_fee_for_txn(
+        &self,
+        slot: Slot,
+        payer: Option<Pubkey>,
+        accounts: Vec<Pubkey>,
+        priority_fee: u64,
+        is_vote: bool,
+    ) {
+        self.tracker
+            .push_payer_priority_fee_for_txn(slot, payer, accounts, priority_fee, is_vote);
+    }
+
+    /// Pushes the priority fee of a failed non-vote transaction into the tracker.
+    pub fn push_failed_priority_fee_for_txn(
+        &self,
+        slot: Slot,
+        accounts: Vec<Pubkey>,
+        priority_fee: u64,
+    ) {
+        self.push_payer_failed_priority_fee_for_txn(slot, None, accounts, priority_fee);
+    }
+
+    /// Pushes the priority fee of a failed non-vote transaction paid by `payer` into the tracker.
+    pub fn push_payer_failed_priority_fee_for_txn(
+        &self,
+        slot: Slot,
+        payer: Option<Pubkey>,
+        accounts: Vec<Pubkey>,
+        priority_fee: u64,
+    ) {
+        self.tracker
+            .push_payer_failed_priority_fee_for_txn(slot, payer, accounts, priority_fee);
+    }
+
+    /// Pushes the tip, in lamports, paid by a transaction writing to `accounts`.
+    ///
+    /// Tips of slots without fees are dropped, see [`CoreTracker::push_tip_for_txn`].
+    pub fn push_tip_for_txn(&self, slot: Slot, accounts: Vec<Pubkey>, tip: u64) {
+        self.tracker.push_tip_for_txn(slot, accounts, tip);
+    }
+
+    /// Pushes the compute units consumed by a transaction writing to `accounts`.
+    pub fn push_compute_units_for_txn(
+        &self,
+        slot: Slot,
+        accounts: Vec<Pubkey>,
+        compute_units: u64,
+    ) {
+        self.tracker
+            .push_compute_units_for_txn(slot, accounts, compute_units);
+    }
+
+    /// Records block metadata for a slot, creating the slot entry if no fees were pushed yet.
+    ///
+    /// Slots between the block's parent and the block itself are recorded as skipped.
+    pub fn push_block_metadata(&self, slot: Slot, block: BlockMetadata) {
+        self.push_block_metadata_with_commitment(slot, block, SlotCommitment::Confirmed);
+    }
+
+    /// Records block metadata for a slot ingested at the given commitment.
+    pub fn push_block_metadata_with_commitment(
+        &self,
+        slot: Slot,
+        block: BlockMetadata,
+        commitment: SlotCommitment,
+    ) {
+        let skipped = self
+            .tracker
+            .push_block_metadata_with_commitment(slot, block, commitment);
+        statsd_count!("skipped_slots_recorded", skipped as i64);
+    }
+
+    /// Promotes a processed slot to confirmed, dropping the older processed slots that are not on
+    /// its chain.
+    pub fn confirm_slot(&self, slot: Slot, parent_slot: Option<Slot>) {
+        let abandoned = self.tracker.confirm_slot(slot, parent_slot);
+        statsd_count!("processed_slots_abandoned", abandoned as i64);
+        self.touch();
+    }
+
+    /// Drops the provisional fees of a slot that will never be confirmed and records it as skipped.
+    pub fn abandon_slot(&self, slot: Slot) {
+        if self.tracker.abandon_slot(slot) {
+            statsd_count!("processed_slots_abandoned", 1);
+        }
+        self.touch();
+    }
+
+    /// Records the leader of a tracked slot. Untracked slots are ignored.
+    pub fn push_slot_leader(&self, slot: Slot, leader: Pubkey) {
+        self.tracker.push_slot_leader(slot, leader);
+    }
+
+    /// Marks a slot as fully ingested, adding its fees to the history of its leader and publishing
+    /// them to cached estimates.
+    ///
+    /// Call once all transactions of the slot were pushed. Fees pushed for a slot are not visible
+    /// through [`Self::data_version`] until the slot is completed.
+    pub fn complete_slot(&self, slot: Slot) {
+        self.tracker.record_leader_slot(slot);
+        self.touch();
+    }
+
+    /// Returns the fee statistics of the most recent slots produced by `leader`, oldest first.
+    pub fn leader_fee_history(&self, leader: &Pubkey) -> Vec<LeaderSlotFees> {
+        self.tracker.leader_fee_history(leader)
+    }
+
+    /// Returns the distinct leaders of the slots following the newest tracked slot.
+    ///
+    /// Returns `None` when no leader schedule is configured.
+    pub fn upcoming_leaders(&self) -> Option<Vec<Pubkey>> {
+        let schedule = self.options.leader_schedule.as_ref()?;
+        let newest_slot = self.newest_slot().unwrap_or_default();
+        Some(schedule.upcoming_leaders(newest_slot, UPCOMING_LEADER_SLOTS))
+    }
+
+    /// Returns the summary for the given slot, or for the newest tracked slot if none is given.
+    pub fn slot_summary(&self, slot: Option<Slot>) -> Option<SlotSummary> {
+        self.tracker.slot_summary(slot)
+    }
+
+    /// Returns the newest tracked slot.
+    pub fn newest_slot(&self) -> Option<Slot> {
+        self.tracker.newest_slot()
+    }
+
+    /// Returns summaries for the slots selected by the lookback window, newest first.
+    pub fn slot_summaries(
+        &self,
+        lookback_period: &Option<u32>,
+        options: &CalculationOptions,
+    ) -> Vec<SlotSummary> {
+        self.tracker.slot_summaries(lookback_period, options)
+    }
+
+    /// Returns the fees recorded for `account`, or the global fees, in the given slot.
+    pub fn slot_fee_breakdown(
+        &self,
+        account: Option<&Pubkey>,
+        slot: Slot,
+        max_raw_fees: usize,
+    ) -> Option<SlotFeeBreakdown> {
+        self.tracker.slot_fee_breakdown(account, slot, max_raw_fees)
+    }
+
+    /// Returns the fees recorded for `account`, or the global fees, in the tracked slots between
+    /// `start_slot` and `end_slot` inclusive, newest first. Skipped slots are included.
+    pub fn slot_fee_breakdowns(
+        &self,
+        account: Option<&Pubkey>,
+        start_slot: Option<Slot>,
+        end_slot: Option<Slot>,
+        max_raw_fees: usize,
+    ) -> Vec<SlotFeeBreakdown> {
+        self.tracker
+            .slot_fee_breakdowns(account, start_slot, end_slot, max_raw_fees)
+    }
+
+    /// Returns a counter that changes once per completed slot, slot status update or removal of
+    /// tracked slots.
+    pub fn data_version(&self) -> u64 {
+        self.data_version.load(Ordering::Acquire)
+    }
+
+    fn touch(&self) {
+        self.data_version.fetch_add(1, Ordering::Release);
+    }
+
+    /// Returns the tracked slots, newest first.
+    pub fn tracked_slots(&self) -> Vec<Slot> {
+        self.tracker.tracked_slots()
+    }
+
+    /// Calls `inspect` with the fees of a tracked slot, returning `None` if the slot is not
+    /// tracked.
+    pub fn inspect_slot<R>(
+        &self,
+        slot: Slot,
+        inspect: impl FnOnce(&SlotPriorityFees) -> R,
+    ) -> Option<R> {
+        self.tracker.inspect_slot(slot, inspect)
+    }
+
+    /// Removes a slot and its fees, returning true if it was tracked.
+    pub fn evict_slot(&self, slot: Slot) -> bool {
+        let evicted = self.tracker.evict_slot(slot);
+        if evicted {
+            self.touch();
         }
+        evicted
+    }
 
-        if let Some(slot_to_remove) = slot_to_remove {
-            self.priority_fees.remove(&slot_to_remove);
+    /// Removes every tracked slot, returning the number removed. Leader fee history is kept.
+    pub fn clear(&self) -> usize {
+        let cleared = self.tracker.clear();
+        if cleared > 0 {
+            self.touch();
         }
+        cleared
     }
 
-    /// Calculates priority fee estimates based on the provided calculation algorithm.
+    /// Returns the number of slots in the window.
+    pub fn tracked_slot_count(&self) -> usize {
+        self.tracker.tracked_slot_count()
+    }
+
+    /// Removes slots whose block time is older than `max_age`, returning the number removed.
+    ///
+    /// Slots without a known block time are kept.
+    pub fn remove_slots_older_than(&self, max_age: Duration) -> usize {
+        let removed = self.tracker.remove_slots_older_than(max_age);
+        if removed > 0 {
+            self.touch();
+        }
+        removed
+    }
+
+    /// Forecasts fee percentiles for the next `horizon` slots from the per-slot fees of the given
+    /// accounts, or of all transactions when `accounts` is empty.
+    pub fn forecast_fees(
+        &self,
+        accounts: &[Pubkey],
+        include_vote: bool,
+        lookback_period: &Option<u32>,
+        options: &CalculationOptions,
+        horizon: u32,
+    ) -> Vec<SlotFeeForecast> {
+        self.tracker
+            .forecast_fees(accounts, include_vote, lookback_period, options, horizon)
+    }
+
+    /// Returns tip percentiles, in lamports, of the transactions writing to the given accounts, or
+    /// of all transactions when `accounts` is empty.
+    pub fn tip_estimates(
+        &self,
+        accounts: &[Pubkey],
+        lookback_period: &Option<u32>,
+        options: &CalculationOptions,
+    ) -> LamportTipEstimates {
+        self.tracker
+            .tip_estimates(accounts, lookback_period, options)
+    }
+
+    /// Calculates priority fee estimates based on the provided calculation algorithm, rounded to
+    /// whole micro-lamports.
     pub fn calculate_priority_fee(
         &self,
         calculation: &Calculations,
     ) -> anyhow::Result<MicroLamportPriorityFeeEstimates> {
-        let res = calculation.get_priority_fee_estimates(&self.priority_fees)?;
-        let res = res.into_iter().fold(
-            MicroLamportPriorityFeeEstimates::default(),
-            |estimate, mut data| estimate_max_values(&mut data.1, estimate),
+        let data = priority_fee_calculation::get_priority_fee_estimates(
+            calculation,
+            self.tracker.priority_fees(),
+        )?;
+        let estimates = self.tracker.aggregate_estimates(
+            data,
+            calculation.lookback_period(),
+            calculation.options(),
         );
-        Ok(res)
+        Ok(estimates.map(round_fee))
     }
 
-    /// Calculates detailed priority fee estimates and statistics.
+    /// Calculates detailed priority fee estimates and statistics, rounded to whole
+    /// micro-lamports.
     pub fn calculate_priority_fee_details(
         &self,
         calculation: &Calculations,
@@ -332,70 +782,46 @@ impl PriorityFeeTracker {
         MicroLamportPriorityFeeEstimates,
         HashMap<String, MicroLamportPriorityFeeDetails>,
     )> {
-        let data = calculation.get_priority_fee_estimates(&self.priority_fees)?;
-        let final_result: MicroLamportPriorityFeeEstimates = data.clone().into_iter().fold(
-            MicroLamportPriorityFeeEstimates::default(),
-            |estimate, mut data| estimate_max_values(&mut data.1, estimate),
+        let stats = priority_fee_calculation::get_priority_fee_details(
+            calculation,
+            self.tracker.priority_fees(),
+        )?;
+        let (estimates, mut details) = self.tracker.detail_estimates(
+            stats,
+            calculation.accounts(),
+            calculation.lookback_period(),
+            calculation.options(),
         );
-        let results: HashMap<String, MicroLamportPriorityFeeDetails> = data
-            .into_iter()
-            .map(|mut data| {
-                let estimate = MicroLamportPriorityFeeDetails {
-                    estimates: estimate_max_values(
-                        &mut data.1,
-                        MicroLamportPriorityFeeEstimates::default(),
-                    ),
-                    mean: data.1.mean().unwrap_or(f64::NAN).round(),
-                    stdev: data.1.std_dev().unwrap_or(f64::NAN).round(),
-                    skew: data.1.skewness().unwrap_or(f64::NAN).round(),
-                    count: data.1.len(),
-                };
-                (data.0.to_string(), estimate)
-            })
-            .collect();
-        Ok((final_result, results))
+        for detail in details.values_mut() {
+            detail.estimates = detail.estimates.clone().map(round_fee);
+            detail.failed_estimates = detail.failed_estimates.clone().map(round_fee);
+            detail.mean = detail.mean.round();
+            detail.stdev = detail.stdev.round();
+            detail.skew = detail.skew.round();
+            if let Some(interval) = detail.confidence_interval.as_mut() {
+                interval.lower = interval.lower.clone().map(f64::round);
+                interval.upper = interval.upper.clone().map(f64::round);
+            }
+        }
+        Ok((estimates.map(round_fee), details))
     }
 }
 
-fn estimate_max_values(
-    fees: &mut Data<Vec<f64>>,
-    mut estimates: MicroLamportPriorityFeeEstimates,
-) -> MicroLamportPriorityFeeEstimates {
-    estimates.min = fees
-        .percentile(PriorityLevel::Min.into())
-        .round()
-        .max(estimates.min);
-    estimates.low = fees
-        .percentile(PriorityLevel::Low.into())
-        .round()
-        .max(estimates.low);
-    estimates.medium = fees
-        .percentile(PriorityLevel::Medium.into())
-        .round()
-        .max(estimates.medium);
-    estimates.high = fees
-        .percentile(PriorityLevel::High.into())
-        .round()
-        .max(estimates.high);
-    estimates.very_high = fees
-        .percentile(PriorityLevel::VeryHigh.into())
-        .round()
-        .max(estimates.very_high);
-    estimates.unsafe_max = fees
-        .percentile(PriorityLevel::UnsafeMax.into())
-        .round()
-        .max(estimates.unsafe_max);
-    estimates
+/// Rounds a fee to whole micro-lamports, fees of data types without samples become zero.
+fn round_fee(fee: f64) -> f64 {
+    fee.round().max(0.0)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::telemetry::{capture_spans, span_attribute};
     use anyhow::Context;
     use cadence::{NopMetricSink, StatsdClient};
     use cadence_macros::set_global_default;
     use solana_compute_budget_interface::ComputeBudgetInstruction;
     use std::collections::HashSet;
+    use yellowstone_grpc_proto::geyser::{SubscribeUpdateBlock, SubscribeUpdateSlot};
 
     fn init_metrics() {
         let noop = NopMetricSink {};
@@ -1547,6 +1973,204 @@ mod tests {
         assert_eq!(estimates.unsafe_max, expected_max_fee);
     }
 
+    #[tokio::test]
+    async fn test_skipped_slots() {
+        init_metrics();
+        let tracker = PriorityFeeTracker::new(10);
+        let account = Pubkey::new_unique();
+
+        tracker.push_block_metadata(
+            20,
+            BlockMetadata {
+                parent_slot: 17,
+                ..Default::default()
+            },
+        );
+        tracker.push_priority_fee_for_txn(20, vec![account], 100, false);
+        let dead_slot = SubscribeUpdate {
+            update_oneof: Some(UpdateOneof::Slot(SubscribeUpdateSlot {
+                slot: 21,
+                status: SlotStatus::SlotDead as i32,
+                ..Default::default()
+            })),
+            ..Default::default()
+        };
+        tracker.consume(&dead_slot).unwrap();
+
+        let skipped: Vec<Slot> = (17..=21)
+            .filter(|slot| {
+                tracker
+                    .slot_summary(Some(*slot))
+                    .is_some_and(|summary| summary.skipped)
+            })
+            .collect();
+        assert_eq!(skipped, vec![18, 19, 21]);
+        assert_eq!(tracker.tracked_slots(), vec![20]);
+
+        // skipped slots are not treated as empty slots for the account
+        let estimates = calculation_details_2(&[account], false, true, &None, &tracker);
+        assert_eq!(estimates[&account.to_string()].count, 1);
+        assert_eq!(estimates[&account.to_string()].estimates.medium, 100.0);
+    }
+
+    #[tokio::test]
+    async fn test_consume_block_spans() {
+        init_metrics();
+        let tracker = PriorityFeeTracker::new(10);
+        let block = SubscribeUpdate {
+            update_oneof: Some(UpdateOneof::Block(SubscribeUpdateBlock {
+                slot: 5,
+                parent_slot: 4,
+                ..Default::default()
+            })),
+            ..Default::default()
+        };
+        let (result, spans) = capture_spans(async { tracker.consume(&block) }).await;
+        result.unwrap();
+        assert_eq!(tracker.newest_slot(), Some(5));
+
+        let consume = spans
+            .iter()
+            .find(|span| span.name == "consume_block")
+            .expect("expected consume span");
+        assert_eq!(span_attribute(consume, "slot").as_deref(), Some("5"));
+        assert_eq!(
+            span_attribute(consume, "transaction_count").as_deref(),
+            Some("0")
+        );
+        for name in ["extract_transactions", "tracker_insert"] {
+            let child = spans
+                .iter()
+                .find(|span| span.name == name)
+                .unwrap_or_else(|| panic!("expected {name} span"));
+            assert_eq!(child.parent_span_id, consume.span_context.span_id());
+        }
+        let insert = spans
+            .iter()
+            .find(|span| span.name == "tracker_insert")
+            .expect("expected insert span");
+        assert_eq!(
+            span_attribute(insert, "transactions_processed").as_deref(),
+            Some("0")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_processed_slots_reconciliation() {
+        init_metrics();
+        let tracker = PriorityFeeTracker::new_with_options(
+            10,
+            TrackerOptions {
+                ingest_commitment: SlotCommitment::Processed,
+                ..Default::default()
+            },
+        );
+        let account = Pubkey::new_unique();
+
+        // slots 31 and 32 are competing forks built on slot 30
+        for (slot, fee) in [(30, 100), (31, 1_000_000), (32, 200), (33, 300)] {
+            tracker.push_block_metadata_with_commitment(
+                slot,
+                BlockMetadata {
+                    parent_slot: if slot == 33 { 32 } else { 30.min(slot - 1) },
+                    ..Default::default()
+                },
+                SlotCommitment::Processed,
+            );
+            tracker.push_priority_fee_for_txn(slot, vec![account], fee, false);
+        }
+        let slot_status = |slot: Slot, parent: Slot, status: SlotStatus| SubscribeUpdate {
+            update_oneof: Some(UpdateOneof::Slot(SubscribeUpdateSlot {
+                slot,
+                parent: Some(parent),
+                status: status as i32,
+                ..Default::default()
+            })),
+            ..Default::default()
+        };
+        tracker
+            .consume(&slot_status(30, 29, SlotStatus::SlotConfirmed))
+            .unwrap();
+        tracker
+            .consume(&slot_status(32, 30, SlotStatus::SlotConfirmed))
+            .unwrap();
+
+        let accounts = vec![account];
+        let confirmed = Calculations::new_calculation2(&accounts, false, false, &None);
+        let estimates = tracker.calculate_priority_fee(&confirmed).unwrap();
+        assert_eq!(estimates.unsafe_max, 200.0);
+
+        let processed = confirmed.clone().with_options(CalculationOptions {
+            commitment: SlotCommitment::Processed,
+            ..Default::default()
+        });
+        let estimates = tracker.calculate_priority_fee(&processed).unwrap();
+        assert_eq!(estimates.unsafe_max, 300.0);
+
+        // slot 33 dies and its provisional fees are dropped
+        tracker
+            .consume(&slot_status(33, 32, SlotStatus::SlotDead))
+            .unwrap();
+        let estimates = tracker.calculate_priority_fee(&processed).unwrap();
+        assert_eq!(estimates.unsafe_max, 200.0);
+        assert!(tracker.slot_summary(Some(31)).unwrap().skipped);
+        assert!(tracker.slot_summary(Some(33)).unwrap().skipped);
+    }
+
+    #[tokio::test]
+    async fn test_tip_to_lookup_table_account() {
+        init_metrics();
+        let payer = Pubkey::new_unique();
+        let tip_account = Pubkey::new_unique();
+        let readonly = Pubkey::new_unique();
+        let tracker = PriorityFeeTracker::new_with_options(
+            10,
+            TrackerOptions {
+                tip_accounts: HashSet::from([tip_account]),
+                ..Default::default()
+            },
+        );
+        // system transfer of 10_000 lamports
+        let mut data = 2u32.to_le_bytes().to_vec();
+        data.extend(10_000u64.to_le_bytes());
+        let transaction = |destination: u8| SubscribeUpdateTransactionInfo {
+            transaction: Some(Transaction {
+                message: Some(Message {
+                    header: Some(MessageHeader {
+                        num_required_signatures: 1,
+                        num_readonly_signed_accounts: 0,
+                        num_readonly_unsigned_accounts: 1,
+                    }),
+                    account_keys: vec![
+                        payer.to_bytes().to_vec(),
+                        tips::SYSTEM_PROGRAM_ID.to_bytes().to_vec(),
+                    ],
+                    instructions: vec![solana::storage::confirmed_block::CompiledInstruction {
+                        program_id_index: 1,
+                        accounts: vec![0, destination],
+                        data: data.clone(),
+                    }],
+                    versioned: true,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+            meta: Some(TransactionStatusMeta {
+                loaded_writable_addresses: vec![tip_account.to_bytes().to_vec()],
+                loaded_readonly_addresses: vec![readonly.to_bytes().to_vec()],
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        // the tip account is loaded from a lookup table, after the static account keys
+        let extracted = tracker.extract_transaction(&transaction(2)).unwrap();
+        assert_eq!(extracted.tip, 10_000);
+        assert!(extracted.writable_accounts.contains(&tip_account));
+        let extracted = tracker.extract_transaction(&transaction(3)).unwrap();
+        assert_eq!(extracted.tip, 0);
+    }
+
     #[test]
     fn test_constructing_accounts() {
         // same test as above but with an extra slot to throw off the value
@@ -1,276 +1,64 @@
-use crate::{DataType, PriorityFeesBySlot};
+use crate::PriorityFeesBySlot;
 use cadence_macros::{statsd_count, statsd_gauge};
-use solana_sdk::pubkey::Pubkey;
-use statrs::statistics::Data;
-use std::collections::HashMap;
+use priority_fee_core::calculation::StatusStats;
 use std::time::Instant;
-use Calculations::{Calculation1, Calculation2};
-
-///
-/// The result with type of
-///
-pub type DataStats<'a> = HashMap<DataType<'a>, Data<Vec<f64>>>;
-
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Ord, PartialOrd)]
-/// Enum representing different priority fee calculation algorithms.
-pub enum Calculations<'a> {
-    /// Algorithm 1: Collects all transaction fees and fees for specified accounts over n slots.
-    Calculation1 {
-        /// Accounts to filter fees for.
-        accounts: &'a [Pubkey],
-        /// Whether to include vote transactions.
-        include_vote: bool,
-        /// Whether to include empty slots in the calculation.
-        include_empty_slots: bool,
-        /// Lookback period in slots.
-        lookback_period: &'a Option<u32>,
-    },
-    /// Algorithm 2: Collects all transaction fees and fees for each specified account separately.
-    Calculation2 {
-        /// Accounts to filter fees for.
-        accounts: &'a [Pubkey],
-        /// Whether to include vote transactions.
-        include_vote: bool,
-        /// Whether to include empty slots in the calculation.
-        include_empty_slots: bool,
-        /// Lookback period in slots.
-        lookback_period: &'a Option<u32>,
-    },
-}
-
-impl<'a> Calculations<'a> {
-    /// Creates a new Calculation1 instance.
-    pub const fn new_calculation1(
-        accounts: &'a [Pubkey],
-        include_vote: bool,
-        include_empty_slots: bool,
-        lookback_period: &'a Option<u32>,
-    ) -> Calculations<'a> {
-        Calculation1 {
-            accounts,
-            include_vote,
-            include_empty_slots,
-            lookback_period,
-        }
-    }
-
-    /// Creates a new Calculation2 instance.
-    pub const fn new_calculation2(
-        accounts: &'a [Pubkey],
-        include_vote: bool,
-        include_empty_slots: bool,
-        lookback_period: &'a Option<u32>,
-    ) -> Calculations<'a> {
-        Calculation2 {
-            accounts,
-            include_vote,
-            include_empty_slots,
-            lookback_period,
-        }
-    }
-
-    /// Calculates priority fee estimates based on the selected algorithm.
-    pub fn get_priority_fee_estimates(
-        &self,
-        priority_fees: &PriorityFeesBySlot,
-    ) -> anyhow::Result<DataStats<'a>> {
-        let start = Instant::now();
-
-        let result = match self {
-            Calculation1 {
-                accounts,
-                include_vote,
-                include_empty_slots,
-                lookback_period,
-            } => v1::get_priority_fee_estimates(
-                accounts,
-                include_vote,
-                include_empty_slots,
-                lookback_period,
-                priority_fees,
-            ),
-            Calculation2 {
-                accounts,
-                include_vote,
-                include_empty_slots,
-                lookback_period,
-            } => v2::get_priority_fee_estimates(
-                accounts,
-                include_vote,
-                include_empty_slots,
-                lookback_period,
-                priority_fees,
-            ),
-        };
-        let version = match self {
-            Calculation1 { .. } => "v1",
-            Calculation2 { .. } => "v2",
-        };
-        statsd_gauge!(
-            "get_priority_fee_estimates_time",
-            start.elapsed().as_nanos() as u64,
-            "version" => &version
-        );
-        statsd_count!(
-            "get_priority_fee_calculation_version",
-            1,
-            "version" => &version
-        );
-
-        result
-    }
-}
-
-mod v1 {
-    use crate::priority_fee_calculation::{calculate_lookback_size, DataStats, DataType};
-    use crate::PriorityFeesBySlot;
-    use solana_sdk::clock::Slot;
-    use solana_sdk::pubkey::Pubkey;
-    use statrs::statistics::Data;
-
-    ///
-    /// Algo1: given the list of accounts the algorithm will:
-    /// 1. collect all the transactions fees over n slots
-    /// 2. collect all the transaction fees for all the accounts specified over n slots
-    /// 3. will calculate the percentile distributions for each of two groups
-    /// 4. will choose the highest value from each percentile between two groups
-    ///
-    pub(super) fn get_priority_fee_estimates<'a>(
-        accounts: &'a [Pubkey],
-        include_vote: &bool,
-        include_empty_slots: &bool,
-        lookback_period: &Option<u32>,
-        priority_fees: &PriorityFeesBySlot,
-    ) -> anyhow::Result<DataStats<'a>> {
-        let mut slots_vec: Vec<Slot> = priority_fees.iter().map(|entry| entry.slot).collect();
-        slots_vec.sort();
-        slots_vec.reverse();
-
-        let slots_vec = slots_vec;
 
-        let lookback = calculate_lookback_size(lookback_period, slots_vec.len());
-
-        let mut global_fees: Vec<f64> = Vec::new();
-        let mut account_fees: Vec<f64> = Vec::new();
-        for slot in &slots_vec[..lookback] {
-            if let Some(slot_priority_fees) = priority_fees.get(slot) {
-                if *include_vote {
-                    global_fees.extend_from_slice(&slot_priority_fees.fees.vote_fees);
-                }
-                global_fees.extend_from_slice(&slot_priority_fees.fees.non_vote_fees);
-
-                if !accounts.is_empty() {
-                    let mut has_data = false;
-                    accounts.iter().for_each(|account| {
-                        if let Some(account_priority_fees) =
-                            slot_priority_fees.account_fees.get(account)
-                        {
-                            if *include_vote {
-                                account_fees.extend_from_slice(&account_priority_fees.vote_fees);
-                            }
-                            account_fees.extend_from_slice(&account_priority_fees.non_vote_fees);
-                            has_data = true;
-                        }
-                    });
-                    if !has_data && *include_empty_slots {
-                        account_fees.push(0f64);
-                    }
-                }
-            }
-        }
-
-        let mut data = DataStats::new();
-        data.insert(DataType::Global, Data::new(global_fees));
-        data.insert(DataType::AllAccounts, Data::new(account_fees));
-        Ok(data)
-    }
+pub use priority_fee_core::calculation::DataStats;
+pub use priority_fee_core::Calculations;
+
+/// Calculates priority fee estimates with the algorithm of `calculation`, reporting the time it
+/// took and the algorithm version.
+pub fn get_priority_fee_estimates<'a>(
+    calculation: &Calculations<'a>,
+    priority_fees: &PriorityFeesBySlot,
+) -> anyhow::Result<DataStats<'a>> {
+    timed(calculation, || {
+        calculation.get_priority_fee_estimates(priority_fees)
+    })
 }
 
-mod v2 {
-    use crate::priority_fee_calculation::{calculate_lookback_size, DataStats, DataType};
-    use crate::PriorityFeesBySlot;
-    use solana_sdk::clock::Slot;
-    use solana_sdk::pubkey::Pubkey;
-    use statrs::statistics::Data;
-    use std::collections::HashMap;
-
-    ///
-    /// Algo2: given the list of accounts the algorithm will:
-    /// 1. collect all the transactions fees over n slots
-    /// 2. for each specified account collect the fees and calculate the percentiles
-    /// 4. choose maximum values for each percentile between all transactions and each account
-    ///
-    pub(super) fn get_priority_fee_estimates<'a>(
-        accounts: &'a [Pubkey],
-        include_vote: &bool,
-        include_empty_slots: &bool,
-        lookback_period: &Option<u32>,
-        priority_fees: &PriorityFeesBySlot,
-    ) -> anyhow::Result<DataStats<'a>> {
-        let mut slots_vec: Vec<Slot> = priority_fees.iter().map(|entry| entry.slot).collect();
-        slots_vec.sort();
-        slots_vec.reverse();
-
-        let slots_vec = slots_vec;
-
-        let lookback = calculate_lookback_size(lookback_period, slots_vec.len());
-
-        let mut data: HashMap<DataType<'a>, Vec<f64>> = HashMap::new();
-        for slot in &slots_vec[..lookback] {
-            if let Some(slot_priority_fees) = priority_fees.get(slot) {
-                let fees: &mut Vec<f64> = data.entry(DataType::Global).or_default();
-
-                if *include_vote {
-                    fees.extend_from_slice(&slot_priority_fees.fees.vote_fees);
-                }
-                fees.extend_from_slice(&slot_priority_fees.fees.non_vote_fees);
-
-                accounts.iter().for_each(|account| {
-                    /*
-                    Always insert the fees list for every account
-                    If account has no fees than we can fill the buckets with 0 for every slot missed
-                     */
-                    let fees: &mut Vec<f64> = data.entry(DataType::Account(account)).or_default();
-                    if let Some(account_priority_fees) =
-                        slot_priority_fees.account_fees.get(account)
-                    {
-                        if *include_vote {
-                            fees.extend_from_slice(&account_priority_fees.vote_fees);
-                        }
-                        fees.extend_from_slice(&account_priority_fees.non_vote_fees);
-                    } else if *include_empty_slots {
-                        // for all empty slot we need to add a 0
-                        fees.push(0f64);
-                    }
-                });
-            }
-        }
-
-        let data = data
-            .into_iter()
-            .map(|(data_type, fees)| (data_type, Data::new(fees)))
-            .collect::<DataStats>();
-        Ok(data)
-    }
+/// Calculates priority fee estimates together with the fees of succeeded and failed
+/// transactions, reporting the time it took and the algorithm version.
+pub fn get_priority_fee_details<'a>(
+    calculation: &Calculations<'a>,
+    priority_fees: &PriorityFeesBySlot,
+) -> anyhow::Result<StatusStats<'a>> {
+    timed(calculation, || {
+        calculation.get_priority_fee_details(priority_fees)
+    })
 }
 
-fn calculate_lookback_size(pref_num_slots: &Option<u32>, max_available_slots: usize) -> usize {
-    max_available_slots.min(
-        pref_num_slots
-            .map(|v| v as usize)
-            .unwrap_or(max_available_slots),
-    )
+fn timed<T>(calculation: &Calculations<'_>, calculate: impl FnOnce() -> T) -> T {
+    let start = Instant::now();
+    let result = calculate();
+    let version = match calculation {
+        Calculations::Calculation1 { .. } => "v1",
+        Calculations::Calculation2 { .. } => "v2",
+        Calculations::Calculation3 { .. } => "v3",
+    };
+    statsd_gauge!(
+        "get_priority_fee_estimates_time",
+        start.elapsed().as_nanos() as u64,
+        "version" => &version
+    );
+    statsd_count!(
+        "get_priority_fee_calculation_version",
+        1,
+        "version" => &version
+    );
+
+    result
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::priority_fee_calculation::DataType::{Account, AllAccounts, Global};
-    use crate::{Fees, SlotPriorityFees};
+    use crate::DataType::{Account, AllAccounts, Global};
+    use crate::{FeeSamples, Fees, SlotPriorityFees};
     use cadence::{NopMetricSink, StatsdClient};
     use cadence_macros::set_global_default;
     use solana_sdk::clock::Slot;
-    use statrs::statistics::OrderStatistics;
+    use solana_sdk::pubkey::Pubkey;
 
     fn init_metrics() {
         let noop = NopMetricSink {};
@@ -303,14 +91,13 @@ mod tests {
 
         // Scenario 1: no vote transactions and no empty slots and default lookback period
         let calc = Calculations::new_calculation1(&accounts, false, false, &None);
-        let mut estimates: DataStats = calc
-            .get_priority_fee_estimates(&tracker)
-            .expect("estimates to be valid");
+        let mut estimates: DataStats =
+            get_priority_fee_estimates(&calc, &tracker).expect("estimates to be valid");
 
         // Since the fixed fees are evenly distributed, the 50th percentile should be the middle value
         assert_eq!(estimates.len(), 2);
         {
-            let stats: &mut Data<Vec<f64>> = estimates.get_mut(&Global).unwrap();
+            let stats: &mut FeeSamples = estimates.get_mut(&Global).unwrap();
             assert_eq!(stats.percentile(0).round(), 0.0);
             assert_eq!(stats.percentile(25).round(), 25.0);
             assert_eq!(stats.percentile(50).round(), 50.0);
@@ -320,7 +107,7 @@ mod tests {
         }
 
         {
-            let stats: &mut Data<Vec<f64>> = estimates.get_mut(&AllAccounts).unwrap();
+            let stats: &mut FeeSamples = estimates.get_mut(&AllAccounts).unwrap();
             assert!(stats.percentile(0).is_nan());
             assert!(stats.percentile(25).is_nan());
             assert!(stats.percentile(50).is_nan());
@@ -331,14 +118,13 @@ mod tests {
 
         // Scenario 2: with vote transactions and no empty slots and default lookback period
         let calc = Calculations::new_calculation1(&accounts, true, false, &None);
-        let mut estimates: DataStats = calc
-            .get_priority_fee_estimates(&tracker)
-            .expect("estimates to be valid");
+        let mut estimates: DataStats =
+            get_priority_fee_estimates(&calc, &tracker).expect("estimates to be valid");
 
         // Since the fixed fees are evenly distributed, the 50th percentile should be the middle value
         assert_eq!(estimates.len(), 2);
         {
-            let stats: &mut Data<Vec<f64>> = estimates.get_mut(&Global).unwrap();
+            let stats: &mut FeeSamples = estimates.get_mut(&Global).unwrap();
             assert_eq!(stats.percentile(0).round(), 0.0);
             assert_eq!(stats.percentile(25).round(), 25.0);
             assert_eq!(stats.percentile(50).round(), 50.0);
@@ -348,7 +134,7 @@ mod tests {
         }
 
         {
-            let stats: &mut Data<Vec<f64>> = estimates.get_mut(&AllAccounts).unwrap();
+            let stats: &mut FeeSamples = estimates.get_mut(&AllAccounts).unwrap();
             assert!(stats.percentile(0).is_nan());
             assert!(stats.percentile(25).is_nan());
             assert!(stats.percentile(50).is_nan());
@@ -359,14 +145,13 @@ mod tests {
 
         // Scenario 3: with vote transactions and empty slots and default lookback period
         let calc = Calculations::new_calculation1(&accounts, true, true, &None);
-        let mut estimates: DataStats = calc
-            .get_priority_fee_estimates(&tracker)
-            .expect("estimates to be valid");
+        let mut estimates: DataStats =
+            get_priority_fee_estimates(&calc, &tracker).expect("estimates to be valid");
 
         // Since the fixed fees are evenly distributed, the 50th percentile should be the middle value
         assert_eq!(estimates.len(), 2);
         {
-            let stats: &mut Data<Vec<f64>> = estimates.get_mut(&Global).unwrap();
+            let stats: &mut FeeSamples = estimates.get_mut(&Global).unwrap();
             assert_eq!(stats.percentile(0).round(), 0.0);
             assert_eq!(stats.percentile(25).round(), 25.0);
             assert_eq!(stats.percentile(50).round(), 50.0);
@@ -376,7 +161,7 @@ mod tests {
         }
 
         {
-            let stats: &mut Data<Vec<f64>> = estimates.get_mut(&AllAccounts).unwrap();
+            let stats: &mut FeeSamples = estimates.get_mut(&AllAccounts).unwrap();
             assert!(stats.percentile(0).is_nan());
             assert!(stats.percentile(25).is_nan());
             assert!(stats.percentile(50).is_nan());
@@ -387,14 +172,13 @@ mod tests {
 
         // Scenario 4: with vote transactions and empty slots and different lookback period
         let calc = Calculations::new_calculation1(&accounts, true, true, &Some(1));
-        let mut estimates: DataStats = calc
-            .get_priority_fee_estimates(&tracker)
-            .expect("estimates to be valid");
+        let mut estimates: DataStats =
+            get_priority_fee_estimates(&calc, &tracker).expect("estimates to be valid");
 
         // Since the fixed fees are evenly distributed, the 50th percentile should be the middle value
         assert_eq!(estimates.len(), 2);
         {
-            let stats: &mut Data<Vec<f64>> = estimates.get_mut(&Global).unwrap();
+            let stats: &mut FeeSamples = estimates.get_mut(&Global).unwrap();
             assert_eq!(stats.percentile(0).round(), 0.0);
             assert_eq!(stats.percentile(25).round(), 25.0);
             assert_eq!(stats.percentile(50).round(), 50.0);
@@ -404,7 +188,7 @@ mod tests {
         }
 
         {
-            let stats: &mut Data<Vec<f64>> = estimates.get_mut(&AllAccounts).unwrap();
+            let stats: &mut FeeSamples = estimates.get_mut(&AllAccounts).unwrap();
             assert!(stats.percentile(0).is_nan());
             assert!(stats.percentile(25).is_nan());
             assert!(stats.percentile(50).is_nan());
@@ -439,14 +223,13 @@ mod tests {
 
         // Scenario 1: no vote transactions and no empty slots and default lookback period
         let calc = Calculations::new_calculation2(&accounts, false, false, &None);
-        let mut estimates: DataStats = calc
-            .get_priority_fee_estimates(&tracker)
-            .expect("estimates to be valid");
+        let mut estimates: DataStats =
+            get_priority_fee_estimates(&calc, &tracker).expect("estimates to be valid");
 
         // Since the fixed fees are evenly distributed, the 50th percentile should be the middle value
         assert_eq!(estimates.len(), 1);
         {
-            let stats: &mut Data<Vec<f64>> = estimates.get_mut(&Global).unwrap();
+            let stats: &mut FeeSamples = estimates.get_mut(&Global).unwrap();
             assert_eq!(stats.percentile(0).round(), 0.0);
             assert_eq!(stats.percentile(25).round(), 25.0);
             assert_eq!(stats.percentile(50).round(), 50.0);
@@ -457,14 +240,13 @@ mod tests {
 
         // Scenario 2: with vote transactions and no empty slots and default lookback period
         let calc = Calculations::new_calculation2(&accounts, true, false, &None);
-        let mut estimates: DataStats = calc
-            .get_priority_fee_estimates(&tracker)
-            .expect("estimates to be valid");
+        let mut estimates: DataStats =
+            get_priority_fee_estimates(&calc, &tracker).expect("estimates to be valid");
 
         // Since the fixed fees are evenly distributed, the 50th percentile should be the middle value
         assert_eq!(estimates.len(), 1);
         {
-            let stats: &mut Data<Vec<f64>> = estimates.get_mut(&Global).unwrap();
+            let stats: &mut FeeSamples = estimates.get_mut(&Global).unwrap();
             assert_eq!(stats.percentile(0).round(), 0.0);
             assert_eq!(stats.percentile(25).round(), 25.0);
             assert_eq!(stats.percentile(50).round(), 50.0);
@@ -475,14 +257,13 @@ mod tests {
 
         // Scenario 3: with vote transactions and empty slots and default lookback period
         let calc = Calculations::new_calculation2(&accounts, true, true, &None);
-        let mut estimates: DataStats = calc
-            .get_priority_fee_estimates(&tracker)
-            .expect("estimates to be valid");
+        let mut estimates: DataStats =
+            get_priority_fee_estimates(&calc, &tracker).expect("estimates to be valid");
 
         // Since the fixed fees are evenly distributed, the 50th percentile should be the middle value
         assert_eq!(estimates.len(), 1);
         {
-            let stats: &mut Data<Vec<f64>> = estimates.get_mut(&Global).unwrap();
+            let stats: &mut FeeSamples = estimates.get_mut(&Global).unwrap();
             assert_eq!(stats.percentile(0).round(), 0.0);
             assert_eq!(stats.percentile(25).round(), 25.0);
             assert_eq!(stats.percentile(50).round(), 50.0);
@@ -493,14 +274,13 @@ mod tests {
 
         // Scenario 4: with vote transactions and empty slots and different lookback period
         let calc = Calculations::new_calculation2(&accounts, true, true, &Some(1));
-        let mut estimates: DataStats = calc
-            .get_priority_fee_estimates(&tracker)
-            .expect("estimates to be valid");
+        let mut estimates: DataStats =
+            get_priority_fee_estimates(&calc, &tracker).expect("estimates to be valid");
 
         // Since the fixed fees are evenly distributed, the 50th percentile should be the middle value
         assert_eq!(estimates.len(), 1);
         {
-            let stats: &mut Data<Vec<f64>> = estimates.get_mut(&Global).unwrap();
+            let stats: &mut FeeSamples = estimates.get_mut(&Global).unwrap();
             assert_eq!(stats.percentile(0).round(), 0.0);
             assert_eq!(stats.percentile(25).round(), 25.0);
             assert_eq!(stats.percentile(50).round(), 50.0);
@@ -533,14 +313,13 @@ mod tests {
 
         // Scenario 1: no vote transactions and no empty slots and default lookback period
         let calc = Calculations::new_calculation1(&accounts[..=0], false, false, &None);
-        let mut estimates: DataStats = calc
-            .get_priority_fee_estimates(&tracker)
-            .expect("estimates to be valid");
+        let mut estimates: DataStats =
+            get_priority_fee_estimates(&calc, &tracker).expect("estimates to be valid");
 
         // Since the fixed fees are evenly distributed, the 50th percentile should be the middle value
         assert_eq!(estimates.len(), 2);
         {
-            let stats: &mut Data<Vec<f64>> = estimates.get_mut(&Global).unwrap();
+            let stats: &mut FeeSamples = estimates.get_mut(&Global).unwrap();
             assert_eq!(stats.percentile(0).round(), 0.0);
             assert_eq!(stats.percentile(25).round(), 25.0);
             assert_eq!(stats.percentile(50).round(), 50.0);
@@ -550,7 +329,7 @@ mod tests {
         }
 
         {
-            let stats: &mut Data<Vec<f64>> = estimates.get_mut(&AllAccounts).unwrap();
+            let stats: &mut FeeSamples = estimates.get_mut(&AllAccounts).unwrap();
             assert_eq!(stats.percentile(0).round(), 0.0);
             assert_eq!(stats.percentile(25).round(), 25.0);
             assert_eq!(stats.percentile(50).round(), 50.0);
@@ -561,14 +340,13 @@ mod tests {
 
         // Scenario 2: no vote transactions and no empty slots and default lookback period
         let calc = Calculations::new_calculation1(&accounts[..=0], true, false, &None);
-        let mut estimates: DataStats = calc
-            .get_priority_fee_estimates(&tracker)
-            .expect("estimates to be valid");
+        let mut estimates: DataStats =
+            get_priority_fee_estimates(&calc, &tracker).expect("estimates to be valid");
 
         // Since the fixed fees are evenly distributed, the 50th percentile should be the middle value
         assert_eq!(estimates.len(), 2);
         {
-            let stats: &mut Data<Vec<f64>> = estimates.get_mut(&Global).unwrap();
+            let stats: &mut FeeSamples = estimates.get_mut(&Global).unwrap();
             assert_eq!(stats.percentile(0).round(), 0.0);
             assert_eq!(stats.percentile(25).round(), 25.0);
             assert_eq!(stats.percentile(50).round(), 50.0);
@@ -578,7 +356,7 @@ mod tests {
         }
 
         {
-            let stats: &mut Data<Vec<f64>> = estimates.get_mut(&AllAccounts).unwrap();
+            let stats: &mut FeeSamples = estimates.get_mut(&AllAccounts).unwrap();
             assert_eq!(stats.percentile(0).round(), 0.0);
             assert_eq!(stats.percentile(25).round(), 25.0);
             assert_eq!(stats.percentile(50).round(), 50.0);
@@ -589,14 +367,13 @@ mod tests {
 
         // Scenario 1: no vote transactions and no empty slots and default lookback period
         let calc = Calculations::new_calculation1(&accounts[..=0], true, true, &None);
-        let mut estimates: DataStats = calc
-            .get_priority_fee_estimates(&tracker)
-            .expect("estimates to be valid");
+        let mut estimates: DataStats =
+            get_priority_fee_estimates(&calc, &tracker).expect("estimates to be valid");
 
         // Since the fixed fees are evenly distributed, the 50th percentile should be the middle value
         assert_eq!(estimates.len(), 2);
         {
-            let stats: &mut Data<Vec<f64>> = estimates.get_mut(&Global).unwrap();
+            let stats: &mut FeeSamples = estimates.get_mut(&Global).unwrap();
             assert_eq!(stats.percentile(0).round(), 0.0);
             assert_eq!(stats.percentile(25).round(), 25.0);
             assert_eq!(stats.percentile(50).round(), 50.0);
@@ -606,7 +383,7 @@ mod tests {
         }
 
         {
-            let stats: &mut Data<Vec<f64>> = estimates.get_mut(&AllAccounts).unwrap();
+            let stats: &mut FeeSamples = estimates.get_mut(&AllAccounts).unwrap();
             assert_eq!(stats.percentile(0).round(), 0.0);
             assert_eq!(stats.percentile(25).round(), 25.0);
             assert_eq!(stats.percentile(50).round(), 50.0);
@@ -617,14 +394,13 @@ mod tests {
 
         // Scenario 1: no vote transactions and no empty slots and default lookback period
         let calc = Calculations::new_calculation1(&accounts[..=0], true, true, &Some(1));
-        let mut estimates: DataStats = calc
-            .get_priority_fee_estimates(&tracker)
-            .expect("estimates to be valid");
+        let mut estimates: DataStats =
+            get_priority_fee_estimates(&calc, &tracker).expect("estimates to be valid");
 
         // Since the fixed fees are evenly distributed, the 50th percentile should be the middle value
         assert_eq!(estimates.len(), 2);
         {
-            let stats: &mut Data<Vec<f64>> = estimates.get_mut(&Global).unwrap();
+            let stats: &mut FeeSamples = estimates.get_mut(&Global).unwrap();
             assert_eq!(stats.percentile(0).round(), 0.0);
             assert_eq!(stats.percentile(25).round(), 25.0);
             assert_eq!(stats.percentile(50).round(), 50.0);
@@ -634,7 +410,7 @@ mod tests {
         }
 
         {
-            let stats: &mut Data<Vec<f64>> = estimates.get_mut(&AllAccounts).unwrap();
+            let stats: &mut FeeSamples = estimates.get_mut(&AllAccounts).unwrap();
             assert_eq!(stats.percentile(0).round(), 0.0);
             assert_eq!(stats.percentile(25).round(), 25.0);
             assert_eq!(stats.percentile(50).round(), 50.0);
@@ -667,14 +443,13 @@ mod tests {
 
         // Scenario 1: no vote transactions and no empty slots and default lookback period
         let calc = Calculations::new_calculation2(&accounts[..=0], false, false, &None);
-        let mut estimates: DataStats = calc
-            .get_priority_fee_estimates(&tracker)
-            .expect("estimates to be valid");
+        let mut estimates: DataStats =
+            get_priority_fee_estimates(&calc, &tracker).expect("estimates to be valid");
 
         // Since the fixed fees are evenly distributed, the 50th percentile should be the middle value
         assert_eq!(estimates.len(), 2);
         {
-            let stats: &mut Data<Vec<f64>> = estimates.get_mut(&Global).unwrap();
+            let stats: &mut FeeSamples = estimates.get_mut(&Global).unwrap();
             assert_eq!(stats.percentile(0).round(), 0.0);
             assert_eq!(stats.percentile(25).round(), 25.0);
             assert_eq!(stats.percentile(50).round(), 50.0);
@@ -684,7 +459,7 @@ mod tests {
         }
 
         {
-            let stats: &mut Data<Vec<f64>> = estimates.get_mut(&Account(&account_1)).unwrap();
+            let stats: &mut FeeSamples = estimates.get_mut(&Account(&account_1)).unwrap();
             assert_eq!(stats.percentile(0).round(), 0.0);
             assert_eq!(stats.percentile(25).round(), 25.0);
             assert_eq!(stats.percentile(50).round(), 50.0);
@@ -695,14 +470,13 @@ mod tests {
 
         // Scenario 2: no vote transactions and no empty slots and default lookback period
         let calc = Calculations::new_calculation2(&accounts[..=0], true, false, &None);
-        let mut estimates: DataStats = calc
-            .get_priority_fee_estimates(&tracker)
-            .expect("estimates to be valid");
+        let mut estimates: DataStats =
+            get_priority_fee_estimates(&calc, &tracker).expect("estimates to be valid");
 
         // Since the fixed fees are evenly distributed, the 50th percentile should be the middle value
         assert_eq!(estimates.len(), 2);
         {
-            let stats: &mut Data<Vec<f64>> = estimates.get_mut(&Global).unwrap();
+            let stats: &mut FeeSamples = estimates.get_mut(&Global).unwrap();
             assert_eq!(stats.percentile(0).round(), 0.0);
             assert_eq!(stats.percentile(25).round(), 25.0);
             assert_eq!(stats.percentile(50).round(), 50.0);
@@ -712,7 +486,7 @@ mod tests {
         }
 
         {
-            let stats: &mut Data<Vec<f64>> = estimates.get_mut(&Account(&account_1)).unwrap();
+            let stats: &mut FeeSamples = estimates.get_mut(&Account(&account_1)).unwrap();
             assert_eq!(stats.percentile(0).round(), 0.0);
             assert_eq!(stats.percentile(25).round(), 25.0);
             assert_eq!(stats.percentile(50).round(), 50.0);
@@ -723,14 +497,13 @@ mod tests {
 
         // Scenario 1: no vote transactions and no empty slots and default lookback period
         let calc = Calculations::new_calculation2(&accounts[..=0], true, true, &None);
-        let mut estimates: DataStats = calc
-            .get_priority_fee_estimates(&tracker)
-            .expect("estimates to be valid");
+        let mut estimates: DataStats =
+            get_priority_fee_estimates(&calc, &tracker).expect("estimates to be valid");
 
         // Since the fixed fees are evenly distributed, the 50th percentile should be the middle value
         assert_eq!(estimates.len(), 2);
         {
-            let stats: &mut Data<Vec<f64>> = estimates.get_mut(&Global).unwrap();
+            let stats: &mut FeeSamples = estimates.get_mut(&Global).unwrap();
             assert_eq!(stats.percentile(0).round(), 0.0);
             assert_eq!(stats.percentile(25).round(), 25.0);
             assert_eq!(stats.percentile(50).round(), 50.0);
@@ -740,7 +513,7 @@ mod tests {
         }
 
         {
-            let stats: &mut Data<Vec<f64>> = estimates.get_mut(&Account(&account_1)).unwrap();
+            let stats: &mut FeeSamples = estimates.get_mut(&Account(&account_1)).unwrap();
             assert_eq!(stats.percentile(0).round(), 0.0);
             assert_eq!(stats.percentile(25).round(), 25.0);
             assert_eq!(stats.percentile(50).round(), 50.0);
@@ -751,14 +524,13 @@ mod tests {
 
         // Scenario 1: no vote transactions and no empty slots and default lookback period
         let calc = Calculations::new_calculation2(&accounts[..=0], true, true, &Some(1));
-        let mut estimates: DataStats = calc
-            .get_priority_fee_estimates(&tracker)
-            .expect("estimates to be valid");
+        let mut estimates: DataStats =
+            get_priority_fee_estimates(&calc, &tracker).expect("estimates to be valid");
 
         // Since the fixed fees are evenly distributed, the 50th percentile should be the middle value
         assert_eq!(estimates.len(), 2);
         {
-            let stats: &mut Data<Vec<f64>> = estimates.get_mut(&Global).unwrap();
+            let stats: &mut FeeSamples = estimates.get_mut(&Global).unwrap();
             assert_eq!(stats.percentile(0).round(), 0.0);
             assert_eq!(stats.percentile(25).round(), 25.0);
             assert_eq!(stats.percentile(50).round(), 50.0);
@@ -768,7 +540,7 @@ mod tests {
         }
 
         {
-            let stats: &mut Data<Vec<f64>> = estimates.get_mut(&Account(&account_1)).unwrap();
+            let stats: &mut FeeSamples = estimates.get_mut(&Account(&account_1)).unwrap();
             assert_eq!(stats.percentile(0).round(), 0.0);
             assert_eq!(stats.percentile(25).round(), 25.0);
             assert_eq!(stats.percentile(50).round(), 50.0);
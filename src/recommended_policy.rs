@@ -0,0 +1,244 @@
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+
+use cadence_macros::statsd_count;
+use figment::providers::{Format, Toml};
+use figment::Figment;
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+use tracing::{error, info};
+
+use crate::config::watch_file;
+use crate::{MicroLamportPriorityFeeEstimates, PriorityLevel};
+
+/// Definition of how the recommended fee is derived from the estimates.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct RecommendedFeePolicy {
+    /// Name reported in responses using this policy.
+    pub name: String,
+    /// Estimate level the recommended fee is based on.
+    pub level: PriorityLevel,
+    /// Factor applied to the level before the floor and ceiling.
+    pub multiplier: f64,
+    /// Lowest recommended fee.
+    pub floor: f64,
+    /// Highest recommended fee, unbounded by default.
+    pub ceiling: Option<f64>,
+}
+
+impl Default for RecommendedFeePolicy {
+    fn default() -> Self {
+        Self {
+            name: "default".to_string(),
+            level: PriorityLevel::Medium,
+            multiplier: 1.0,
+            floor: 10_000.0,
+            ceiling: None,
+        }
+    }
+}
+
+impl RecommendedFeePolicy {
+    /// Returns the recommended fee for the given estimates, rounded up.
+    pub fn apply(&self, priority_fee_levels: &MicroLamportPriorityFeeEstimates) -> f64 {
+        let base = match self.level {
+            PriorityLevel::Min => priority_fee_levels.min,
+            PriorityLevel::Low => priority_fee_levels.low,
+            PriorityLevel::Medium | PriorityLevel::Default => priority_fee_levels.medium,
+            PriorityLevel::High => priority_fee_levels.high,
+            PriorityLevel::VeryHigh => priority_fee_levels.very_high,
+            PriorityLevel::UnsafeMax => priority_fee_levels.unsafe_max,
+        };
+        let recommended = (base * self.multiplier).max(self.floor);
+        let recommended = match self.ceiling {
+            Some(ceiling) => recommended.min(ceiling),
+            None => recommended,
+        };
+        recommended.ceil()
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        if !(self.multiplier.is_finite() && self.multiplier > 0.0) {
+            anyhow::bail!("policy {}: multiplier must be positive", self.name);
+        }
+        if self.ceiling.is_some_and(|ceiling| ceiling < self.floor) {
+            anyhow::bail!("policy {}: ceiling must not be below the floor", self.name);
+        }
+        Ok(())
+    }
+}
+
+/// Policy used instead of the default one for requests touching any of its accounts.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PolicyOverride {
+    /// Base58-encoded account or program addresses the override applies to.
+    pub accounts: Vec<String>,
+    /// Policy applied to matching requests.
+    #[serde(flatten)]
+    pub policy: RecommendedFeePolicy,
+}
+
+/// Default policy and per-account overrides, as loaded from the policy file.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct RecommendedFeePolicies {
+    /// Policy applied when no override matches.
+    pub default: RecommendedFeePolicy,
+    /// Overrides, the first matching one is used.
+    pub overrides: Vec<PolicyOverride>,
+}
+
+impl RecommendedFeePolicies {
+    /// Loads and validates the policies from a TOML file.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let policies: Self = Figment::from(Toml::file_exact(path)).extract()?;
+        policies.default.validate()?;
+        for policy_override in &policies.overrides {
+            policy_override.policy.validate()?;
+            for account in &policy_override.accounts {
+                Pubkey::from_str(account).map_err(|_| {
+                    anyhow::anyhow!(
+                        "policy {}: invalid account {}",
+                        policy_override.policy.name,
+                        account
+                    )
+                })?;
+            }
+        }
+        Ok(policies)
+    }
+
+    /// Returns the policy applying to a request for the given accounts.
+    pub fn select(&self, accounts: &[Pubkey]) -> &RecommendedFeePolicy {
+        self.overrides
+            .iter()
+            .find(|policy_override| {
+                policy_override.accounts.iter().any(|account| {
+                    Pubkey::from_str(account).is_ok_and(|account| accounts.contains(&account))
+                })
+            })
+            .map_or(&self.default, |policy_override| &policy_override.policy)
+    }
+}
+
+/// Recommended fee policies that can be swapped while serving requests.
+#[derive(Debug, Default)]
+pub struct RecommendedPolicyStore {
+    policies: RwLock<Arc<RecommendedFeePolicies>>,
+}
+
+impl RecommendedPolicyStore {
+    /// Creates a store holding the given policies.
+    pub fn new(policies: RecommendedFeePolicies) -> Self {
+        Self {
+            policies: RwLock::new(Arc::new(policies)),
+        }
+    }
+
+    /// Returns the current policies.
+    pub fn current(&self) -> Arc<RecommendedFeePolicies> {
+        self.policies.read().expect("policy lock poisoned").clone()
+    }
+
+    /// Replaces the current policies.
+    pub fn replace(&self, policies: RecommendedFeePolicies) {
+        *self.policies.write().expect("policy lock poisoned") = Arc::new(policies);
+    }
+
+    /// Loads the policies from `path` and starts reloading them whenever the file changes.
+    /// Invalid files are logged and leave the current policies in place.
+    pub fn spawn(path: PathBuf) -> anyhow::Result<Arc<Self>> {
+        let store = Arc::new(Self::new(RecommendedFeePolicies::load(&path)?));
+        let reloaded = store.clone();
+        let watched = path.clone();
+        watch_file(watched, move || match RecommendedFeePolicies::load(&path) {
+            Ok(policies) => {
+                info!("reloaded recommended fee policies from {:?}", path);
+                statsd_count!("recommended_policy_reloaded", 1);
+                reloaded.replace(policies);
+            }
+            Err(e) => {
+                error!("failed to reload recommended fee policies: {:?}", e);
+                statsd_count!("recommended_policy_reload_failed", 1);
+            }
+        });
+        Ok(store)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn levels(medium: f64, high: f64) -> MicroLamportPriorityFeeEstimates {
+        MicroLamportPriorityFeeEstimates {
+            medium,
+            high,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_default_policy() {
+        let policy = RecommendedFeePolicy::default();
+        assert_eq!(policy.apply(&levels(100.0, 200.0)), 10_000.0);
+        assert_eq!(policy.apply(&levels(20_000.4, 200.0)), 20_001.0);
+    }
+
+    #[test]
+    fn test_policy_overrides() {
+        figment::Jail::expect_with(|jail| {
+            let account = Pubkey::new_unique();
+            jail.create_file(
+                "policies.toml",
+                &format!(
+                    r#"
+                    [default]
+                    name = "standard"
+                    floor = 5000
+
+                    [[overrides]]
+                    name = "hot"
+                    accounts = ["{account}"]
+                    level = "High"
+                    multiplier = 1.5
+                    floor = 20000
+                    ceiling = 1000000
+                    "#
+                ),
+            )?;
+            let policies = RecommendedFeePolicies::load(Path::new("policies.toml"))
+                .map_err(|e| e.to_string())?;
+
+            let standard = policies.select(&[Pubkey::new_unique()]);
+            assert_eq!(standard.name, "standard");
+            assert_eq!(standard.apply(&levels(100.0, 200.0)), 5_000.0);
+
+            let hot = policies.select(&[Pubkey::new_unique(), account]);
+            assert_eq!(hot.name, "hot");
+            assert_eq!(hot.apply(&levels(100.0, 200_000.0)), 300_000.0);
+            assert_eq!(hot.apply(&levels(100.0, 2_000_000.0)), 1_000_000.0);
+            assert_eq!(hot.apply(&levels(100.0, 200.0)), 20_000.0);
+
+            jail.create_file("invalid.toml", "[default]\nfloor = 10\nceiling = 5\n")?;
+            assert!(RecommendedFeePolicies::load(Path::new("invalid.toml")).is_err());
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_replace_policies() {
+        let store = RecommendedPolicyStore::default();
+        assert_eq!(store.current().default.name, "default");
+        store.replace(RecommendedFeePolicies {
+            default: RecommendedFeePolicy {
+                name: "swapped".to_string(),
+                ..Default::default()
+            },
+            overrides: vec![],
+        });
+        assert_eq!(store.current().default.name, "swapped");
+    }
+}
@@ -3,39 +3,55 @@ use std::{
     fmt,
     str::FromStr,
     sync::Arc,
-    time::Instant,
+    time::{Instant, SystemTime, UNIX_EPOCH},
 };
 
-use crate::errors::invalid_request;
+use crate::config::RuntimeConfigStore;
+use crate::errors::{invalid_request, not_ready};
+use crate::estimate_cache::EstimateCache;
+use crate::health::{HealthReport, IngestStatus};
 use crate::priority_fee::{construct_writable_accounts, PriorityFeeTracker};
 use crate::priority_fee_calculation::Calculations;
+use crate::recommended_policy::{RecommendedFeePolicy, RecommendedPolicyStore};
 use crate::solana::solana_rpc::decode_and_deserialize;
-use crate::{MicroLamportPriorityFeeDetails, MicroLamportPriorityFeeEstimates, PriorityLevel};
+use crate::{
+    Aggregation, CalculationOptions, LamportTipEstimates, LeaderBoost, LeaderSlotFees,
+    MicroLamportPriorityFeeDetails, MicroLamportPriorityFeeEstimates, OutlierFilter, PayerDedup,
+    PriorityLevel, SlotCommitment, SlotFeeBreakdown, SlotFeeForecast, SlotSummary,
+    TransactionStatusFilter,
+};
 use cadence_macros::{statsd_count, statsd_time};
 use jsonrpsee::types::error::{INTERNAL_ERROR_CODE, INTERNAL_ERROR_MSG};
-use jsonrpsee::{
-    core::{async_trait, RpcResult},
-    proc_macros::rpc,
-    types::ErrorObjectOwned,
-};
+use jsonrpsee::{core::RpcResult, proc_macros::rpc, types::ErrorObjectOwned};
+use priority_fee_core::forecast::MAX_FORECAST_SLOTS;
 use serde::{Deserialize, Serialize};
 use solana_account_decoder::parse_address_lookup_table::{
     parse_address_lookup_table, LookupTableAccountType,
 };
 use solana_client::rpc_client::RpcClient;
+use solana_sdk::clock::Slot;
 use solana_sdk::message::MessageHeader;
 use solana_sdk::{pubkey::Pubkey, transaction::VersionedTransaction};
 use solana_transaction_status::UiTransactionEncoding;
-use tracing::{info, warn};
+use tracing::field::Empty;
+use tracing::{info, info_span, warn};
 
 /// Main estimator struct that handles RPC requests.
 pub struct AtlasPriorityFeeEstimator {
     /// Tracker for priority fees.
     pub priority_fee_tracker: Arc<PriorityFeeTracker>,
-    /// Optional Solana RPC client for fetching transaction details.
-    pub rpc_client: Option<RpcClient>,
+    /// Optional Solana RPC client for fetching transaction details, shared with the leader
+    /// schedule.
+    pub rpc_client: Option<Arc<RpcClient>>,
     /// Maximum number of slots to look back for estimation.
     pub max_lookback_slots: usize,
+    /// Policies deriving the recommended fee from the estimates.
+    pub recommended_policies: Arc<RecommendedPolicyStore>,
+    /// Settings that can change while serving requests.
+    pub runtime_config: Arc<RuntimeConfigStore>,
+    /// Connection state and last error of the gRPC ingestion.
+    pub ingest_status: Arc<IngestStatus>,
+    estimate_cache: Arc<EstimateCache<Arc<CachedEstimate>>>,
 }
 
 impl fmt::Debug for AtlasPriorityFeeEstimator {
@@ -43,6 +59,10 @@ impl fmt::Debug for AtlasPriorityFeeEstimator {
         f.debug_struct("AtlasPriorityFeeEstimator")
             .field("priority_fee_tracker", &self.priority_fee_tracker)
             .field("rpc_client", &"RpcClient { ... }") // RpcClient does not implement Debug
+            .field("recommended_policies", &self.recommended_policies)
+            .field("runtime_config", &self.runtime_config)
+            .field("ingest_status", &self.ingest_status)
+            .field("estimate_cache", &self.estimate_cache.len())
             .finish()
     }
 }
@@ -78,6 +98,8 @@ pub struct GetPriorityFeeEstimateOptions {
     #[serde()]
     /// Number of slots to look back for estimation.
     pub lookback_slots: Option<u32>, // how many slots to look back on, default 50, min 1, max 300
+    /// Only use slots produced within this many seconds.
+    pub lookback_seconds: Option<u32>, // wall-clock window based on block time, min 1
     /// Whether to include vote transactions in the estimate.
     pub include_vote: Option<bool>, // include vote txns in the estimate
     /// Whether to return the recommended fee (median fee excluding vote txns).
@@ -86,6 +108,26 @@ pub struct GetPriorityFeeEstimateOptions {
     pub evaluate_empty_slot_as_zero: Option<bool>, // if true than slots with no transactions will be treated as 0
     /// Whether to include detailed breakdown of data used for calculation.
     pub include_details: Option<bool>, // default to false, if provided will include detailed breakdown of data used for calculation
+    /// Minimum commitment of the slots used for the estimate.
+    pub commitment: Option<SlotCommitment>, // default to confirmed, processed also uses provisional slots
+    /// Whether to include fees of failed transactions in the estimate.
+    pub include_failed: Option<bool>, // default to false, failed txns are tracked separately and reported in details
+    /// Weight slots by age, halving a slot's weight every this many slots.
+    pub half_life_slots: Option<u32>, // if provided the time-decayed (v3) algorithm is used, min 1
+    /// Number of upcoming slots to forecast fees for.
+    pub forecast: Option<u32>, // between 1 and 10, forecasts per-slot median and upper percentiles
+    /// Outlier handling applied to the fees of each account before percentiles are taken.
+    pub outlier_filter: Option<OutlierFilter>, // {"trim": 5}, {"winsorize": 5} or {"mad": 3}, default none
+    /// Weight multiplier of slots produced by the upcoming leaders.
+    pub upcoming_leader_weight: Option<u32>, // min 1, blends the fee history of the upcoming leaders into the estimate, requires a leader schedule
+    /// Limits the fees each fee payer contributes to every slot.
+    pub payer_dedup: Option<PayerDedup>, // {"cap": 5} or "collapse", default none
+    /// How the estimates of the global fees and of each account are combined.
+    pub aggregation: Option<Aggregation>, // "max" (default), "maxExcludingGlobal", "weightedMean" or "mostContended"
+    /// Accounts with fewer fees than this fall back to the global fees.
+    pub min_samples: Option<u32>, // min 1, fallbacks are reported in details
+    /// Name of the cluster serving the request.
+    pub cluster: Option<String>, // default to the cluster of the path prefix, or the default cluster
 }
 
 /// Response object for the `getPriorityFeeEstimate` method.
@@ -101,41 +143,156 @@ pub struct GetPriorityFeeEstimateResponse {
     /// Detailed breakdown of estimates per account, if requested.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub priority_fee_estimate_details: Option<Vec<(String, MicroLamportPriorityFeeDetails)>>,
+    /// Summaries of the slots considered for the estimate, newest first, if details were requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub slot_summaries: Option<Vec<SlotSummary>>,
+    /// Forecast fee percentiles for the upcoming slots, if requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub forecast: Option<Vec<SlotFeeForecast>>,
+    /// Name of the policy that produced the recommended fee, if requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recommended_policy: Option<String>,
+}
+
+/// Request object for the `getSlotSummary` method.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(
+    rename_all(serialize = "camelCase", deserialize = "camelCase"),
+    deny_unknown_fields
+)]
+pub struct GetSlotSummaryRequest {
+    /// Slot to summarize, defaults to the newest tracked slot.
+    pub slot: Option<Slot>,
+    /// Name of the cluster serving the request.
+    pub cluster: Option<String>,
+}
+
+/// Request object for the `getLeaderFeeHistory` method.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(
+    rename_all(serialize = "camelCase", deserialize = "camelCase"),
+    deny_unknown_fields
+)]
+pub struct GetLeaderFeeHistoryRequest {
+    /// Base58-encoded identity of the leader.
+    pub leader: String,
+    /// Name of the cluster serving the request.
+    pub cluster: Option<String>,
+}
+
+/// Number of raw fees returned per slot and kind of transaction by `getSlotFees` by default.
+pub const DEFAULT_MAX_RAW_FEES: usize = 100;
+
+/// Largest number of raw fees per slot and kind of transaction `getSlotFees` may return.
+pub const MAX_RAW_FEES: usize = 1_000;
+
+/// Request object for the `getSlotFees` method.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(
+    rename_all(serialize = "camelCase", deserialize = "camelCase"),
+    deny_unknown_fields
+)]
+pub struct GetSlotFeesRequest {
+    /// Base58-encoded account whose fees are returned, the global fees of each slot when unset.
+    pub account_key: Option<String>,
+    /// First slot returned, defaults to the oldest tracked slot.
+    pub start_slot: Option<Slot>,
+    /// Last slot returned, defaults to the newest tracked slot.
+    pub end_slot: Option<Slot>,
+    /// Number of raw fees returned per slot and kind of transaction, at most `MAX_RAW_FEES`.
+    pub max_raw_fees: Option<usize>, // default to DEFAULT_MAX_RAW_FEES
+    /// Name of the cluster serving the request.
+    pub cluster: Option<String>,
+}
+
+/// Request object for the `getTipEstimate` method.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(
+    rename_all(serialize = "camelCase", deserialize = "camelCase"),
+    deny_unknown_fields
+)]
+pub struct GetTipEstimateRequest {
+    /// Optional list of account keys, tips of transactions writing to them are used.
+    pub account_keys: Option<Vec<String>>, // default to all tips
+    /// Number of slots to look back for estimation.
+    pub lookback_slots: Option<u32>, // default to all tracked slots, min 1, max 150
+    /// Name of the cluster serving the request.
+    pub cluster: Option<String>,
 }
 
 /// RPC trait for the Atlas Priority Fee Estimator.
+///
+/// Every method except `health` is served by the cluster selected by the path prefix of the
+/// request or the `cluster` field of its parameters, see [`ClusterRouter`](crate::cluster::ClusterRouter).
 #[rpc(server)]
 pub trait AtlasPriorityFeeEstimatorRpc {
     /// Returns the health status of the estimator.
     #[method(name = "health")]
     fn health(&self) -> String;
 
+    /// Returns `ok` once the window is warm and its data is fresh, an error listing the reasons
+    /// otherwise.
+    #[method(name = "ready", with_extensions)]
+    fn ready(&self) -> RpcResult<String>;
+
+    /// Returns the ingestion state, window fill and readiness of the estimator.
+    #[method(name = "getHealth", with_extensions)]
+    fn get_health(&self) -> RpcResult<HealthReport>;
+
     /// Returns a priority fee estimate based on the provided request.
-    #[method(name = "getPriorityFeeEstimate")]
+    #[method(name = "getPriorityFeeEstimate", with_extensions)]
     fn get_priority_fee_estimate(
         &self,
         get_priority_fee_estimate_request: GetPriorityFeeEstimateRequest,
-    ) -> RpcResult<GetPriorityFeeEstimateResponse> {
-        self.get_priority_fee_estimate_v2(get_priority_fee_estimate_request)
-    }
+    ) -> RpcResult<GetPriorityFeeEstimateResponse>;
 
     /// Returns a priority fee estimate using algorithm version 1.
-    #[method(name = "getPriorityFeeEstimateV1")]
+    #[method(name = "getPriorityFeeEstimateV1", with_extensions)]
     fn get_priority_fee_estimate_v1(
         &self,
         get_priority_fee_estimate_request: GetPriorityFeeEstimateRequest,
     ) -> RpcResult<GetPriorityFeeEstimateResponse>;
 
     /// Returns a priority fee estimate using algorithm version 2.
-    #[method(name = "getPriorityFeeEstimateV2")]
+    #[method(name = "getPriorityFeeEstimateV2", with_extensions)]
     fn get_priority_fee_estimate_v2(
         &self,
         get_priority_fee_estimate_request: GetPriorityFeeEstimateRequest,
     ) -> RpcResult<GetPriorityFeeEstimateResponse>;
+
+    /// Returns block metadata and transaction counts for a tracked slot.
+    #[method(name = "getSlotSummary", with_extensions)]
+    fn get_slot_summary(
+        &self,
+        get_slot_summary_request: Option<GetSlotSummaryRequest>,
+    ) -> RpcResult<SlotSummary>;
+
+    /// Returns fee statistics of the most recent slots produced by a leader, oldest first.
+    #[method(name = "getLeaderFeeHistory", with_extensions)]
+    fn get_leader_fee_history(
+        &self,
+        get_leader_fee_history_request: GetLeaderFeeHistoryRequest,
+    ) -> RpcResult<Vec<LeaderSlotFees>>;
+
+    /// Returns sample counts, percentiles and raw fees of the tracked slots in a range, for an
+    /// account or globally, newest first.
+    #[method(name = "getSlotFees", with_extensions)]
+    fn get_slot_fees(
+        &self,
+        get_slot_fees_request: Option<GetSlotFeesRequest>,
+    ) -> RpcResult<Vec<SlotFeeBreakdown>>;
+
+    /// Returns tip percentiles in lamports.
+    #[method(name = "getTipEstimate", with_extensions)]
+    fn get_tip_estimate(
+        &self,
+        get_tip_estimate_request: Option<GetTipEstimateRequest>,
+    ) -> RpcResult<LamportTipEstimates>;
 }
 
 fn validate_get_priority_fee_estimate_request(
     get_priority_fee_estimate_request: &GetPriorityFeeEstimateRequest,
+    max_account_keys: usize,
 ) -> Option<ErrorObjectOwned> {
     if get_priority_fee_estimate_request.transaction.is_some()
         && get_priority_fee_estimate_request.account_keys.is_some()
@@ -145,19 +302,23 @@ fn validate_get_priority_fee_estimate_request(
         ));
     }
     if let Some(account_keys) = get_priority_fee_estimate_request.account_keys.clone() {
-        if account_keys.len() > 500 {
-            return Some(invalid_request("number of account_keys must be <= 500"));
+        if account_keys.len() > max_account_keys {
+            return Some(invalid_request(&format!(
+                "number of account_keys must be <= {max_account_keys}"
+            )));
         }
     }
     if let Some(options) = get_priority_fee_estimate_request.options.clone() {
         let custom_controls_set = options.priority_level.is_some()
             || options.include_all_priority_fee_levels.is_some()
             || options.lookback_slots.is_some()
-            || options.include_vote.is_some();
+            || options.lookback_seconds.is_some()
+            || options.include_vote.is_some()
+            || options.commitment.is_some();
         let recommended_set = options.recommended.is_some();
         if custom_controls_set && recommended_set {
             return Some(invalid_request(
-                "recommended cannot be used with priority_level, include_all_priority_fee_levels, lookback_slots, include_vote",
+                "recommended cannot be used with priority_level, include_all_priority_fee_levels, lookback_slots, lookback_seconds, include_vote, commitment",
             ));
         }
     }
@@ -263,7 +424,7 @@ fn get_from_address_lookup_tables(
 }
 
 fn get_accounts(
-    rpc_client: &Option<RpcClient>,
+    rpc_client: &Option<Arc<RpcClient>>,
     get_priority_fee_estimate_request: GetPriorityFeeEstimateRequest,
 ) -> RpcResult<Vec<String>> {
     if let Some(account_keys) = get_priority_fee_estimate_request.account_keys {
@@ -296,56 +457,227 @@ fn get_accounts(
     Ok(vec![])
 }
 
-#[async_trait]
-impl AtlasPriorityFeeEstimatorRpcServer for AtlasPriorityFeeEstimator {
-    fn health(&self) -> String {
+/// Methods of a single cluster, called by the [`AtlasPriorityFeeEstimatorRpc`] implementation of
+/// [`ClusterRouter`](crate::cluster::ClusterRouter). The `cluster` fields of requests are ignored.
+impl AtlasPriorityFeeEstimator {
+    /// Returns the health status of the estimator.
+    pub fn health(&self) -> String {
         "ok".to_string()
     }
 
-    fn get_priority_fee_estimate_v1(
+    /// Returns `ok` once the window is warm and its data is fresh, an error listing the reasons
+    /// otherwise.
+    pub fn ready(&self) -> RpcResult<String> {
+        let report = self.health_report();
+        if report.ready {
+            Ok("ok".to_string())
+        } else {
+            statsd_count!("not_ready", 1);
+            Err(not_ready(&report.not_ready_reasons))
+        }
+    }
+
+    /// Returns the ingestion state, window fill and readiness of the estimator.
+    pub fn get_health(&self) -> HealthReport {
+        self.health_report()
+    }
+
+    /// Returns a priority fee estimate using algorithm version 2.
+    pub fn get_priority_fee_estimate(
+        &self,
+        get_priority_fee_estimate_request: GetPriorityFeeEstimateRequest,
+    ) -> RpcResult<GetPriorityFeeEstimateResponse> {
+        self.get_priority_fee_estimate_v2(get_priority_fee_estimate_request)
+    }
+
+    /// Returns a priority fee estimate using algorithm version 1.
+    pub fn get_priority_fee_estimate_v1(
         &self,
         get_priority_fee_estimate_request: GetPriorityFeeEstimateRequest,
     ) -> RpcResult<GetPriorityFeeEstimateResponse> {
         self.execute_priority_fee_estimate_coordinator(get_priority_fee_estimate_request, true)
     }
 
-    fn get_priority_fee_estimate_v2(
+    /// Returns a priority fee estimate using algorithm version 2.
+    pub fn get_priority_fee_estimate_v2(
         &self,
         get_priority_fee_estimate_request: GetPriorityFeeEstimateRequest,
     ) -> RpcResult<GetPriorityFeeEstimateResponse> {
         self.execute_priority_fee_estimate_coordinator(get_priority_fee_estimate_request, false)
     }
+
+    /// Returns block metadata and transaction counts for a tracked slot.
+    pub fn get_slot_summary(
+        &self,
+        get_slot_summary_request: Option<GetSlotSummaryRequest>,
+    ) -> RpcResult<SlotSummary> {
+        let slot = get_slot_summary_request.and_then(|request| request.slot);
+        self.priority_fee_tracker
+            .slot_summary(slot)
+            .ok_or_else(|| match slot {
+                Some(slot) => invalid_request(&format!("slot {slot} is not tracked")),
+                None => invalid_request("no slots are tracked yet"),
+            })
+    }
+
+    /// Returns fee statistics of the most recent slots produced by a leader, oldest first.
+    pub fn get_leader_fee_history(
+        &self,
+        get_leader_fee_history_request: GetLeaderFeeHistoryRequest,
+    ) -> RpcResult<Vec<LeaderSlotFees>> {
+        let leader = Pubkey::from_str(&get_leader_fee_history_request.leader)
+            .map_err(|_| invalid_request("leader must be a valid base58 pubkey"))?;
+        Ok(self.priority_fee_tracker.leader_fee_history(&leader))
+    }
+
+    /// Returns sample counts, percentiles and raw fees of the tracked slots in a range, for an
+    /// account or globally, newest first.
+    pub fn get_slot_fees(
+        &self,
+        get_slot_fees_request: Option<GetSlotFeesRequest>,
+    ) -> RpcResult<Vec<SlotFeeBreakdown>> {
+        let request = get_slot_fees_request.unwrap_or_default();
+        let account = request
+            .account_key
+            .as_deref()
+            .map(Pubkey::from_str)
+            .transpose()
+            .map_err(|_| invalid_request("account_key must be a valid base58 pubkey"))?;
+        if let (Some(start_slot), Some(end_slot)) = (request.start_slot, request.end_slot) {
+            if start_slot > end_slot {
+                return Err(invalid_request("start_slot must be <= end_slot"));
+            }
+        }
+        let max_raw_fees = request.max_raw_fees.unwrap_or(DEFAULT_MAX_RAW_FEES);
+        if max_raw_fees > MAX_RAW_FEES {
+            return Err(invalid_request(&format!(
+                "max_raw_fees must be <= {MAX_RAW_FEES}"
+            )));
+        }
+        Ok(self.priority_fee_tracker.slot_fee_breakdowns(
+            account.as_ref(),
+            request.start_slot,
+            request.end_slot,
+            max_raw_fees,
+        ))
+    }
+
+    /// Returns tip percentiles in lamports.
+    pub fn get_tip_estimate(
+        &self,
+        get_tip_estimate_request: Option<GetTipEstimateRequest>,
+    ) -> RpcResult<LamportTipEstimates> {
+        let request = get_tip_estimate_request.unwrap_or_default();
+        let account_keys = request.account_keys.unwrap_or_default();
+        let max_account_keys = self.runtime_config.current().max_account_keys;
+        if account_keys.len() > max_account_keys {
+            return Err(invalid_request(&format!(
+                "number of account_keys must be <= {max_account_keys}"
+            )));
+        }
+        let accounts: Vec<Pubkey> = account_keys
+            .iter()
+            .filter_map(|a| Pubkey::from_str(a).ok())
+            .collect();
+        if let Some(lookback_slots) = request.lookback_slots {
+            if lookback_slots < 1 || lookback_slots as usize > self.max_lookback_slots {
+                return Err(invalid_request(&format!(
+                    "lookback_slots must be between 1 and {}",
+                    self.max_lookback_slots
+                )));
+            }
+        }
+        let lookback_slots = request
+            .lookback_slots
+            .or(self.runtime_config.current().default_lookback_slots);
+        Ok(self.priority_fee_tracker.tip_estimates(
+            &accounts,
+            &lookback_slots,
+            &CalculationOptions::default(),
+        ))
+    }
 }
 
 impl AtlasPriorityFeeEstimator {
     /// Creates a new AtlasPriorityFeeEstimator.
     pub fn new(
         priority_fee_tracker: Arc<PriorityFeeTracker>,
-        rpc_url: String,
+        rpc_client: Arc<RpcClient>,
         max_lookback_slots: usize,
     ) -> Self {
         AtlasPriorityFeeEstimator {
             priority_fee_tracker,
-            rpc_client: Some(RpcClient::new(rpc_url)),
+            rpc_client: Some(rpc_client),
             max_lookback_slots,
+            recommended_policies: Default::default(),
+            runtime_config: Default::default(),
+            ingest_status: Default::default(),
+            estimate_cache: Default::default(),
         }
     }
 
+    /// Returns this estimator deriving recommended fees from the given policies.
+    pub fn with_recommended_policies(
+        mut self,
+        recommended_policies: Arc<RecommendedPolicyStore>,
+    ) -> Self {
+        self.recommended_policies = recommended_policies;
+        self
+    }
+
+    /// Returns this estimator reading its runtime settings from the given store.
+    pub fn with_runtime_config(mut self, runtime_config: Arc<RuntimeConfigStore>) -> Self {
+        self.runtime_config = runtime_config;
+        self
+    }
+
+    /// Returns this estimator reporting the given ingestion state in its health checks.
+    pub fn with_ingest_status(mut self, ingest_status: Arc<IngestStatus>) -> Self {
+        self.ingest_status = ingest_status;
+        self
+    }
+
+    fn health_report(&self) -> HealthReport {
+        HealthReport::new(
+            &self.priority_fee_tracker,
+            self.max_lookback_slots,
+            &self.ingest_status,
+            &self.runtime_config.current(),
+        )
+    }
+
     fn execute_priority_fee_estimate_coordinator(
         &self,
         get_priority_fee_estimate_request: GetPriorityFeeEstimateRequest,
         is_v1: bool,
     ) -> RpcResult<GetPriorityFeeEstimateResponse> {
+        let span = info_span!(
+            "get_priority_fee_estimate",
+            algorithm = Empty,
+            account_count = Empty,
+            slot = Empty
+        );
+        let _span = span.enter();
         let options = get_priority_fee_estimate_request.options.clone();
         let include_details = options
             .as_ref()
             .and_then(|op| op.include_details)
             .unwrap_or(false);
-        let reason = validate_get_priority_fee_estimate_request(&get_priority_fee_estimate_request);
+        let reason = info_span!("validate_request").in_scope(|| {
+            validate_get_priority_fee_estimate_request(
+                &get_priority_fee_estimate_request,
+                self.runtime_config.current().max_account_keys,
+            )
+        });
         if let Some(reason) = reason {
             return Err(reason);
         }
-        let accounts = get_accounts(&self.rpc_client, get_priority_fee_estimate_request);
+        // resolves the address lookup tables of the transaction, if any, over RPC
+        let accounts = info_span!(
+            "resolve_accounts",
+            transaction = get_priority_fee_estimate_request.transaction.is_some()
+        )
+        .in_scope(|| get_accounts(&self.rpc_client, get_priority_fee_estimate_request));
         if let Err(e) = accounts {
             return Err(e);
         }
@@ -354,15 +686,114 @@ impl AtlasPriorityFeeEstimator {
             .iter()
             .filter_map(|a| Pubkey::from_str(a).ok())
             .collect();
+        span.record("account_count", accounts.len());
         let lookback_slots = options.as_ref().and_then(|o| o.lookback_slots);
         if let Some(lookback_slots) = &lookback_slots {
             if *lookback_slots < 1 || *lookback_slots as usize > self.max_lookback_slots {
                 return Err(invalid_request("lookback_slots must be between 1 and 150"));
             }
         }
+        let lookback_slots =
+            lookback_slots.or(self.runtime_config.current().default_lookback_slots);
+        let lookback_seconds = options.as_ref().and_then(|o| o.lookback_seconds);
+        if lookback_seconds == Some(0) {
+            return Err(invalid_request("lookback_seconds must be at least 1"));
+        }
+        let outlier_filter = options.as_ref().and_then(|o| o.outlier_filter);
+        match outlier_filter {
+            Some(OutlierFilter::Trim(percent) | OutlierFilter::Winsorize(percent))
+                if percent >= 50 =>
+            {
+                return Err(invalid_request(
+                    "outlier_filter percentage must be less than 50",
+                ));
+            }
+            Some(OutlierFilter::Mad(0)) => {
+                return Err(invalid_request(
+                    "outlier_filter threshold must be at least 1",
+                ));
+            }
+            _ => {}
+        }
+        let upcoming_leader_weight = options.as_ref().and_then(|o| o.upcoming_leader_weight);
+        let leader_boost = match upcoming_leader_weight {
+            Some(0) => {
+                return Err(invalid_request("upcoming_leader_weight must be at least 1"));
+            }
+            Some(weight) => match self.priority_fee_tracker.upcoming_leaders() {
+                Some(leaders) => Some(LeaderBoost { leaders, weight }),
+                None => {
+                    return Err(invalid_request(
+                        "upcoming_leader_weight requires a leader schedule",
+                    ));
+                }
+            },
+            None => None,
+        };
+        let payer_dedup = options.as_ref().and_then(|o| o.payer_dedup);
+        if payer_dedup == Some(PayerDedup::Cap(0)) {
+            return Err(invalid_request("payer_dedup cap must be at least 1"));
+        }
+        let min_samples = options.as_ref().and_then(|o| o.min_samples);
+        if min_samples == Some(0) {
+            return Err(invalid_request("min_samples must be at least 1"));
+        }
+        let calculation_options = CalculationOptions {
+            lookback_seconds,
+            commitment: options
+                .as_ref()
+                .and_then(|o| o.commitment)
+                .unwrap_or_default(),
+            transaction_status: if should_include_failed(&options) {
+                TransactionStatusFilter::All
+            } else {
+                TransactionStatusFilter::Succeeded
+            },
+            outlier_filter,
+            leader_boost,
+            payer_dedup,
+            aggregation: options
+                .as_ref()
+                .and_then(|o| o.aggregation)
+                .unwrap_or_default(),
+            min_samples,
+        };
         let include_vote = should_include_vote(&options);
         let include_empty_slots = should_include_empty_slots(&options);
-        let calc = if is_v1 {
+        let forecast_slots = options.as_ref().and_then(|o| o.forecast);
+        if let Some(forecast_slots) = forecast_slots {
+            if !(1..=MAX_FORECAST_SLOTS).contains(&forecast_slots) {
+                return Err(invalid_request(&format!(
+                    "forecast must be between 1 and {MAX_FORECAST_SLOTS}"
+                )));
+            }
+        }
+        let half_life_slots = options.as_ref().and_then(|o| o.half_life_slots);
+        if half_life_slots == Some(0) {
+            return Err(invalid_request("half_life_slots must be at least 1"));
+        }
+        if is_v1 && half_life_slots.is_some() {
+            return Err(invalid_request(
+                "half_life_slots is not supported by getPriorityFeeEstimateV1",
+            ));
+        }
+        span.record(
+            "algorithm",
+            match (half_life_slots, is_v1) {
+                (Some(_), _) => "weighted",
+                (None, true) => "v1",
+                (None, false) => "v2",
+            },
+        );
+        let calc = if let Some(half_life_slots) = half_life_slots {
+            Calculations::new_calculation3(
+                &accounts,
+                include_vote,
+                include_empty_slots,
+                &lookback_slots,
+                half_life_slots,
+            )
+        } else if is_v1 {
             Calculations::new_calculation1(
                 &accounts,
                 include_vote,
@@ -376,45 +807,48 @@ impl AtlasPriorityFeeEstimator {
                 include_empty_slots,
                 &lookback_slots,
             )
+        }
+        .with_options(calculation_options);
+        // the leader fee history only holds the successful non-vote fees of whole slots
+        if calc.options().leader_boost.is_some() && !calc.supports_leader_boost() {
+            return Err(invalid_request(
+                "upcoming_leader_weight cannot be used with accounts, include_vote, include_failed or payer_dedup",
+            ));
+        }
+        if let Some(slot) = self.priority_fee_tracker.newest_slot() {
+            span.record("slot", slot);
+        }
+        let cache_key =
+            estimate_cache_key(is_v1, &accounts, &lookback_slots, &options, calc.options());
+        let estimate = info_span!("calculate").in_scope(|| {
+            self.estimate_cache.get_or_compute(
+                self.priority_fee_tracker.data_version(),
+                cache_key,
+                || {
+                    self.compute_estimate(
+                        &calc,
+                        &accounts,
+                        include_vote,
+                        include_details,
+                        &lookback_slots,
+                        forecast_slots,
+                    )
+                },
+            )
+        });
+        let Some(estimate) = estimate else {
+            return Err(ErrorObjectOwned::owned(
+                INTERNAL_ERROR_CODE,
+                INTERNAL_ERROR_MSG,
+                None::<String>,
+            ));
         };
-        let result: anyhow::Result<(
-            MicroLamportPriorityFeeEstimates,
-            Option<HashMap<String, MicroLamportPriorityFeeDetails>>,
-        )> = if include_details {
-            self.priority_fee_tracker
-                .calculate_priority_fee_details(&calc)
-                .map(|(fee, details)| (fee, Some(details)))
-        } else {
-            self.priority_fee_tracker
-                .calculate_priority_fee(&calc)
-                .map(|fee| (fee, None))
-        };
-
-        let (total_priority_fee_levels, priority_fee_levels): (
-            MicroLamportPriorityFeeEstimates,
-            Option<HashMap<String, MicroLamportPriorityFeeDetails>>,
-        ) = match result {
-            Ok(level) => level,
-            Err(e) => {
-                warn!("failed to calculate priority_fee_levels details: {:#?}", e);
-                return Err(ErrorObjectOwned::owned(
-                    INTERNAL_ERROR_CODE,
-                    INTERNAL_ERROR_MSG,
-                    None::<String>,
-                ));
-            }
-        };
-
-        let priority_fee_levels: Option<Vec<(String, MicroLamportPriorityFeeDetails)>> =
-            match priority_fee_levels {
-                None => None,
-                Some(fees_map) => {
-                    let mut fees: Vec<(String, MicroLamportPriorityFeeDetails)> =
-                        fees_map.into_iter().collect();
-                    fees.sort_by(|a, b| b.0.cmp(&a.0));
-                    Some(fees)
-                }
-            };
+        let CachedEstimate {
+            priority_fee_levels: total_priority_fee_levels,
+            details: priority_fee_levels,
+            forecast,
+            slot_summaries,
+        } = estimate.as_ref().clone();
 
         if let Some(options) = options.as_ref() {
             if options.include_all_priority_fee_levels == Some(true) {
@@ -422,6 +856,9 @@ impl AtlasPriorityFeeEstimator {
                     priority_fee_estimate_details: priority_fee_levels,
                     priority_fee_estimate: None,
                     priority_fee_levels: Some(total_priority_fee_levels),
+                    slot_summaries,
+                    forecast,
+                    recommended_policy: None,
                 });
             }
             if let Some(priority_level) = options.priority_level.as_ref() {
@@ -438,22 +875,150 @@ impl AtlasPriorityFeeEstimator {
                     priority_fee_estimate_details: priority_fee_levels,
                     priority_fee_estimate: Some(priority_fee),
                     priority_fee_levels: None,
+                    slot_summaries,
+                    forecast,
+                    recommended_policy: None,
                 });
             }
         }
         let recommended =
             options.is_some_and(|o: GetPriorityFeeEstimateOptions| o.recommended.unwrap_or(false));
-        let priority_fee = if recommended {
-            get_recommended_fee(total_priority_fee_levels)
+        let (priority_fee, recommended_policy) = if recommended {
+            let policies = self.recommended_policies.current();
+            let policy = policies.select(&accounts);
+            (
+                policy.apply(&total_priority_fee_levels),
+                Some(policy.name.clone()),
+            )
         } else {
-            total_priority_fee_levels.medium
+            (total_priority_fee_levels.medium, None)
         };
         Ok(GetPriorityFeeEstimateResponse {
             priority_fee_estimate_details: priority_fee_levels,
             priority_fee_estimate: Some(priority_fee),
             priority_fee_levels: None,
+            slot_summaries,
+            forecast,
+            recommended_policy,
         })
     }
+
+    /// Computes the parts of an estimate that only depend on the tracked data and the calculation,
+    /// returning `None` when the calculation fails.
+    fn compute_estimate(
+        &self,
+        calc: &Calculations,
+        accounts: &[Pubkey],
+        include_vote: bool,
+        include_details: bool,
+        lookback_slots: &Option<u32>,
+        forecast_slots: Option<u32>,
+    ) -> Option<Arc<CachedEstimate>> {
+        let result: anyhow::Result<(
+            MicroLamportPriorityFeeEstimates,
+            Option<HashMap<String, MicroLamportPriorityFeeDetails>>,
+        )> = if include_details {
+            self.priority_fee_tracker
+                .calculate_priority_fee_details(calc)
+                .map(|(fee, details)| (fee, Some(details)))
+        } else {
+            self.priority_fee_tracker
+                .calculate_priority_fee(calc)
+                .map(|fee| (fee, None))
+        };
+
+        let (priority_fee_levels, details) = match result {
+            Ok(level) => level,
+            Err(e) => {
+                warn!("failed to calculate priority_fee_levels details: {:#?}", e);
+                return None;
+            }
+        };
+
+        let details = details.map(|fees_map| {
+            let mut fees: Vec<(String, MicroLamportPriorityFeeDetails)> =
+                fees_map.into_iter().collect();
+            fees.sort_by(|a, b| b.0.cmp(&a.0));
+            fees
+        });
+        let forecast = forecast_slots.map(|forecast_slots| {
+            self.priority_fee_tracker.forecast_fees(
+                accounts,
+                include_vote,
+                lookback_slots,
+                calc.options(),
+                forecast_slots,
+            )
+        });
+        let slot_summaries = include_details.then(|| {
+            self.priority_fee_tracker
+                .slot_summaries(lookback_slots, calc.options())
+        });
+        Some(Arc::new(CachedEstimate {
+            priority_fee_levels,
+            details,
+            forecast,
+            slot_summaries,
+        }))
+    }
+}
+
+/// Parts of an estimate shared by identical requests against the same tracked data.
+#[derive(Debug, Clone)]
+struct CachedEstimate {
+    priority_fee_levels: MicroLamportPriorityFeeEstimates,
+    details: Option<Vec<(String, MicroLamportPriorityFeeDetails)>>,
+    forecast: Option<Vec<SlotFeeForecast>>,
+    slot_summaries: Option<Vec<SlotSummary>>,
+}
+
+/// Returns the cache key of a request: its sorted accounts, its lookback, which may come from the
+/// runtime settings, and the options affecting the calculation. Options only selecting which level
+/// is returned are left out, they are applied to the cached estimate, and so is the cluster, every
+/// cluster having its own cache.
+fn estimate_cache_key(
+    is_v1: bool,
+    accounts: &[Pubkey],
+    lookback_slots: &Option<u32>,
+    options: &Option<GetPriorityFeeEstimateOptions>,
+    calculation_options: &CalculationOptions,
+) -> String {
+    let mut accounts: Vec<String> = accounts.iter().map(Pubkey::to_string).collect();
+    accounts.sort_unstable();
+    let options = options
+        .clone()
+        .map(|options| GetPriorityFeeEstimateOptions {
+            priority_level: None,
+            include_all_priority_fee_levels: None,
+            recommended: None,
+            cluster: None,
+            ..options
+        });
+    // time-based windows move with the clock and the boosted leaders with the leader schedule,
+    // neither of which changes the data version
+    let clock_second = calculation_options.lookback_seconds.map(|_| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default()
+    });
+    let leaders = calculation_options.leader_boost.as_ref().map(|boost| {
+        boost
+            .leaders
+            .iter()
+            .map(Pubkey::to_string)
+            .collect::<Vec<_>>()
+            .join(",")
+    });
+    format!(
+        "{}|{}|{:?}|{}|{:?}|{:?}",
+        if is_v1 { "v1" } else { "v2" },
+        accounts.join(","),
+        lookback_slots,
+        serde_json::to_string(&options).unwrap_or_default(),
+        clock_second,
+        leaders
+    )
 }
 
 // default to true for backwards compatibility. Recommended fee does not include vote txns
@@ -471,25 +1036,34 @@ fn should_include_empty_slots(options: &Option<GetPriorityFeeEstimateOptions>) -
     false
 }
 
-const MIN_RECOMMENDED_PRIORITY_FEE: f64 = 10_000.0;
+fn should_include_failed(options: &Option<GetPriorityFeeEstimateOptions>) -> bool {
+    options
+        .as_ref()
+        .and_then(|options| options.include_failed)
+        .unwrap_or(false)
+}
 
-/// Returns the recommended priority fee based on the provided estimates.
-/// Currently, the recommended fee is the medium (50th percentile) fee.
+/// Returns the recommended priority fee based on the provided estimates, using the default policy:
+/// the medium (50th percentile) fee with a floor of 10,000 micro-lamports.
 pub fn get_recommended_fee(priority_fee_levels: MicroLamportPriorityFeeEstimates) -> f64 {
-    let recommended = if priority_fee_levels.medium > MIN_RECOMMENDED_PRIORITY_FEE {
-        priority_fee_levels.medium
-    } else {
-        MIN_RECOMMENDED_PRIORITY_FEE
-    };
-    recommended.ceil()
+    RecommendedFeePolicy::default().apply(&priority_fee_levels)
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::priority_fee::PriorityFeeTracker;
+    use crate::config::RuntimeConfig;
+    use crate::errors::NOT_READY_CODE;
+    use crate::priority_fee::{PriorityFeeTracker, TrackerOptions};
+    use crate::recommended_policy::{PolicyOverride, RecommendedFeePolicies, RecommendedFeePolicy};
     use crate::rpc_server::{
-        AtlasPriorityFeeEstimator, AtlasPriorityFeeEstimatorRpcServer,
-        GetPriorityFeeEstimateOptions, GetPriorityFeeEstimateRequest,
+        estimate_cache_key, AtlasPriorityFeeEstimator, GetLeaderFeeHistoryRequest,
+        GetPriorityFeeEstimateOptions, GetPriorityFeeEstimateRequest, GetSlotFeesRequest,
+        GetSlotSummaryRequest, GetTipEstimateRequest, MAX_RAW_FEES,
+    };
+    use crate::telemetry::{capture_spans, span_attribute};
+    use crate::{
+        Aggregation, BlockMetadata, CalculationOptions, LeaderBoost, PayerDedup, PriorityLevel,
+        SlotCommitment,
     };
     use cadence::{NopMetricSink, StatsdClient};
     use jsonrpsee::core::Cow;
@@ -497,7 +1071,9 @@ mod tests {
     use jsonrpsee::types::{Id, Request, TwoPointZero};
     use solana_sdk::clock::Slot;
     use solana_sdk::pubkey::Pubkey;
+    use std::collections::HashMap;
     use std::sync::Arc;
+    use std::time::{SystemTime, UNIX_EPOCH};
 
     #[tokio::test]
     async fn test_calculating_fees_with_all_options_none() {
@@ -512,6 +1088,10 @@ mod tests {
             priority_fee_tracker: Arc::new(tracker),
             rpc_client: None,
             max_lookback_slots: 150,
+            recommended_policies: Default::default(),
+            runtime_config: Default::default(),
+            ingest_status: Default::default(),
+            estimate_cache: Default::default(),
         };
 
         let result = server.get_priority_fee_estimate(GetPriorityFeeEstimateRequest {
@@ -537,6 +1117,10 @@ mod tests {
             priority_fee_tracker: Arc::new(tracker),
             rpc_client: None,
             max_lookback_slots: 150,
+            recommended_policies: Default::default(),
+            runtime_config: Default::default(),
+            ingest_status: Default::default(),
+            estimate_cache: Default::default(),
         };
 
         let result = server.get_priority_fee_estimate(GetPriorityFeeEstimateRequest {
@@ -562,6 +1146,10 @@ mod tests {
             priority_fee_tracker: Arc::new(tracker),
             rpc_client: None,
             max_lookback_slots: 150,
+            recommended_policies: Default::default(),
+            runtime_config: Default::default(),
+            ingest_status: Default::default(),
+            estimate_cache: Default::default(),
         };
 
         let result = server.get_priority_fee_estimate(GetPriorityFeeEstimateRequest {
@@ -596,6 +1184,10 @@ mod tests {
             priority_fee_tracker: Arc::new(tracker),
             rpc_client: None,
             max_lookback_slots: 150,
+            recommended_policies: Default::default(),
+            runtime_config: Default::default(),
+            ingest_status: Default::default(),
+            estimate_cache: Default::default(),
         };
 
         let result = server.get_priority_fee_estimate(GetPriorityFeeEstimateRequest {
@@ -609,6 +1201,1190 @@ mod tests {
         let resp = result.unwrap();
         assert!(resp.priority_fee_levels.is_none());
         assert_eq!(resp.priority_fee_estimate, Some(10000.0));
+        assert_eq!(resp.recommended_policy.as_deref(), Some("default"));
+
+        // the recommended fee is always derived from confirmed slots
+        let result = server.get_priority_fee_estimate(GetPriorityFeeEstimateRequest {
+            account_keys: Some(vec![acc1.to_string(), acc2.to_string()]),
+            options: Some(GetPriorityFeeEstimateOptions {
+                recommended: Some(true),
+                commitment: Some(SlotCommitment::Processed),
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+        assert!(result.is_err());
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_recommended_policy_override() {
+        prep_statsd();
+
+        let acc1 = Pubkey::new_unique();
+        let acc2 = Pubkey::new_unique();
+        let tracker = PriorityFeeTracker::new(150);
+        for fee in [100_000, 100_000, 200_000, 200_000, 200_000] {
+            tracker.push_priority_fee_for_txn(1 as Slot, vec![acc1, acc2], fee, false);
+        }
+
+        let server = AtlasPriorityFeeEstimator {
+            priority_fee_tracker: Arc::new(tracker),
+            rpc_client: None,
+            max_lookback_slots: 150,
+            recommended_policies: Default::default(),
+            runtime_config: Default::default(),
+            ingest_status: Default::default(),
+            estimate_cache: Default::default(),
+        };
+        let request = |account: Pubkey| GetPriorityFeeEstimateRequest {
+            account_keys: Some(vec![account.to_string()]),
+            options: Some(GetPriorityFeeEstimateOptions {
+                recommended: Some(true),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let resp = server.get_priority_fee_estimate(request(acc1)).unwrap();
+        assert_eq!(resp.priority_fee_estimate, Some(200_000.0));
+
+        // policies are swapped without restarting the server
+        server.recommended_policies.replace(RecommendedFeePolicies {
+            default: RecommendedFeePolicy {
+                name: "capped".to_string(),
+                ceiling: Some(80_000.0),
+                ..Default::default()
+            },
+            overrides: vec![PolicyOverride {
+                accounts: vec![acc2.to_string()],
+                policy: RecommendedFeePolicy {
+                    name: "boosted".to_string(),
+                    level: PriorityLevel::High,
+                    multiplier: 2.0,
+                    ..Default::default()
+                },
+            }],
+        });
+        let resp = server.get_priority_fee_estimate(request(acc1)).unwrap();
+        assert_eq!(resp.priority_fee_estimate, Some(80_000.0));
+        assert_eq!(resp.recommended_policy.as_deref(), Some("capped"));
+        let resp = server.get_priority_fee_estimate(request(acc2)).unwrap();
+        assert_eq!(resp.priority_fee_estimate, Some(400_000.0));
+        assert_eq!(resp.recommended_policy.as_deref(), Some("boosted"));
+    }
+
+    #[tokio::test]
+    async fn test_estimate_cache() {
+        prep_statsd();
+
+        let acc1 = Pubkey::new_unique();
+        let acc2 = Pubkey::new_unique();
+        let tracker = Arc::new(PriorityFeeTracker::new(150));
+        tracker.push_priority_fee_for_txn(1 as Slot, vec![acc1, acc2], 100, false);
+
+        let server = AtlasPriorityFeeEstimator {
+            priority_fee_tracker: tracker.clone(),
+            rpc_client: None,
+            max_lookback_slots: 150,
+            recommended_policies: Default::default(),
+            runtime_config: Default::default(),
+            ingest_status: Default::default(),
+            estimate_cache: Default::default(),
+        };
+        let request =
+            |accounts: Vec<String>, priority_level: PriorityLevel| GetPriorityFeeEstimateRequest {
+                account_keys: Some(accounts),
+                options: Some(GetPriorityFeeEstimateOptions {
+                    priority_level: Some(priority_level),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            };
+
+        let resp = server
+            .get_priority_fee_estimate(request(
+                vec![acc1.to_string(), acc2.to_string()],
+                PriorityLevel::Medium,
+            ))
+            .unwrap();
+        assert_eq!(resp.priority_fee_estimate, Some(100.0));
+        // the account order and the returned level do not change the computation
+        let resp = server
+            .get_priority_fee_estimate(request(
+                vec![acc2.to_string(), acc1.to_string()],
+                PriorityLevel::High,
+            ))
+            .unwrap();
+        assert_eq!(resp.priority_fee_estimate, Some(100.0));
+        assert_eq!(server.estimate_cache.len(), 1);
+
+        // fees of a slot still being ingested do not invalidate the cache
+        tracker.push_priority_fee_for_txn(2 as Slot, vec![acc1, acc2], 10_000, false);
+        let resp = server
+            .get_priority_fee_estimate(request(
+                vec![acc1.to_string(), acc2.to_string()],
+                PriorityLevel::UnsafeMax,
+            ))
+            .unwrap();
+        assert_eq!(resp.priority_fee_estimate, Some(100.0));
+
+        // completing the slot invalidates the cache
+        tracker.complete_slot(2 as Slot);
+        let resp = server
+            .get_priority_fee_estimate(request(
+                vec![acc1.to_string(), acc2.to_string()],
+                PriorityLevel::UnsafeMax,
+            ))
+            .unwrap();
+        assert_eq!(resp.priority_fee_estimate, Some(10_000.0));
+        assert_eq!(server.estimate_cache.len(), 1);
+
+        // the clock and the upcoming leaders are part of the key when they affect the estimate
+        let key = |calculation_options: &CalculationOptions| {
+            estimate_cache_key(false, &[acc1], &None, &None, calculation_options)
+        };
+        let options = CalculationOptions::default();
+        assert_eq!(key(&options), key(&options));
+        let time_window = CalculationOptions {
+            lookback_seconds: Some(10),
+            ..Default::default()
+        };
+        let clock_second = || {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs()
+        };
+        let before = clock_second();
+        let time_key = key(&time_window);
+        assert!((before..=clock_second())
+            .any(|second| time_key.ends_with(&format!("|Some({second})|None"))));
+        let boost = |leader: Pubkey| CalculationOptions {
+            leader_boost: Some(LeaderBoost {
+                leaders: vec![leader],
+                weight: 4,
+            }),
+            ..Default::default()
+        };
+        assert_ne!(key(&boost(acc1)), key(&boost(acc2)));
+    }
+
+    #[tokio::test]
+    async fn test_estimate_spans() {
+        prep_statsd();
+
+        let acc1 = Pubkey::new_unique();
+        let tracker = Arc::new(PriorityFeeTracker::new(150));
+        tracker.push_priority_fee_for_txn(7 as Slot, vec![acc1], 100, false);
+
+        let server = AtlasPriorityFeeEstimator {
+            priority_fee_tracker: tracker,
+            rpc_client: None,
+            max_lookback_slots: 150,
+            recommended_policies: Default::default(),
+            runtime_config: Default::default(),
+            ingest_status: Default::default(),
+            estimate_cache: Default::default(),
+        };
+        let (resp, spans) = capture_spans(async {
+            server.get_priority_fee_estimate_v1(GetPriorityFeeEstimateRequest {
+                account_keys: Some(vec![acc1.to_string()]),
+                ..Default::default()
+            })
+        })
+        .await;
+        assert_eq!(resp.unwrap().priority_fee_estimate, Some(100.0));
+
+        let request = spans
+            .iter()
+            .find(|span| span.name == "get_priority_fee_estimate")
+            .expect("expected request span");
+        assert_eq!(span_attribute(request, "algorithm").as_deref(), Some("v1"));
+        assert_eq!(
+            span_attribute(request, "account_count").as_deref(),
+            Some("1")
+        );
+        assert_eq!(span_attribute(request, "slot").as_deref(), Some("7"));
+        for name in ["validate_request", "resolve_accounts", "calculate"] {
+            let child = spans
+                .iter()
+                .find(|span| span.name == name)
+                .unwrap_or_else(|| panic!("expected {name} span"));
+            assert_eq!(child.parent_span_id, request.span_context.span_id());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_default_lookback() {
+        prep_statsd();
+
+        let acc1 = Pubkey::new_unique();
+        let tracker = PriorityFeeTracker::new(150);
+        for slot in 1..=3 as Slot {
+            tracker.push_priority_fee_for_txn(slot, vec![acc1], slot * 100, false);
+        }
+        let server = AtlasPriorityFeeEstimator {
+            priority_fee_tracker: Arc::new(tracker),
+            rpc_client: None,
+            max_lookback_slots: 150,
+            recommended_policies: Default::default(),
+            runtime_config: Default::default(),
+            ingest_status: Default::default(),
+            estimate_cache: Default::default(),
+        };
+        let min_fee = |lookback_slots: Option<u32>| {
+            server
+                .get_priority_fee_estimate(GetPriorityFeeEstimateRequest {
+                    account_keys: Some(vec![acc1.to_string()]),
+                    options: Some(GetPriorityFeeEstimateOptions {
+                        priority_level: Some(PriorityLevel::Min),
+                        lookback_slots,
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                })
+                .unwrap()
+                .priority_fee_estimate
+        };
+        assert_eq!(min_fee(None), Some(100.0));
+
+        // requests without lookback_slots use the default, others keep their own
+        server.runtime_config.replace(RuntimeConfig {
+            default_lookback_slots: Some(1),
+            ..Default::default()
+        });
+        assert_eq!(min_fee(None), Some(300.0));
+        assert_eq!(min_fee(Some(2)), Some(200.0));
+    }
+
+    #[tokio::test]
+    async fn test_runtime_max_account_keys() {
+        prep_statsd();
+
+        let server = AtlasPriorityFeeEstimator {
+            priority_fee_tracker: Arc::new(PriorityFeeTracker::new(150)),
+            rpc_client: None,
+            max_lookback_slots: 150,
+            recommended_policies: Default::default(),
+            runtime_config: Default::default(),
+            ingest_status: Default::default(),
+            estimate_cache: Default::default(),
+        };
+        let request = GetPriorityFeeEstimateRequest {
+            account_keys: Some((0..3).map(|_| Pubkey::new_unique().to_string()).collect()),
+            ..Default::default()
+        };
+        assert!(server.get_priority_fee_estimate(request.clone()).is_ok());
+
+        // the limit is lowered without restarting the server
+        server.runtime_config.replace(RuntimeConfig {
+            max_account_keys: 2,
+            ..Default::default()
+        });
+        let err = server.get_priority_fee_estimate(request).unwrap_err();
+        assert_eq!(
+            err.message(),
+            "Invalid Request: number of account_keys must be <= 2"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_readiness() {
+        prep_statsd();
+
+        let tracker = Arc::new(PriorityFeeTracker::new(150));
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+        for slot in 1..=2 as Slot {
+            tracker.push_block_metadata(
+                slot,
+                BlockMetadata {
+                    block_time: Some(now),
+                    parent_slot: slot - 1,
+                    ..Default::default()
+                },
+            );
+        }
+        let server = AtlasPriorityFeeEstimator {
+            priority_fee_tracker: tracker.clone(),
+            rpc_client: None,
+            max_lookback_slots: 150,
+            recommended_policies: Default::default(),
+            runtime_config: Default::default(),
+            ingest_status: Default::default(),
+            estimate_cache: Default::default(),
+        };
+
+        // two slots do not warm a window of 150
+        let err = server.ready().unwrap_err();
+        assert_eq!(err.code(), NOT_READY_CODE);
+        assert_eq!(err.message(), "Not Ready: window holds 2 of 150 slots");
+        let report = server.get_health();
+        assert_eq!(report.newest_slot, Some(2));
+        assert_eq!(report.window_slots, 2);
+        assert!(!report.grpc_connected);
+
+        let server = AtlasPriorityFeeEstimator {
+            priority_fee_tracker: tracker,
+            rpc_client: None,
+            max_lookback_slots: 2,
+            recommended_policies: Default::default(),
+            runtime_config: Default::default(),
+            ingest_status: Default::default(),
+            estimate_cache: Default::default(),
+        };
+        assert_eq!(server.ready().unwrap(), "ok");
+        assert!(server.get_health().ready);
+    }
+
+    #[tokio::test]
+    async fn test_slot_summaries() {
+        prep_statsd();
+
+        let acc1 = Pubkey::new_unique();
+        let tracker = PriorityFeeTracker::new(150);
+        for slot in 1..=3 as Slot {
+            tracker.push_block_metadata(
+                slot,
+                BlockMetadata {
+                    block_time: Some(1_700_000_000 + slot as i64),
+                    parent_slot: slot - 1,
+                    executed_transaction_count: 1,
+                    ..Default::default()
+                },
+            );
+            tracker.push_priority_fee_for_txn(slot, vec![acc1], 100u64, false);
+        }
+
+        let server = AtlasPriorityFeeEstimator {
+            priority_fee_tracker: Arc::new(tracker),
+            rpc_client: None,
+            max_lookback_slots: 150,
+            recommended_policies: Default::default(),
+            runtime_config: Default::default(),
+            ingest_status: Default::default(),
+            estimate_cache: Default::default(),
+        };
+
+        let summary = server.get_slot_summary(None).unwrap();
+        assert_eq!(summary.slot, 3);
+        assert_eq!(summary.non_vote_transaction_count, 1);
+        assert_eq!(summary.block.unwrap().block_time, Some(1_700_000_003));
+
+        let summary = server
+            .get_slot_summary(Some(GetSlotSummaryRequest {
+                slot: Some(1),
+                ..Default::default()
+            }))
+            .unwrap();
+        assert_eq!(summary.block.unwrap().parent_slot, 0);
+        assert!(server
+            .get_slot_summary(Some(GetSlotSummaryRequest {
+                slot: Some(10),
+                ..Default::default()
+            }))
+            .is_err());
+
+        let resp = server
+            .get_priority_fee_estimate(GetPriorityFeeEstimateRequest {
+                account_keys: Some(vec![acc1.to_string()]),
+                options: Some(GetPriorityFeeEstimateOptions {
+                    include_details: Some(true),
+                    lookback_slots: Some(2),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            })
+            .unwrap();
+        let slots: Vec<Slot> = resp
+            .slot_summaries
+            .unwrap()
+            .iter()
+            .map(|summary| summary.slot)
+            .collect();
+        assert_eq!(slots, vec![3, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_slot_fees() {
+        prep_statsd();
+
+        let acc1 = Pubkey::new_unique();
+        let acc2 = Pubkey::new_unique();
+        let tracker = PriorityFeeTracker::new(150);
+        for slot in 1..=3 as Slot {
+            for fee in 1..=4u64 {
+                tracker.push_priority_fee_for_txn(slot, vec![acc1], fee * 100, false);
+            }
+            tracker.push_priority_fee_for_txn(slot, vec![acc2], 10, true);
+        }
+        tracker.push_failed_priority_fee_for_txn(2, vec![acc1], 50);
+
+        let server = AtlasPriorityFeeEstimator {
+            priority_fee_tracker: Arc::new(tracker),
+            rpc_client: None,
+            max_lookback_slots: 150,
+            recommended_policies: Default::default(),
+            runtime_config: Default::default(),
+            ingest_status: Default::default(),
+            estimate_cache: Default::default(),
+        };
+
+        let slot_fees = server
+            .get_slot_fees(Some(GetSlotFeesRequest {
+                account_key: Some(acc1.to_string()),
+                start_slot: Some(2),
+                max_raw_fees: Some(3),
+                ..Default::default()
+            }))
+            .unwrap();
+        let slots: Vec<Slot> = slot_fees.iter().map(|fees| fees.slot).collect();
+        assert_eq!(slots, vec![3, 2]);
+        let fees = &slot_fees[1];
+        assert_eq!(fees.account, Some(acc1.to_string()));
+        assert_eq!(fees.non_vote.count, 4);
+        assert_eq!(fees.non_vote.fees, vec![100.0, 200.0, 300.0]);
+        assert!(fees.non_vote.truncated);
+        assert_eq!(fees.non_vote.estimates.unsafe_max, 400.0);
+        assert_eq!(fees.vote.count, 0);
+        assert_eq!(fees.failed.fees, vec![50.0]);
+
+        // without an account the global fees are returned, vote fees included
+        let slot_fees = server
+            .get_slot_fees(Some(GetSlotFeesRequest {
+                start_slot: Some(1),
+                end_slot: Some(1),
+                ..Default::default()
+            }))
+            .unwrap();
+        assert_eq!(slot_fees.len(), 1);
+        assert_eq!(slot_fees[0].account, None);
+        assert_eq!(slot_fees[0].non_vote.count, 4);
+        assert!(!slot_fees[0].non_vote.truncated);
+        assert_eq!(slot_fees[0].vote.fees, vec![10.0]);
+
+        assert!(server
+            .get_slot_fees(Some(GetSlotFeesRequest {
+                start_slot: Some(3),
+                end_slot: Some(1),
+                ..Default::default()
+            }))
+            .is_err());
+        assert!(server
+            .get_slot_fees(Some(GetSlotFeesRequest {
+                max_raw_fees: Some(MAX_RAW_FEES + 1),
+                ..Default::default()
+            }))
+            .is_err());
+        assert!(server
+            .get_slot_fees(Some(GetSlotFeesRequest {
+                account_key: Some("invalid".to_string()),
+                ..Default::default()
+            }))
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_calculating_fees_with_lookback_seconds() {
+        prep_statsd();
+
+        let acc1 = Pubkey::new_unique();
+        let tracker = PriorityFeeTracker::new(150);
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+        // an old slot with a high fee followed by a recent slot with a low fee
+        for (slot, age, fee) in [(1 as Slot, 600, 1_000_000u64), (2, 1, 100)] {
+            tracker.push_block_metadata(
+                slot,
+                BlockMetadata {
+                    block_time: Some(now - age),
+                    ..Default::default()
+                },
+            );
+            tracker.push_priority_fee_for_txn(slot, vec![acc1], fee, false);
+        }
+
+        let server = AtlasPriorityFeeEstimator {
+            priority_fee_tracker: Arc::new(tracker),
+            rpc_client: None,
+            max_lookback_slots: 150,
+            recommended_policies: Default::default(),
+            runtime_config: Default::default(),
+            ingest_status: Default::default(),
+            estimate_cache: Default::default(),
+        };
+
+        let result = server.get_priority_fee_estimate(GetPriorityFeeEstimateRequest {
+            account_keys: Some(vec![acc1.to_string()]),
+            options: Some(GetPriorityFeeEstimateOptions {
+                include_all_priority_fee_levels: Some(true),
+                lookback_seconds: Some(60),
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+        let levels = result.unwrap().priority_fee_levels.unwrap();
+        assert_eq!(levels.unsafe_max, 100.0);
+
+        let result = server.get_priority_fee_estimate(GetPriorityFeeEstimateRequest {
+            account_keys: Some(vec![acc1.to_string()]),
+            options: Some(GetPriorityFeeEstimateOptions {
+                lookback_seconds: Some(0),
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+        assert!(result.is_err());
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_calculating_fees_with_failed_transactions() {
+        prep_statsd();
+
+        let acc1 = Pubkey::new_unique();
+        let tracker = PriorityFeeTracker::new(150);
+        tracker.push_priority_fee_for_txn(1, vec![acc1], 100, false);
+        tracker.push_failed_priority_fee_for_txn(1, vec![acc1], 50_000);
+
+        let server = AtlasPriorityFeeEstimator {
+            priority_fee_tracker: Arc::new(tracker),
+            rpc_client: None,
+            max_lookback_slots: 150,
+            recommended_policies: Default::default(),
+            runtime_config: Default::default(),
+            ingest_status: Default::default(),
+            estimate_cache: Default::default(),
+        };
+
+        let result = server.get_priority_fee_estimate(GetPriorityFeeEstimateRequest {
+            account_keys: Some(vec![acc1.to_string()]),
+            options: Some(GetPriorityFeeEstimateOptions {
+                include_all_priority_fee_levels: Some(true),
+                include_details: Some(true),
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+        let resp = result.unwrap();
+        assert_eq!(resp.priority_fee_levels.unwrap().unsafe_max, 100.0);
+        let details = resp.priority_fee_estimate_details.unwrap();
+        let (_, details) = details
+            .iter()
+            .find(|(key, _)| *key == acc1.to_string())
+            .unwrap();
+        assert_eq!(details.success_count, 1);
+        assert_eq!(details.failed_count, 1);
+        assert_eq!(details.failed_estimates.unsafe_max, 50_000.0);
+
+        let result = server.get_priority_fee_estimate(GetPriorityFeeEstimateRequest {
+            account_keys: Some(vec![acc1.to_string()]),
+            options: Some(GetPriorityFeeEstimateOptions {
+                include_all_priority_fee_levels: Some(true),
+                include_failed: Some(true),
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+        let levels = result.unwrap().priority_fee_levels.unwrap();
+        assert_eq!(levels.unsafe_max, 50_000.0);
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_calculating_fees_with_half_life() {
+        prep_statsd();
+
+        let acc1 = Pubkey::new_unique();
+        let tracker = PriorityFeeTracker::new(150);
+        // a burst of expensive slots that has since cooled down
+        for slot in 1..=10 {
+            tracker.push_priority_fee_for_txn(slot, vec![acc1], 1_000_000, false);
+        }
+        tracker.push_priority_fee_for_txn(40, vec![acc1], 100, false);
+
+        let server = AtlasPriorityFeeEstimator {
+            priority_fee_tracker: Arc::new(tracker),
+            rpc_client: None,
+            max_lookback_slots: 150,
+            recommended_policies: Default::default(),
+            runtime_config: Default::default(),
+            ingest_status: Default::default(),
+            estimate_cache: Default::default(),
+        };
+
+        let result = server.get_priority_fee_estimate(GetPriorityFeeEstimateRequest {
+            account_keys: Some(vec![acc1.to_string()]),
+            options: Some(GetPriorityFeeEstimateOptions {
+                include_all_priority_fee_levels: Some(true),
+                evaluate_empty_slot_as_zero: Some(false),
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+        let levels = result.unwrap().priority_fee_levels.unwrap();
+        assert_eq!(levels.medium, 1_000_000.0);
+
+        let result = server.get_priority_fee_estimate(GetPriorityFeeEstimateRequest {
+            account_keys: Some(vec![acc1.to_string()]),
+            options: Some(GetPriorityFeeEstimateOptions {
+                include_all_priority_fee_levels: Some(true),
+                evaluate_empty_slot_as_zero: Some(false),
+                half_life_slots: Some(3),
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+        let levels = result.unwrap().priority_fee_levels.unwrap();
+        assert_eq!(levels.medium, 100.0);
+        assert_eq!(levels.unsafe_max, 1_000_000.0);
+
+        let result = server.get_priority_fee_estimate(GetPriorityFeeEstimateRequest {
+            account_keys: Some(vec![acc1.to_string()]),
+            options: Some(GetPriorityFeeEstimateOptions {
+                half_life_slots: Some(0),
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+        assert!(result.is_err());
+
+        // the v1 algorithm has no time-decayed variant
+        let result = server.get_priority_fee_estimate_v1(GetPriorityFeeEstimateRequest {
+            account_keys: Some(vec![acc1.to_string()]),
+            options: Some(GetPriorityFeeEstimateOptions {
+                half_life_slots: Some(3),
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+        assert!(result.is_err());
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_forecast() {
+        prep_statsd();
+
+        let acc1 = Pubkey::new_unique();
+        let tracker = PriorityFeeTracker::new(150);
+        // fees rising by 1000 every slot
+        for slot in 1..=20 {
+            tracker.push_priority_fee_for_txn(slot, vec![acc1], slot * 1_000, false);
+        }
+
+        let server = AtlasPriorityFeeEstimator {
+            priority_fee_tracker: Arc::new(tracker),
+            rpc_client: None,
+            max_lookback_slots: 150,
+            recommended_policies: Default::default(),
+            runtime_config: Default::default(),
+            ingest_status: Default::default(),
+            estimate_cache: Default::default(),
+        };
+
+        let result = server.get_priority_fee_estimate(GetPriorityFeeEstimateRequest {
+            account_keys: Some(vec![acc1.to_string()]),
+            options: Some(GetPriorityFeeEstimateOptions {
+                forecast: Some(2),
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+        let forecast = result.unwrap().forecast.unwrap();
+        assert_eq!(forecast.len(), 2);
+        assert_eq!(forecast[0].step, 1);
+        assert_eq!(forecast[0].medium, 21_000.0);
+        assert_eq!(forecast[1].medium, 22_000.0);
+
+        for forecast in [0, 11] {
+            let result = server.get_priority_fee_estimate(GetPriorityFeeEstimateRequest {
+                account_keys: Some(vec![acc1.to_string()]),
+                options: Some(GetPriorityFeeEstimateOptions {
+                    forecast: Some(forecast),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            });
+            assert!(result.is_err());
+        }
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_outlier_filter() {
+        prep_statsd();
+
+        let acc1 = Pubkey::new_unique();
+        let tracker = PriorityFeeTracker::new(150);
+        for fee in 1..=99 {
+            tracker.push_priority_fee_for_txn(1, vec![acc1], fee * 1_000, false);
+        }
+        tracker.push_priority_fee_for_txn(1, vec![acc1], 2_000_000_000, false);
+
+        let server = AtlasPriorityFeeEstimator {
+            priority_fee_tracker: Arc::new(tracker),
+            rpc_client: None,
+            max_lookback_slots: 150,
+            recommended_policies: Default::default(),
+            runtime_config: Default::default(),
+            ingest_status: Default::default(),
+            estimate_cache: Default::default(),
+        };
+
+        let result = server.get_priority_fee_estimate(GetPriorityFeeEstimateRequest {
+            account_keys: Some(vec![acc1.to_string()]),
+            options: Some(GetPriorityFeeEstimateOptions {
+                include_all_priority_fee_levels: Some(true),
+                include_details: Some(true),
+                outlier_filter: Some(OutlierFilter::Mad(3)),
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+        let resp = result.unwrap();
+        assert_eq!(resp.priority_fee_levels.unwrap().unsafe_max, 99_000.0);
+        let details = resp.priority_fee_estimate_details.unwrap();
+        let (_, details) = details
+            .iter()
+            .find(|(key, _)| *key == acc1.to_string())
+            .unwrap();
+        assert_eq!(details.outliers_removed, 1);
+        assert_eq!(details.count, 99);
+
+        for outlier_filter in [OutlierFilter::Trim(50), OutlierFilter::Mad(0)] {
+            let result = server.get_priority_fee_estimate(GetPriorityFeeEstimateRequest {
+                account_keys: Some(vec![acc1.to_string()]),
+                options: Some(GetPriorityFeeEstimateOptions {
+                    outlier_filter: Some(outlier_filter),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            });
+            assert!(result.is_err());
+        }
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_upcoming_leader_weight() {
+        prep_statsd();
+
+        let acc1 = Pubkey::new_unique();
+        let quiet_leader = Pubkey::new_unique();
+        let busy_leader = Pubkey::new_unique();
+        // the busy leader produced slot 1 and produces the upcoming slots
+        let schedule: HashMap<Slot, Pubkey> = (1..=12)
+            .map(|slot| {
+                let leader = if (2..=4).contains(&slot) {
+                    quiet_leader
+                } else {
+                    busy_leader
+                };
+                (slot, leader)
+            })
+            .collect();
+        let tracker = PriorityFeeTracker::new_with_options(
+            150,
+            TrackerOptions {
+                leader_schedule: Some(Arc::new(schedule.clone())),
+                ..Default::default()
+            },
+        );
+        for slot in 1..=4 {
+            let fee = if slot == 1 { 1_000 } else { 10 };
+            tracker.push_priority_fee_for_txn(slot, vec![acc1], fee, false);
+            tracker.push_slot_leader(slot, schedule[&slot]);
+            tracker.complete_slot(slot);
+        }
+
+        let server = AtlasPriorityFeeEstimator {
+            priority_fee_tracker: Arc::new(tracker),
+            rpc_client: None,
+            max_lookback_slots: 150,
+            recommended_policies: Default::default(),
+            runtime_config: Default::default(),
+            ingest_status: Default::default(),
+            estimate_cache: Default::default(),
+        };
+
+        // slot 1 is outside of the lookback window
+        let request = |upcoming_leader_weight| GetPriorityFeeEstimateRequest {
+            options: Some(GetPriorityFeeEstimateOptions {
+                include_all_priority_fee_levels: Some(true),
+                evaluate_empty_slot_as_zero: Some(false),
+                upcoming_leader_weight,
+                lookback_slots: Some(2),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let levels = server
+            .get_priority_fee_estimate(request(None))
+            .unwrap()
+            .priority_fee_levels
+            .unwrap();
+        assert_eq!(levels.medium, 10.0);
+
+        // the recorded slot of the busy leader counts 4 times as much as each window slot
+        let levels = server
+            .get_priority_fee_estimate(request(Some(4)))
+            .unwrap()
+            .priority_fee_levels
+            .unwrap();
+        assert_eq!(levels.medium, 670.0);
+        assert!(server.get_priority_fee_estimate(request(Some(0))).is_err());
+
+        // account estimates are not made of the fees in the leader fee history
+        let mut account_request = request(Some(4));
+        account_request.account_keys = Some(vec![acc1.to_string()]);
+        assert!(server.get_priority_fee_estimate(account_request).is_err());
+        let mut vote_request = request(Some(4));
+        if let Some(options) = vote_request.options.as_mut() {
+            options.include_vote = Some(true);
+        }
+        assert!(server.get_priority_fee_estimate(vote_request).is_err());
+
+        let history = server
+            .get_leader_fee_history(GetLeaderFeeHistoryRequest {
+                leader: quiet_leader.to_string(),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(history.len(), 3);
+        assert_eq!(history[0].estimates.medium, 10.0);
+        assert!(server
+            .get_leader_fee_history(GetLeaderFeeHistoryRequest {
+                leader: "not a pubkey".to_string(),
+                ..Default::default()
+            })
+            .is_err());
+
+        // without a leader schedule there are no upcoming leaders
+        let server = AtlasPriorityFeeEstimator {
+            priority_fee_tracker: Arc::new(PriorityFeeTracker::new(150)),
+            rpc_client: None,
+            max_lookback_slots: 150,
+            recommended_policies: Default::default(),
+            runtime_config: Default::default(),
+            ingest_status: Default::default(),
+            estimate_cache: Default::default(),
+        };
+        assert!(server.get_priority_fee_estimate(request(Some(4))).is_err());
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_payer_dedup() {
+        prep_statsd();
+
+        let acc1 = Pubkey::new_unique();
+        let bot = Pubkey::new_unique();
+        let tracker = PriorityFeeTracker::new(150);
+        for fee in [100, 200, 300, 400] {
+            tracker.push_payer_priority_fee_for_txn(
+                1,
+                Some(Pubkey::new_unique()),
+                vec![acc1],
+                fee,
+                false,
+            );
+        }
+        for _ in 0..100 {
+            tracker.push_payer_priority_fee_for_txn(1, Some(bot), vec![acc1], 1_000_000, false);
+        }
+
+        let server = AtlasPriorityFeeEstimator {
+            priority_fee_tracker: Arc::new(tracker),
+            rpc_client: None,
+            max_lookback_slots: 150,
+            recommended_policies: Default::default(),
+            runtime_config: Default::default(),
+            ingest_status: Default::default(),
+            estimate_cache: Default::default(),
+        };
+
+        let request = |payer_dedup| GetPriorityFeeEstimateRequest {
+            account_keys: Some(vec![acc1.to_string()]),
+            options: Some(GetPriorityFeeEstimateOptions {
+                include_all_priority_fee_levels: Some(true),
+                include_details: Some(true),
+                payer_dedup,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let levels = server
+            .get_priority_fee_estimate(request(None))
+            .unwrap()
+            .priority_fee_levels
+            .unwrap();
+        assert_eq!(levels.medium, 1_000_000.0);
+
+        let resp = server
+            .get_priority_fee_estimate(request(Some(PayerDedup::Collapse)))
+            .unwrap();
+        assert_eq!(resp.priority_fee_levels.unwrap().medium, 300.0);
+        assert_eq!(resp.slot_summaries.unwrap()[0].payer_count, 5);
+
+        let result = server.get_priority_fee_estimate(request(Some(PayerDedup::Cap(0))));
+        assert!(result.is_err());
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_aggregation() {
+        prep_statsd();
+
+        let hot = Pubkey::new_unique();
+        let quiet = Pubkey::new_unique();
+        let other = Pubkey::new_unique();
+        let tracker = PriorityFeeTracker::new(150);
+        for _ in 0..3 {
+            tracker.push_priority_fee_for_txn(1, vec![hot], 300, false);
+        }
+        tracker.push_priority_fee_for_txn(1, vec![quiet], 100, false);
+        tracker.push_priority_fee_for_txn(1, vec![other], 1_000_000, false);
+
+        let server = AtlasPriorityFeeEstimator {
+            priority_fee_tracker: Arc::new(tracker),
+            rpc_client: None,
+            max_lookback_slots: 150,
+            recommended_policies: Default::default(),
+            runtime_config: Default::default(),
+            ingest_status: Default::default(),
+            estimate_cache: Default::default(),
+        };
+
+        let request = |aggregation| GetPriorityFeeEstimateRequest {
+            account_keys: Some(vec![hot.to_string(), quiet.to_string()]),
+            options: Some(GetPriorityFeeEstimateOptions {
+                include_all_priority_fee_levels: Some(true),
+                aggregation,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let medium = |aggregation| {
+            server
+                .get_priority_fee_estimate(request(aggregation))
+                .unwrap()
+                .priority_fee_levels
+                .unwrap()
+                .medium
+        };
+
+        // the unrelated expensive transaction only shows in the global fees
+        assert_eq!(medium(None), 300.0);
+        assert_eq!(medium(None), medium(Some(Aggregation::Max)));
+        let levels = server
+            .get_priority_fee_estimate(request(None))
+            .unwrap()
+            .priority_fee_levels
+            .unwrap();
+        assert_eq!(levels.unsafe_max, 1_000_000.0);
+        let levels = server
+            .get_priority_fee_estimate(request(Some(Aggregation::MaxExcludingGlobal)))
+            .unwrap()
+            .priority_fee_levels
+            .unwrap();
+        assert_eq!(levels.unsafe_max, 300.0);
+        assert_eq!(medium(Some(Aggregation::WeightedMean)), 250.0);
+        assert_eq!(medium(Some(Aggregation::MostContended)), 300.0);
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_min_samples() {
+        prep_statsd();
+
+        let busy = Pubkey::new_unique();
+        let quiet = Pubkey::new_unique();
+        let tracker = PriorityFeeTracker::new(150);
+        for fee in 1..=20 {
+            tracker.push_priority_fee_for_txn(1, vec![busy], fee * 100, false);
+        }
+        for _ in 0..3 {
+            tracker.push_priority_fee_for_txn(1, vec![quiet], 1_000_000, false);
+        }
+
+        let server = AtlasPriorityFeeEstimator {
+            priority_fee_tracker: Arc::new(tracker),
+            rpc_client: None,
+            max_lookback_slots: 150,
+            recommended_policies: Default::default(),
+            runtime_config: Default::default(),
+            ingest_status: Default::default(),
+            estimate_cache: Default::default(),
+        };
+
+        let request = |min_samples| GetPriorityFeeEstimateRequest {
+            account_keys: Some(vec![busy.to_string(), quiet.to_string()]),
+            options: Some(GetPriorityFeeEstimateOptions {
+                include_all_priority_fee_levels: Some(true),
+                include_details: Some(true),
+                aggregation: Some(Aggregation::MaxExcludingGlobal),
+                min_samples,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let resp = server.get_priority_fee_estimate(request(None)).unwrap();
+        assert_eq!(resp.priority_fee_levels.unwrap().medium, 1_000_000.0);
+        let details = resp.priority_fee_estimate_details.unwrap();
+        let (_, quiet_details) = details
+            .iter()
+            .find(|(key, _)| *key == quiet.to_string())
+            .unwrap();
+        assert_eq!(quiet_details.fallback, None);
+        assert_eq!(quiet_details.count, 3);
+        let interval = quiet_details.confidence_interval.as_ref().unwrap();
+        assert_eq!(interval.lower.medium, 1_000_000.0);
+        assert_eq!(interval.upper.medium, 1_000_000.0);
+        assert_eq!(interval.resample_size, 3);
+        let (_, busy_details) = details
+            .iter()
+            .find(|(key, _)| *key == busy.to_string())
+            .unwrap();
+        let interval = busy_details.confidence_interval.as_ref().unwrap();
+        assert!(interval.lower.medium < interval.upper.medium);
+
+        // the quiet account falls back to the global fees
+        let resp = server.get_priority_fee_estimate(request(Some(10))).unwrap();
+        assert_eq!(resp.priority_fee_levels.unwrap().medium, 1_200.0);
+        let details = resp.priority_fee_estimate_details.unwrap();
+        let (_, quiet_details) = details
+            .iter()
+            .find(|(key, _)| *key == quiet.to_string())
+            .unwrap();
+        assert_eq!(quiet_details.fallback.as_deref(), Some("Global"));
+        assert_eq!(quiet_details.count, 23);
+        let (_, busy_details) = details
+            .iter()
+            .find(|(key, _)| *key == busy.to_string())
+            .unwrap();
+        assert_eq!(busy_details.fallback, None);
+
+        // v1 combines the requested accounts before applying the fallback
+        let v1_request = |min_samples| GetPriorityFeeEstimateRequest {
+            account_keys: Some(vec![quiet.to_string()]),
+            options: Some(GetPriorityFeeEstimateOptions {
+                include_all_priority_fee_levels: Some(true),
+                min_samples,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let resp = server
+            .get_priority_fee_estimate_v1(v1_request(None))
+            .unwrap();
+        assert_eq!(resp.priority_fee_levels.unwrap().medium, 1_000_000.0);
+        let resp = server
+            .get_priority_fee_estimate_v1(v1_request(Some(10)))
+            .unwrap();
+        assert!(resp.priority_fee_levels.unwrap().medium < 1_000_000.0);
+
+        assert!(server.get_priority_fee_estimate(request(Some(0))).is_err());
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_congestion() {
+        prep_statsd();
+
+        let hot = Pubkey::new_unique();
+        let cold = Pubkey::new_unique();
+        let tracker = PriorityFeeTracker::new(150);
+        tracker.push_priority_fee_for_txn(1, vec![hot, cold], 100, false);
+        tracker.push_compute_units_for_txn(1, vec![hot], 6_000_000);
+        tracker.push_compute_units_for_txn(1, vec![hot, cold], 1_200_000);
+
+        let server = AtlasPriorityFeeEstimator {
+            priority_fee_tracker: Arc::new(tracker),
+            rpc_client: None,
+            max_lookback_slots: 150,
+            recommended_policies: Default::default(),
+            runtime_config: Default::default(),
+            ingest_status: Default::default(),
+            estimate_cache: Default::default(),
+        };
+
+        let resp = server
+            .get_priority_fee_estimate(GetPriorityFeeEstimateRequest {
+                account_keys: Some(vec![hot.to_string(), cold.to_string()]),
+                options: Some(GetPriorityFeeEstimateOptions {
+                    include_details: Some(true),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            })
+            .unwrap();
+        let details = resp.priority_fee_estimate_details.unwrap();
+        let congestion = |key: String| {
+            details
+                .iter()
+                .find(|(name, _)| *name == key)
+                .map(|(_, details)| details.congestion)
+                .unwrap()
+        };
+        assert_eq!(congestion(hot.to_string()), 0.6);
+        assert_eq!(congestion(cold.to_string()), 0.1);
+        assert_eq!(congestion("Global".to_string()), 0.12);
+        assert_eq!(resp.slot_summaries.unwrap()[0].block_utilization, 0.12);
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_tip_estimate() {
+        prep_statsd();
+
+        let acc1 = Pubkey::new_unique();
+        let acc2 = Pubkey::new_unique();
+        let tracker = PriorityFeeTracker::new(150);
+        tracker.push_priority_fee_for_txn(1, vec![acc1, acc2], 100, false);
+        tracker.push_tip_for_txn(1, vec![acc1], 1_000);
+        tracker.push_tip_for_txn(1, vec![acc1], 2_000);
+        tracker.push_priority_fee_for_txn(2, vec![acc2], 100, false);
+        tracker.push_tip_for_txn(2, vec![acc2], 50_000);
+        // tips of slots without fees are dropped
+        tracker.push_tip_for_txn(3, vec![acc2], 90_000);
+        assert!(tracker.slot_summary(Some(3)).is_none());
+
+        let server = AtlasPriorityFeeEstimator {
+            priority_fee_tracker: Arc::new(tracker),
+            rpc_client: None,
+            max_lookback_slots: 150,
+            recommended_policies: Default::default(),
+            runtime_config: Default::default(),
+            ingest_status: Default::default(),
+            estimate_cache: Default::default(),
+        };
+
+        let estimates = server.get_tip_estimate(None).unwrap();
+        assert_eq!(estimates.min, 1_000.0);
+        assert_eq!(estimates.unsafe_max, 50_000.0);
+
+        let estimates = server
+            .get_tip_estimate(Some(GetTipEstimateRequest {
+                account_keys: Some(vec![acc1.to_string()]),
+                ..Default::default()
+            }))
+            .unwrap();
+        assert_eq!(estimates.unsafe_max, 2_000.0);
+
+        let estimates = server
+            .get_tip_estimate(Some(GetTipEstimateRequest {
+                lookback_slots: Some(1),
+                ..Default::default()
+            }))
+            .unwrap();
+        assert_eq!(estimates.min, 50_000.0);
+
+        let result = server.get_tip_estimate(Some(GetTipEstimateRequest {
+            lookback_slots: Some(0),
+            ..Default::default()
+        }));
+        assert!(result.is_err());
+
+        // tips do not count as fee transactions
+        let summary = server.get_slot_summary(None).unwrap();
+        assert_eq!(summary.tip_count, 1);
+        assert_eq!(summary.non_vote_transaction_count, 1);
     }
 
     #[test]
@@ -648,11 +2424,12 @@ mod tests {
             (r#"{"accountkeys": null}"#, "unknown field `accountkeys`, expected one of `transaction`, `accountKeys`, `options` at line 1 column 15"),
             (r#"{"accountKeys": [1, 2]}"#, "invalid type: integer `1`, expected a string at line 1 column 19"),
             (r#"{"option": null}"#, "unknown field `option`, expected one of `transaction`, `accountKeys`, `options` at line 1 column 10"),
-            (r#"{"options": {"transaction_encoding":null}}"#, "unknown field `transaction_encoding`, expected one of `transactionEncoding`, `priorityLevel`, `includeAllPriorityFeeLevels`, `lookbackSlots`, `includeVote`, `recommended`, `evaluateEmptySlotAsZero`, `includeDetails` at line 1 column 36"),
+            (r#"{"options": {"transaction_encoding":null}}"#, "unknown field `transaction_encoding`, expected one of `transactionEncoding`, `priorityLevel`, `includeAllPriorityFeeLevels`, `lookbackSlots`, `lookbackSeconds`, `includeVote`, `recommended`, `evaluateEmptySlotAsZero`, `includeDetails`, `commitment`, `includeFailed`, `halfLifeSlots`, `forecast`, `outlierFilter`, `upcomingLeaderWeight`, `payerDedup`, `aggregation`, `minSamples` at line 1 column 36"),
             (r#"{"options": {"priorityLevel":"HIGH"}}"#, "unknown variant `HIGH`, expected one of `Min`, `Low`, `Medium`, `High`, `VeryHigh`, `UnsafeMax`, `Default` at line 1 column 36"),
             (r#"{"options": {"includeAllPriorityFeeLevels":"no"}}"#, "invalid type: string \"no\", expected a boolean at line 1 column 48"),
             (r#"{"options": {"lookbackSlots":"no"}}"#, "invalid type: string \"no\", expected u32 at line 1 column 34"),
             (r#"{"options": {"lookbackSlots":"-1"}}"#, "invalid type: string \"-1\", expected u32 at line 1 column 34"),
+            (r#"{"options": {"commitment":"finalized"}}"#, "unknown variant `finalized`, expected `processed` or `confirmed` at line 1 column 38"),
         ]
     }
 }
@@ -0,0 +1,79 @@
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use opentelemetry_sdk::Resource;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, EnvFilter};
+
+/// Service name attached to the exported spans.
+pub const SERVICE_NAME: &str = "atlas_priority_fee_estimator";
+
+/// Installs the global subscriber logging JSON to stdout, filtered by `env_filter`.
+///
+/// When `otlp_endpoint` is set, spans are also exported over OTLP gRPC to that endpoint. The
+/// returned provider must be shut down on exit to flush the pending spans.
+pub fn init(
+    env_filter: &str,
+    otlp_endpoint: Option<&str>,
+) -> Result<Option<SdkTracerProvider>, opentelemetry_otlp::ExporterBuildError> {
+    let provider = match otlp_endpoint {
+        Some(endpoint) => {
+            let exporter = SpanExporter::builder()
+                .with_tonic()
+                .with_endpoint(endpoint)
+                .build()?;
+            Some(
+                SdkTracerProvider::builder()
+                    .with_batch_exporter(exporter)
+                    .with_resource(Resource::builder().with_service_name(SERVICE_NAME).build())
+                    .build(),
+            )
+        }
+        None => None,
+    };
+    let otel_layer = provider
+        .as_ref()
+        .map(|provider| tracing_opentelemetry::layer().with_tracer(provider.tracer(SERVICE_NAME)));
+    tracing_subscriber::registry()
+        .with(EnvFilter::new(env_filter))
+        .with(fmt::layer().json())
+        .with(otel_layer)
+        .init();
+    Ok(provider)
+}
+
+/// Runs `future` with a subscriber exporting its spans to memory, returning its output and the
+/// finished spans.
+#[cfg(test)]
+pub(crate) async fn capture_spans<F: std::future::Future>(
+    future: F,
+) -> (F::Output, Vec<opentelemetry_sdk::trace::SpanData>) {
+    use opentelemetry_sdk::trace::InMemorySpanExporter;
+    use tracing::instrument::WithSubscriber;
+
+    let exporter = InMemorySpanExporter::default();
+    let provider = SdkTracerProvider::builder()
+        .with_simple_exporter(exporter.clone())
+        .build();
+    let subscriber = tracing_subscriber::registry()
+        .with(tracing_opentelemetry::layer().with_tracer(provider.tracer(SERVICE_NAME)));
+    let output = future.with_subscriber(subscriber).await;
+    provider.force_flush().expect("expected spans to flush");
+    let spans = exporter
+        .get_finished_spans()
+        .expect("expected finished spans");
+    (output, spans)
+}
+
+/// Returns the value of a span attribute as a string.
+#[cfg(test)]
+pub(crate) fn span_attribute(
+    span: &opentelemetry_sdk::trace::SpanData,
+    key: &str,
+) -> Option<String> {
+    span.attributes
+        .iter()
+        .find(|attribute| attribute.key.as_str() == key)
+        .map(|attribute| attribute.value.to_string())
+}